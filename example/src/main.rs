@@ -2,9 +2,9 @@ mod menu;
 
 use crate::menu::{Menu, MenuItems};
 use richamster_api::errors::RichamsterError;
-use richamster_api::models::auth::{LoginResponse, OtpLoginResponse};
 use richamster_api::models::user::TransactionsFilter;
-use richamster_api::richamster::auth::Auth;
+use richamster_api::richamster::login::{LoginFlow, LoginStep};
+use richamster_api::richamster::session::Session;
 use richamster_api::richamster::user::User;
 use std::fmt::Error;
 use std::io::BufRead;
@@ -26,13 +26,20 @@ fn setup_logger() {
     info!("Logger initialized");
 }
 
-pub struct JwtTokenStorage {
-    token: Option<String>,
+pub struct SessionStorage {
+    session: Option<Session>,
 }
 
-impl JwtTokenStorage {
-    pub fn update_token(&mut self, token: String) {
-        self.token = Some(token);
+impl SessionStorage {
+    pub fn update_session(&mut self, session: Session) {
+        self.session = Some(session);
+    }
+
+    fn user(&self) -> Result<User, RichamsterError> {
+        match &self.session {
+            Some(session) => Ok(User::with_session(session.clone())),
+            None => Err(RichamsterError::UnauthorizedAccess),
+        }
     }
 }
 
@@ -40,7 +47,7 @@ impl JwtTokenStorage {
 async fn main() -> Result<(), Error> {
     setup_logger();
 
-    let mut token_storage = JwtTokenStorage { token: None };
+    let mut session_storage = SessionStorage { session: None };
 
     loop {
         Menu::print();
@@ -59,20 +66,20 @@ async fn main() -> Result<(), Error> {
             MenuItems::Login => {
                 let login_result = login().await;
                 match login_result {
-                    Ok(token) => token_storage.update_token(token),
+                    Ok(session) => session_storage.update_session(session),
                     Err(e) => {
                         error!("Authentication error: {:?}", e)
                     }
                 }
             }
             MenuItems::UserDetails => {
-                let _ = show_user_details(&token_storage).await;
+                let _ = show_user_details(&session_storage).await;
             }
             MenuItems::UserBalance => {
-                let _ = show_user_balance(&token_storage).await;
+                let _ = show_user_balance(&session_storage).await;
             }
             MenuItems::UserTransactions => {
-                let _ = show_user_transactions(&token_storage).await;
+                let _ = show_user_transactions(&session_storage).await;
             }
             MenuItems::Quit => break,
         }
@@ -80,56 +87,38 @@ async fn main() -> Result<(), Error> {
     Ok(())
 }
 
-async fn login() -> Result<String, RichamsterError> {
+async fn login() -> Result<Session, RichamsterError> {
     println!("Enter your username: ");
     let username = read_input();
     println!("Enter your password: ");
     let password = read_input();
-    let auth = Auth::login(username, password).await;
-    match auth {
-        Ok(login_response) => match login_response {
-            LoginResponse::Jwt(token) => Ok(token),
-            LoginResponse::RequiresTwoFactor(_) => {
-                println!("Enter otp code: ");
-                let otp_code = read_input();
-                let otp = Auth::two_factor_login(otp_code).await;
-                match otp {
-                    Ok(otp_response) => match otp_response {
-                        OtpLoginResponse::Jwt(token) => Ok(token),
-                        OtpLoginResponse::Error(e) => {
-                            error!("{}", e);
-                            Err(RichamsterError::Otp(e))
-                        }
-                    },
-                    Err(e) => {
-                        error!("{:?}", e);
-                        Err(e)
-                    }
-                }
-            }
-        },
-        Err(e) => {
+    let step = LoginFlow::new()
+        .start(username, password)
+        .await
+        .inspect_err(|e| {
             error!("{:?}", e);
-            Err(e)
+        })?;
+    match step {
+        LoginStep::Authenticated(session) => Ok(session),
+        LoginStep::TwoFactorRequired(pending) => {
+            println!("Enter otp code: ");
+            let otp_code = read_input();
+            pending.complete(otp_code).await.inspect_err(|e| {
+                error!("{:?}", e);
+            })
         }
     }
 }
 
-async fn show_user_details(token_storage: &JwtTokenStorage) -> Result<(), RichamsterError> {
-    if token_storage.token.is_none() {
-        return Err(RichamsterError::UnauthorizedAccess);
-    }
-    let user = User::with_jwt_token(token_storage.token.clone().unwrap());
+async fn show_user_details(session_storage: &SessionStorage) -> Result<(), RichamsterError> {
+    let user = session_storage.user()?;
     let result = user.detail_info().await?;
     println!("{}", result);
     Ok(())
 }
 
-async fn show_user_balance(token_storage: &JwtTokenStorage) -> Result<(), RichamsterError> {
-    if token_storage.token.is_none() {
-        return Err(RichamsterError::UnauthorizedAccess);
-    }
-    let user = User::with_jwt_token(token_storage.token.clone().unwrap());
+async fn show_user_balance(session_storage: &SessionStorage) -> Result<(), RichamsterError> {
+    let user = session_storage.user()?;
     let result = user.balances(None).await?;
     for balance in result {
         println!(
@@ -140,11 +129,8 @@ async fn show_user_balance(token_storage: &JwtTokenStorage) -> Result<(), Richam
     Ok(())
 }
 
-async fn show_user_transactions(token_storage: &JwtTokenStorage) -> Result<(), RichamsterError> {
-    if token_storage.token.is_none() {
-        return Err(RichamsterError::UnauthorizedAccess);
-    }
-    let user = User::with_jwt_token(token_storage.token.clone().unwrap());
+async fn show_user_transactions(session_storage: &SessionStorage) -> Result<(), RichamsterError> {
+    let user = session_storage.user()?;
     let result = user
         .transactions_list(TransactionsFilter {
             currency: None,
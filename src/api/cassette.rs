@@ -0,0 +1,316 @@
+use crate::api::trace;
+use crate::api::transport::{Transport, TransportRequest, TransportResponse};
+use crate::errors::RichamsterError;
+use async_trait::async_trait;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use reqwest::{Method, StatusCode};
+use serde_derive::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Mutex;
+
+#[derive(Serialize, Deserialize, PartialEq, Clone, Debug)]
+pub struct RecordedRequest {
+    pub method: String,
+    /// Path and query, without scheme and host, so a cassette replays against any base URL.
+    pub url: String,
+    pub headers: Vec<(String, String)>,
+    pub body: Option<String>,
+}
+
+impl RecordedRequest {
+    fn new(request: &TransportRequest) -> Self {
+        Self {
+            method: request.method.to_string(),
+            url: path_and_query(request),
+            headers: trace::redact_headers(&request.headers),
+            body: request.body.as_deref().map(sanitize_body),
+        }
+    }
+
+    fn matches(&self, other: &RecordedRequest) -> bool {
+        self.method == other.method && self.url == other.url && self.body == other.body
+    }
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Clone, Debug)]
+pub struct RecordedResponse {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+}
+
+impl RecordedResponse {
+    fn new(response: &TransportResponse) -> Self {
+        Self {
+            status: response.status().as_u16(),
+            headers: trace::redact_headers(response.headers()),
+            body: sanitize_body(response.text()),
+        }
+    }
+
+    fn to_response(&self) -> TransportResponse {
+        let mut headers = HeaderMap::new();
+        for (name, value) in &self.headers {
+            if let (Ok(name), Ok(value)) = (
+                HeaderName::from_bytes(name.as_bytes()),
+                HeaderValue::from_str(value),
+            ) {
+                headers.append(name, value);
+            }
+        }
+        let status = StatusCode::from_u16(self.status).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+        TransportResponse::new(status, self.body.clone()).with_headers(headers)
+    }
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Clone, Debug)]
+pub struct Interaction {
+    pub request: RecordedRequest,
+    pub response: RecordedResponse,
+}
+
+/// Recorded request/response pairs, stored as pretty printed JSON. Credentials,
+/// cookie headers, passwords, PIN codes and tokens are redacted before recording.
+#[derive(Serialize, Deserialize, PartialEq, Clone, Debug, Default)]
+pub struct Cassette {
+    pub interactions: Vec<Interaction>,
+}
+
+impl Cassette {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, RichamsterError> {
+        Ok(serde_json::from_str(&fs::read_to_string(path)?)?)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), RichamsterError> {
+        fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+}
+
+fn path_and_query(request: &TransportRequest) -> String {
+    match request.url.query() {
+        Some(query) => format!("{}?{}", request.url.path(), query),
+        None => request.url.path().to_owned(),
+    }
+}
+
+fn sanitize_body(body: &str) -> String {
+    match serde_json::from_str::<Value>(body) {
+        Ok(mut value) => {
+            trace::redact_value(&mut value);
+            value.to_string()
+        }
+        Err(_) => body.to_owned(),
+    }
+}
+
+/// Forwards requests to another transport and appends every interaction to a
+/// cassette file, which is rewritten after each response.
+#[derive(Debug)]
+pub struct RecordingTransport {
+    inner: Box<dyn Transport>,
+    path: PathBuf,
+    cassette: Mutex<Cassette>,
+}
+
+impl RecordingTransport {
+    pub fn new(inner: impl Transport + 'static, path: impl Into<PathBuf>) -> Self {
+        Self {
+            inner: Box::new(inner),
+            path: path.into(),
+            cassette: Mutex::new(Cassette::default()),
+        }
+    }
+
+    pub fn cassette(&self) -> Cassette {
+        self.cassette.lock().unwrap().clone()
+    }
+}
+
+#[async_trait]
+impl Transport for RecordingTransport {
+    async fn send(&self, request: TransportRequest) -> Result<TransportResponse, RichamsterError> {
+        let recorded = RecordedRequest::new(&request);
+        let response = self.inner.send(request).await?;
+        let mut cassette = self.cassette.lock().unwrap();
+        cassette.interactions.push(Interaction {
+            request: recorded,
+            response: RecordedResponse::new(&response),
+        });
+        cassette.save(&self.path)?;
+        Ok(response)
+    }
+}
+
+/// Serves responses from a cassette. Requests are matched by method, path,
+/// query and body; matching interactions are served in recorded order and the
+/// last one is repeated. A request missing from the cassette fails with
+/// [`RichamsterError::UnexpectedRequest`].
+#[derive(Debug)]
+pub struct ReplayTransport {
+    interactions: Vec<Interaction>,
+    served: Mutex<Vec<bool>>,
+}
+
+impl ReplayTransport {
+    pub fn new(cassette: Cassette) -> Self {
+        Self {
+            served: Mutex::new(vec![false; cassette.interactions.len()]),
+            interactions: cassette.interactions,
+        }
+    }
+
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, RichamsterError> {
+        Ok(Self::new(Cassette::load(path)?))
+    }
+}
+
+#[async_trait]
+impl Transport for ReplayTransport {
+    async fn send(&self, request: TransportRequest) -> Result<TransportResponse, RichamsterError> {
+        let recorded = RecordedRequest::new(&request);
+        let mut served = self.served.lock().unwrap();
+        let matching: Vec<usize> = (0..self.interactions.len())
+            .filter(|i| self.interactions[*i].request.matches(&recorded))
+            .collect();
+        let index = matching
+            .iter()
+            .find(|i| !served[**i])
+            .or(matching.last())
+            .copied()
+            .ok_or_else(|| {
+                RichamsterError::UnexpectedRequest(
+                    Method::from_str(&recorded.method).unwrap_or_default(),
+                    recorded.url.clone(),
+                )
+            })?;
+        served[index] = true;
+        Ok(self.interactions[index].response.to_response())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::api::client::ClientConfig;
+    use crate::api::transport::InMemoryTransport;
+    use crate::models::user::{TransactionsFilter, UserOrdersFilter};
+    use crate::richamster::auth::Auth;
+    use crate::richamster::login::{LoginFlow, LoginStep};
+    use crate::richamster::user::User;
+    use reqwest::header::SET_COOKIE;
+
+    const TRANSACTIONS: &str = r#"{"count":1,"next":null,"prev":null,"page_count":1,"results":[{"created_at":"1700000000","closed_at":1700000050.5,"status":"confirmed","type":"Replenish","currency":"UAH","sum":"25000.00","fee":"0.00","balance":"25000.00","hash":"","explorer_link":""}]}"#;
+
+    fn temp_cassette(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("richamster-{}-{}.json", name, std::process::id()))
+    }
+
+    fn filter() -> TransactionsFilter {
+        TransactionsFilter {
+            currency: None,
+            transaction_type: None,
+            closed_at_gte: None,
+            closed_at_lte: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn recorded_interactions_replay_offline() {
+        let path = temp_cassette("replay");
+        let live = InMemoryTransport::new();
+        live.respond(
+            Method::GET,
+            "user/transactions/",
+            TransportResponse::new(StatusCode::OK, TRANSACTIONS),
+        )
+        .respond(
+            Method::POST,
+            "login/",
+            TransportResponse::new(
+                StatusCode::CREATED,
+                r#"{"access":"a.b.c","refresh":"d.e.f"}"#,
+            ),
+        );
+        let recorder = ClientConfig::new()
+            .transport(std::sync::Arc::new(RecordingTransport::new(live, &path)))
+            .build()
+            .unwrap();
+        let recorded = User::with_jwt_token("secret-jwt".to_owned())
+            .client(recorder.clone())
+            .transactions_list(filter())
+            .await
+            .unwrap();
+        Auth::new()
+            .client(recorder)
+            .login("a@b.c", "hunter2")
+            .await
+            .unwrap();
+
+        let content = fs::read_to_string(&path).unwrap();
+        assert!(!content.contains("secret-jwt"));
+        assert!(!content.contains("hunter2"));
+        assert!(!content.contains("a.b.c"));
+
+        let replay = ClientConfig::new()
+            .transport(std::sync::Arc::new(
+                ReplayTransport::from_file(&path).unwrap(),
+            ))
+            .build()
+            .unwrap();
+        let user = User::with_jwt_token("other".to_owned()).client(replay);
+        assert_eq!(user.transactions_list(filter()).await.unwrap(), recorded);
+        assert!(matches!(
+            user.orders(UserOrdersFilter::default()).await,
+            Err(RichamsterError::UnexpectedRequest(Method::GET, _))
+        ));
+        fs::remove_file(path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn login_session_state_is_not_recorded() {
+        let path = temp_cassette("login-state");
+        let mut headers = HeaderMap::new();
+        headers.append(
+            SET_COOKIE,
+            HeaderValue::from_static("sessionid=cookie-secret; HttpOnly; Path=/"),
+        );
+        let live = InMemoryTransport::new();
+        live.respond(
+            Method::POST,
+            "login/",
+            TransportResponse::new(StatusCode::OK, r#"{"ephemeral_token":"ephemeral-secret"}"#)
+                .with_headers(headers),
+        )
+        .respond(
+            Method::POST,
+            "two-factor-login/",
+            TransportResponse::new(
+                StatusCode::CREATED,
+                r#"{"access":"a.b.c","refresh":"d.e.f"}"#,
+            ),
+        );
+        let recorder = ClientConfig::new()
+            .transport(std::sync::Arc::new(RecordingTransport::new(live, &path)))
+            .build()
+            .unwrap();
+        let step = LoginFlow::new()
+            .client(recorder)
+            .start("a@b.c", "hunter2")
+            .await
+            .unwrap();
+        let LoginStep::TwoFactorRequired(pending) = step else {
+            panic!("expected the two factor step");
+        };
+        pending.complete("123456").await.unwrap();
+
+        let content = fs::read_to_string(&path).unwrap();
+        assert!(!content.contains("cookie-secret"));
+        assert!(!content.contains("ephemeral-secret"));
+        assert!(content.contains("set-cookie"));
+        fs::remove_file(path).unwrap();
+    }
+}
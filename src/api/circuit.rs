@@ -0,0 +1,303 @@
+use crate::api::ApiGroup;
+use crate::errors::RichamsterError;
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// When a circuit opens and how long it stays open.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BreakerSettings {
+    consecutive_failures: u32,
+    failure_ratio: Option<(f64, usize)>,
+    cool_down: Duration,
+}
+
+impl Default for BreakerSettings {
+    fn default() -> Self {
+        Self {
+            consecutive_failures: 5,
+            failure_ratio: None,
+            cool_down: Duration::from_secs(30),
+        }
+    }
+}
+
+impl BreakerSettings {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Opens the circuit after `failures` failed requests in a row.
+    pub fn consecutive_failures(mut self, failures: u32) -> Self {
+        self.consecutive_failures = failures.max(1);
+        self
+    }
+
+    /// Also opens the circuit when at least `ratio` of the last `window`
+    /// requests failed.
+    pub fn failure_ratio(mut self, ratio: f64, window: usize) -> Self {
+        self.failure_ratio = Some((ratio.clamp(0.0, 1.0), window.max(1)));
+        self
+    }
+
+    /// How long an open circuit rejects requests before letting a probe through.
+    pub fn cool_down(mut self, cool_down: Duration) -> Self {
+        self.cool_down = cool_down;
+        self
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, strum_macros::Display)]
+pub enum CircuitState {
+    /// Requests flow normally.
+    Closed,
+    /// Requests fail fast with [`RichamsterError::CircuitOpen`].
+    Open,
+    /// The cool-down elapsed, a single probe request decides whether the
+    /// circuit closes or opens again.
+    HalfOpen,
+}
+
+#[derive(Debug)]
+struct Circuit {
+    settings: BreakerSettings,
+    state: CircuitState,
+    consecutive_failures: u32,
+    outcomes: VecDeque<bool>,
+    opened_at: Instant,
+    probe_started: Option<Instant>,
+}
+
+impl Circuit {
+    fn new(settings: BreakerSettings) -> Self {
+        Self {
+            settings,
+            state: CircuitState::Closed,
+            consecutive_failures: 0,
+            outcomes: VecDeque::new(),
+            opened_at: Instant::now(),
+            probe_started: None,
+        }
+    }
+
+    fn state(&self, now: Instant) -> CircuitState {
+        match self.state {
+            CircuitState::Open if self.cooled_down(now) => CircuitState::HalfOpen,
+            state => state,
+        }
+    }
+
+    fn cooled_down(&self, now: Instant) -> bool {
+        now.saturating_duration_since(self.opened_at) >= self.settings.cool_down
+    }
+
+    /// Whether a request may be sent. A half-open circuit admits one probe at a
+    /// time; a probe that never reports back is replaced after another cool-down.
+    fn try_acquire(&mut self, now: Instant) -> bool {
+        match self.state(now) {
+            CircuitState::Closed => true,
+            CircuitState::Open => false,
+            CircuitState::HalfOpen => {
+                let probing = self.probe_started.is_some_and(|started| {
+                    now.saturating_duration_since(started) < self.settings.cool_down
+                });
+                if !probing {
+                    self.state = CircuitState::HalfOpen;
+                    self.probe_started = Some(now);
+                }
+                !probing
+            }
+        }
+    }
+
+    fn record(&mut self, now: Instant, success: bool) {
+        match self.state {
+            CircuitState::HalfOpen if success => self.close(),
+            CircuitState::HalfOpen => self.open(now),
+            // Responses to requests sent before the circuit opened.
+            CircuitState::Open => {}
+            CircuitState::Closed => {
+                self.consecutive_failures = if success {
+                    0
+                } else {
+                    self.consecutive_failures + 1
+                };
+                if self.consecutive_failures >= self.settings.consecutive_failures
+                    || self.ratio_exceeded(success)
+                {
+                    self.open(now);
+                }
+            }
+        }
+    }
+
+    fn ratio_exceeded(&mut self, success: bool) -> bool {
+        let Some((ratio, window)) = self.settings.failure_ratio else {
+            return false;
+        };
+        self.outcomes.push_back(success);
+        while self.outcomes.len() > window {
+            self.outcomes.pop_front();
+        }
+        let failures = self.outcomes.iter().filter(|success| !**success).count();
+        self.outcomes.len() == window && failures as f64 >= ratio * window as f64
+    }
+
+    fn open(&mut self, now: Instant) {
+        self.state = CircuitState::Open;
+        self.opened_at = now;
+        self.probe_started = None;
+    }
+
+    fn close(&mut self) {
+        self.state = CircuitState::Closed;
+        self.consecutive_failures = 0;
+        self.outcomes.clear();
+        self.probe_started = None;
+    }
+}
+
+/// Independent circuit per [`ApiGroup`]. Transport failures and `5xx` responses
+/// count as failures; once a circuit opens, requests of its group fail with
+/// [`RichamsterError::CircuitOpen`] until the cool-down elapses and a probe
+/// request succeeds.
+#[derive(Debug, Default)]
+pub struct CircuitBreaker {
+    settings: BreakerSettings,
+    overrides: HashMap<ApiGroup, BreakerSettings>,
+    circuits: Mutex<HashMap<ApiGroup, Circuit>>,
+}
+
+impl CircuitBreaker {
+    /// Breaker applying `settings` to every group.
+    pub fn new(settings: BreakerSettings) -> Self {
+        Self {
+            settings,
+            ..Self::default()
+        }
+    }
+
+    /// Uses `settings` for `group` instead of the shared ones.
+    pub fn group(mut self, group: ApiGroup, settings: BreakerSettings) -> Self {
+        self.overrides.insert(group, settings);
+        self
+    }
+
+    pub fn state(&self, group: ApiGroup) -> CircuitState {
+        self.circuits
+            .lock()
+            .unwrap()
+            .get(&group)
+            .map_or(CircuitState::Closed, |circuit| {
+                circuit.state(Instant::now())
+            })
+    }
+
+    /// Closes the circuit of `group` regardless of its state.
+    pub fn reset(&self, group: ApiGroup) {
+        self.circuits.lock().unwrap().remove(&group);
+    }
+
+    pub fn acquire(&self, group: ApiGroup) -> Result<(), RichamsterError> {
+        let mut circuits = self.circuits.lock().unwrap();
+        let admitted = circuits
+            .get_mut(&group)
+            .is_none_or(|circuit| circuit.try_acquire(Instant::now()));
+        if admitted {
+            Ok(())
+        } else {
+            Err(RichamsterError::CircuitOpen(group))
+        }
+    }
+
+    pub fn on_success(&self, group: ApiGroup) {
+        self.record(group, true);
+    }
+
+    pub fn on_failure(&self, group: ApiGroup) {
+        self.record(group, false);
+    }
+
+    fn record(&self, group: ApiGroup, success: bool) {
+        let settings = self.overrides.get(&group).copied().unwrap_or(self.settings);
+        let mut circuits = self.circuits.lock().unwrap();
+        let circuit = circuits
+            .entry(group)
+            .or_insert_with(|| Circuit::new(settings));
+        let before = circuit.state;
+        circuit.record(Instant::now(), success);
+        match (before, circuit.state) {
+            (CircuitState::Open, _) => {}
+            (_, CircuitState::Open) => tracing::warn!(%group, "circuit opened"),
+            (CircuitState::HalfOpen, CircuitState::Closed) => {
+                tracing::info!(%group, "circuit closed")
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn opens_after_consecutive_failures_and_probes_after_cool_down() {
+        let mut circuit = Circuit::new(
+            BreakerSettings::new()
+                .consecutive_failures(2)
+                .cool_down(Duration::from_secs(10)),
+        );
+        let now = Instant::now();
+        circuit.record(now, false);
+        circuit.record(now, true);
+        circuit.record(now, false);
+        assert_eq!(circuit.state(now), CircuitState::Closed);
+        circuit.record(now, false);
+        assert_eq!(circuit.state(now), CircuitState::Open);
+        assert!(!circuit.try_acquire(now));
+
+        let later = now + Duration::from_secs(10);
+        assert_eq!(circuit.state(later), CircuitState::HalfOpen);
+        assert!(circuit.try_acquire(later));
+        assert!(!circuit.try_acquire(later));
+        circuit.record(later, false);
+        assert_eq!(circuit.state(later), CircuitState::Open);
+
+        let probe = later + Duration::from_secs(10);
+        assert!(circuit.try_acquire(probe));
+        circuit.record(probe, true);
+        assert_eq!(circuit.state(probe), CircuitState::Closed);
+        assert!(circuit.try_acquire(probe));
+    }
+
+    #[test]
+    fn opens_on_failure_ratio() {
+        let mut circuit = Circuit::new(
+            BreakerSettings::new()
+                .consecutive_failures(10)
+                .failure_ratio(0.5, 4),
+        );
+        let now = Instant::now();
+        for success in [false, true, false] {
+            circuit.record(now, success);
+        }
+        assert_eq!(circuit.state(now), CircuitState::Closed);
+        circuit.record(now, true);
+        assert_eq!(circuit.state(now), CircuitState::Open);
+    }
+
+    #[test]
+    fn groups_are_independent() {
+        let breaker = CircuitBreaker::new(BreakerSettings::new().consecutive_failures(1));
+        breaker.on_failure(ApiGroup::PrivateData);
+        assert!(matches!(
+            breaker.acquire(ApiGroup::PrivateData),
+            Err(RichamsterError::CircuitOpen(ApiGroup::PrivateData))
+        ));
+        assert_eq!(breaker.state(ApiGroup::PrivateData), CircuitState::Open);
+        assert!(breaker.acquire(ApiGroup::PublicData).is_ok());
+        breaker.reset(ApiGroup::PrivateData);
+        assert_eq!(breaker.state(ApiGroup::PrivateData), CircuitState::Closed);
+    }
+}
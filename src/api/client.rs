@@ -0,0 +1,884 @@
+use crate::api::circuit::CircuitBreaker;
+use crate::api::metrics::{MetricsHook, RequestMetrics};
+use crate::api::rate_limit::RateLimiter;
+use crate::api::retry::{self, RetryPolicy};
+use crate::api::signing::SigningScheme;
+#[cfg(feature = "rustls-tls")]
+use crate::api::tls::CertificatePin;
+use crate::api::tls::{RootCertificate, TlsBackend};
+use crate::api::trace;
+use crate::api::transport::{ReqwestTransport, Transport, TransportRequest, TransportResponse};
+use crate::api::{Api, DEFAULT_BASE_URL, RequestData, RequestPath};
+use crate::errors::RichamsterError;
+use reqwest::header::HeaderMap;
+use reqwest::{Certificate, Client, Proxy, StatusCode};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tracing::{Instrument, Level};
+use url::Url;
+
+tokio::task_local! {
+    static TIMEOUT_OVERRIDE: Duration;
+}
+
+/// Runs `future` with every request it sends bounded by `timeout` instead of the
+/// client timeout.
+///
+/// ```no_run
+/// # use richamster_api::api::client::with_timeout;
+/// # use richamster_api::api::token::{CurrencyPair, Token};
+/// # use richamster_api::models::exchange::OrderBookFilter;
+/// # use richamster_api::richamster::exchange::Exchange;
+/// # use std::time::Duration;
+/// # async fn run(exchange: Exchange) {
+/// let filter = OrderBookFilter::new(CurrencyPair::new(Token::BTC, Token::UAH));
+/// let book = with_timeout(Duration::from_millis(500), exchange.order_book(filter)).await;
+/// # }
+/// ```
+pub async fn with_timeout<F: Future>(timeout: Duration, future: F) -> F::Output {
+    TIMEOUT_OVERRIDE.scope(timeout, future).await
+}
+
+#[derive(Clone, Debug)]
+pub struct ClientConfig {
+    base_url: Url,
+    user_agent: Option<String>,
+    timeout: Option<Duration>,
+    connect_timeout: Option<Duration>,
+    read_timeout: Option<Duration>,
+    default_headers: HeaderMap,
+    retry_policy: RetryPolicy,
+    rate_limiter: Option<Arc<RateLimiter>>,
+    circuit_breaker: Option<Arc<CircuitBreaker>>,
+    transport: Option<Arc<dyn Transport>>,
+    metrics: Option<Arc<dyn MetricsHook>>,
+    body_log_level: Option<Level>,
+    signing_scheme: SigningScheme,
+    proxies: Vec<Proxy>,
+    no_proxy: bool,
+    root_certificates: Vec<RootCertificate>,
+    built_in_root_certs: bool,
+    #[cfg(any(feature = "native-tls", feature = "rustls-tls"))]
+    identity: Option<reqwest::Identity>,
+    tls_backend: TlsBackend,
+    #[cfg(feature = "rustls-tls")]
+    pins: Vec<CertificatePin>,
+}
+
+impl Default for ClientConfig {
+    fn default() -> Self {
+        Self {
+            base_url: DEFAULT_BASE_URL.clone(),
+            user_agent: None,
+            timeout: None,
+            connect_timeout: None,
+            read_timeout: None,
+            default_headers: HeaderMap::new(),
+            retry_policy: RetryPolicy::default(),
+            rate_limiter: None,
+            circuit_breaker: None,
+            transport: None,
+            metrics: None,
+            body_log_level: None,
+            signing_scheme: SigningScheme::default(),
+            proxies: vec![],
+            no_proxy: false,
+            root_certificates: vec![],
+            built_in_root_certs: true,
+            #[cfg(any(feature = "native-tls", feature = "rustls-tls"))]
+            identity: None,
+            tls_backend: TlsBackend::default(),
+            #[cfg(feature = "rustls-tls")]
+            pins: vec![],
+        }
+    }
+}
+
+impl ClientConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Base URL every endpoint path is joined to. A missing trailing slash is
+    /// added, otherwise `Url::join` would drop the last path segment.
+    pub fn base_url(mut self, mut base_url: Url) -> Self {
+        if !base_url.path().ends_with('/') {
+            let path = format!("{}/", base_url.path());
+            base_url.set_path(&path);
+        }
+        self.base_url = base_url;
+        self
+    }
+
+    pub fn user_agent(mut self, user_agent: impl Into<String>) -> Self {
+        self.user_agent = Some(user_agent.into());
+        self
+    }
+
+    /// Upper bound for a single attempt, from sending the request until the whole
+    /// response is read. Applies to any transport and can be overridden for a call
+    /// with [`with_timeout`].
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    pub fn connect_timeout(mut self, connect_timeout: Duration) -> Self {
+        self.connect_timeout = Some(connect_timeout);
+        self
+    }
+
+    /// Maximum time between two reads of the response.
+    pub fn read_timeout(mut self, read_timeout: Duration) -> Self {
+        self.read_timeout = Some(read_timeout);
+        self
+    }
+
+    pub fn default_headers(mut self, headers: HeaderMap) -> Self {
+        self.default_headers = headers;
+        self
+    }
+
+    pub fn retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    /// Limiter shared by all clones of the built client.
+    pub fn rate_limiter(mut self, rate_limiter: RateLimiter) -> Self {
+        self.rate_limiter = Some(Arc::new(rate_limiter));
+        self
+    }
+
+    /// Breaker shared by all clones of the built client.
+    pub fn circuit_breaker(mut self, circuit_breaker: CircuitBreaker) -> Self {
+        self.circuit_breaker = Some(Arc::new(circuit_breaker));
+        self
+    }
+
+    /// Replaces the default reqwest based transport. User agent, connect and read
+    /// timeouts, proxies and TLS settings only apply to the default transport.
+    pub fn transport(mut self, transport: Arc<dyn Transport>) -> Self {
+        self.transport = Some(transport);
+        self
+    }
+
+    /// Hook notified with timings, sizes and outcome of every request.
+    pub fn metrics(mut self, hook: Arc<dyn MetricsHook>) -> Self {
+        self.metrics = Some(hook);
+        self
+    }
+
+    /// Logs request and response bodies at `level`, with passwords, PIN codes
+    /// and tokens redacted. Bodies are not logged by default.
+    pub fn log_bodies(mut self, level: Level) -> Self {
+        self.body_log_level = Some(level);
+        self
+    }
+
+    /// How requests authenticated with an API key are signed,
+    /// [`SigningScheme::Legacy`] by default.
+    pub fn signing_scheme(mut self, signing_scheme: SigningScheme) -> Self {
+        self.signing_scheme = signing_scheme;
+        self
+    }
+
+    /// Routes requests through `proxy`. Can be called several times, the first
+    /// proxy matching a request is used. SOCKS proxies need the `socks` feature.
+    pub fn proxy(mut self, proxy: Proxy) -> Self {
+        self.proxies.push(proxy);
+        self
+    }
+
+    /// Ignores proxies configured through the environment, such as `HTTPS_PROXY`.
+    pub fn no_proxy(mut self) -> Self {
+        self.no_proxy = true;
+        self
+    }
+
+    /// Trusts `certificate` in addition to the built-in roots.
+    pub fn add_root_certificate(mut self, certificate: RootCertificate) -> Self {
+        self.root_certificates.push(certificate);
+        self
+    }
+
+    /// Whether the platform or webpki roots are trusted, `true` by default.
+    /// Disable it to trust only the certificates added with
+    /// [`add_root_certificate`](Self::add_root_certificate).
+    pub fn built_in_root_certs(mut self, enabled: bool) -> Self {
+        self.built_in_root_certs = enabled;
+        self
+    }
+
+    /// Client certificate presented during the TLS handshake.
+    #[cfg(any(feature = "native-tls", feature = "rustls-tls"))]
+    pub fn identity(mut self, identity: reqwest::Identity) -> Self {
+        self.identity = Some(identity);
+        self
+    }
+
+    pub fn tls_backend(mut self, tls_backend: TlsBackend) -> Self {
+        self.tls_backend = tls_backend;
+        self
+    }
+
+    /// Accepts only leaf certificates of `host` whose SHA-256 fingerprint is
+    /// one of `fingerprints`, after the usual chain validation. Pinning always
+    /// uses rustls and cannot be combined with [`identity`](Self::identity).
+    #[cfg(feature = "rustls-tls")]
+    pub fn pin_certificate(mut self, host: impl Into<String>, fingerprints: Vec<[u8; 32]>) -> Self {
+        self.pins.push(CertificatePin {
+            host: host.into(),
+            fingerprints,
+        });
+        self
+    }
+
+    pub fn build(self) -> Result<RichamsterClient, RichamsterError> {
+        RichamsterClient::with_config(self)
+    }
+}
+
+/// Handle to a configured Richamster endpoint. Cloning is cheap, the underlying
+/// connection pool is shared between clones.
+#[derive(Clone, Debug)]
+pub struct RichamsterClient {
+    transport: Arc<dyn Transport>,
+    base_url: Url,
+    timeout: Option<Duration>,
+    default_headers: HeaderMap,
+    retry_policy: RetryPolicy,
+    rate_limiter: Option<Arc<RateLimiter>>,
+    circuit_breaker: Option<Arc<CircuitBreaker>>,
+    metrics: Option<Arc<dyn MetricsHook>>,
+    body_log_level: Option<Level>,
+    signing_scheme: SigningScheme,
+}
+
+impl Default for RichamsterClient {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RichamsterClient {
+    pub fn new() -> Self {
+        Self {
+            transport: Arc::new(ReqwestTransport::default()),
+            base_url: DEFAULT_BASE_URL.clone(),
+            timeout: None,
+            default_headers: HeaderMap::new(),
+            retry_policy: RetryPolicy::default(),
+            rate_limiter: None,
+            circuit_breaker: None,
+            metrics: None,
+            body_log_level: None,
+            signing_scheme: SigningScheme::default(),
+        }
+    }
+
+    pub fn with_config(mut config: ClientConfig) -> Result<Self, RichamsterError> {
+        let transport = match config.transport.take() {
+            Some(transport) => transport,
+            None => Arc::new(ReqwestTransport::new(Self::reqwest_client(&mut config)?)),
+        };
+        Ok(Self {
+            transport,
+            base_url: config.base_url,
+            timeout: config.timeout,
+            default_headers: config.default_headers,
+            retry_policy: config.retry_policy,
+            rate_limiter: config.rate_limiter,
+            circuit_breaker: config.circuit_breaker,
+            metrics: config.metrics,
+            body_log_level: config.body_log_level,
+            signing_scheme: config.signing_scheme,
+        })
+    }
+
+    fn reqwest_client(config: &mut ClientConfig) -> Result<Client, RichamsterError> {
+        let mut builder = Client::builder();
+        if let Some(user_agent) = config.user_agent.take() {
+            builder = builder.user_agent(user_agent);
+        }
+        if let Some(connect_timeout) = config.connect_timeout {
+            builder = builder.connect_timeout(connect_timeout);
+        }
+        if let Some(read_timeout) = config.read_timeout {
+            builder = builder.read_timeout(read_timeout);
+        }
+        if config.no_proxy {
+            builder = builder.no_proxy();
+        }
+        for proxy in config.proxies.drain(..) {
+            builder = builder.proxy(proxy);
+        }
+        builder = match config.tls_backend {
+            TlsBackend::Default => builder,
+            #[cfg(feature = "native-tls")]
+            TlsBackend::NativeTls => builder.use_native_tls(),
+            #[cfg(feature = "rustls-tls")]
+            TlsBackend::Rustls => builder.use_rustls_tls(),
+        };
+        #[cfg(feature = "rustls-tls")]
+        if !config.pins.is_empty() {
+            if config.identity.is_some() {
+                return Err(RichamsterError::Tls(
+                    "certificate pinning cannot be combined with a client identity".to_owned(),
+                ));
+            }
+            let tls = crate::api::tls::pinning::client_config(
+                &config.root_certificates,
+                config.built_in_root_certs,
+                std::mem::take(&mut config.pins),
+            )?;
+            return Ok(builder.use_preconfigured_tls(tls).build()?);
+        }
+        builder = builder.tls_built_in_root_certs(config.built_in_root_certs);
+        for root in &config.root_certificates {
+            builder = builder.add_root_certificate(Certificate::from_der(root.der())?);
+        }
+        #[cfg(any(feature = "native-tls", feature = "rustls-tls"))]
+        if let Some(identity) = config.identity.take() {
+            builder = builder.identity(identity);
+        }
+        Ok(builder.build()?)
+    }
+
+    pub fn base_url(&self) -> &Url {
+        &self.base_url
+    }
+
+    /// Timeout of the current call: the [`with_timeout`] override if there is
+    /// one, otherwise the configured one.
+    pub fn timeout(&self) -> Option<Duration> {
+        TIMEOUT_OVERRIDE
+            .try_with(|timeout| *timeout)
+            .ok()
+            .or(self.timeout)
+    }
+
+    pub fn retry_policy(&self) -> &RetryPolicy {
+        &self.retry_policy
+    }
+
+    pub fn rate_limiter(&self) -> Option<&RateLimiter> {
+        self.rate_limiter.as_deref()
+    }
+
+    /// Breaker whose [`state`](CircuitBreaker::state) tells which groups fail fast.
+    pub fn circuit_breaker(&self) -> Option<&CircuitBreaker> {
+        self.circuit_breaker.as_deref()
+    }
+
+    pub fn signing_scheme(&self) -> SigningScheme {
+        self.signing_scheme
+    }
+
+    pub fn request_data(&self, api: &Api) -> RequestData {
+        api.request_data(&self.base_url)
+    }
+
+    /// Sends the request within a `richamster_request` span, retrying idempotent
+    /// ones on transient failures according to the configured [`RetryPolicy`].
+    /// Once attempts are exhausted the last response is returned unchanged so
+    /// callers map its status as usual.
+    ///
+    /// A non-idempotent request that times out fails with
+    /// [`RichamsterError::OutcomeUnknown`], as the server may have processed it.
+    pub(crate) async fn execute(
+        &self,
+        api: Api,
+        request: TransportRequest,
+    ) -> Result<TransportResponse, RichamsterError> {
+        self.execute_composed(api, request, |request| std::future::ready(Ok(request)))
+            .await
+    }
+
+    /// Like [`execute`](Self::execute), with `compose` applied to the request
+    /// before every attempt, e.g. to sign it with a fresh nonce.
+    pub(crate) async fn execute_composed<F>(
+        &self,
+        api: Api,
+        request: TransportRequest,
+        compose: impl Fn(TransportRequest) -> F,
+    ) -> Result<TransportResponse, RichamsterError>
+    where
+        F: Future<Output = Result<TransportRequest, RichamsterError>>,
+    {
+        let span = tracing::info_span!(
+            "richamster_request",
+            request_id = trace::next_request_id(),
+            endpoint = ?api,
+            method = %request.method,
+            path = request.url.path(),
+            status = tracing::field::Empty,
+            latency_ms = tracing::field::Empty,
+        );
+        let started = Instant::now();
+        let result = self
+            .execute_with_retry(api, request, compose)
+            .instrument(span.clone())
+            .await;
+        span.record("latency_ms", started.elapsed().as_millis() as u64);
+        match &result {
+            Ok(response) => {
+                span.record("status", response.status().as_u16());
+                tracing::debug!(parent: &span, status = response.status().as_u16(), "request completed");
+            }
+            Err(e) => tracing::warn!(parent: &span, error = %e, "request failed"),
+        }
+        result
+    }
+
+    async fn execute_with_retry<F>(
+        &self,
+        api: Api,
+        mut request: TransportRequest,
+        compose: impl Fn(TransportRequest) -> F,
+    ) -> Result<TransportResponse, RichamsterError>
+    where
+        F: Future<Output = Result<TransportRequest, RichamsterError>>,
+    {
+        for (name, value) in &self.default_headers {
+            if !request.headers.contains_key(name) {
+                request.headers.insert(name, value.clone());
+            }
+        }
+        if !retry::is_idempotent(&request.method) {
+            return match self.send(api, compose(request).await?, 1).await {
+                Err(RichamsterError::Timeout) => Err(RichamsterError::OutcomeUnknown(api)),
+                result => result,
+            };
+        }
+        let mut attempt = 1;
+        loop {
+            if attempt >= self.retry_policy.attempts() {
+                return self.send(api, compose(request).await?, attempt).await;
+            }
+            let attempt_request = compose(request.clone()).await?;
+            let retry_after = match self.send(api, attempt_request, attempt).await {
+                Ok(response) if retry::is_transient_status(response.status()) => {
+                    retry::retry_after(response.headers())
+                }
+                Err(RichamsterError::Timeout) => None,
+                Err(RichamsterError::Reqwest(e)) if retry::is_transient_error(&e) => None,
+                result => return result,
+            };
+            let delay = self.retry_policy.delay(attempt, retry_after);
+            tracing::debug!(url = %request.url, attempt, ?delay, "retrying request");
+            tokio::time::sleep(delay).await;
+            attempt += 1;
+        }
+    }
+
+    async fn send(
+        &self,
+        api: Api,
+        request: TransportRequest,
+        attempt: u32,
+    ) -> Result<TransportResponse, RichamsterError> {
+        let group = api.group();
+        let method = request.method.clone();
+        let request_bytes = request.body.as_ref().map_or(0, String::len);
+        // The limiter goes first: a half-open breaker hands out its only probe
+        // here, which must not be taken by a request that is never sent.
+        let acquired = match &self.rate_limiter {
+            Some(limiter) => limiter.acquire(group).await,
+            None => Ok(()),
+        };
+        let acquired = match (acquired, &self.circuit_breaker) {
+            (Ok(()), Some(breaker)) => breaker.acquire(group),
+            (acquired, _) => acquired,
+        };
+        if let Err(e) = acquired {
+            if let Some(hook) = &self.metrics {
+                let metrics = RequestMetrics::new(
+                    api,
+                    method,
+                    attempt,
+                    request_bytes,
+                    Duration::ZERO,
+                    Err(&e),
+                );
+                hook.on_request(&metrics);
+            }
+            return Err(e);
+        }
+        tracing::trace!(headers = ?trace::redact_headers(&request.headers), "sending request");
+        if let (Some(level), Some(body)) = (self.body_log_level, request.body.as_deref()) {
+            trace::log_body(level, "request", body);
+        }
+        let started = Instant::now();
+        let result = match self.timeout() {
+            Some(timeout) => tokio::time::timeout(timeout, self.transport.send(request))
+                .await
+                .unwrap_or(Err(RichamsterError::Timeout)),
+            None => self.transport.send(request).await,
+        };
+        let result = match result {
+            Err(RichamsterError::Reqwest(e)) if e.is_timeout() => Err(RichamsterError::Timeout),
+            result => result,
+        };
+        if let Some(breaker) = &self.circuit_breaker {
+            match &result {
+                Ok(response) if !response.status().is_server_error() => breaker.on_success(group),
+                _ => breaker.on_failure(group),
+            }
+        }
+        if let Some(hook) = &self.metrics {
+            let outcome = result.as_ref().map(|r| (r.status(), r.text().len()));
+            let metrics = RequestMetrics::new(
+                api,
+                method,
+                attempt,
+                request_bytes,
+                started.elapsed(),
+                outcome,
+            );
+            hook.on_request(&metrics);
+        }
+        let response = result?;
+        if let Some(level) = self.body_log_level {
+            trace::log_body(level, "response", response.text());
+        }
+        if let Some(limiter) = &self.rate_limiter {
+            if response.status() == StatusCode::TOO_MANY_REQUESTS {
+                limiter.on_throttled(group, retry::retry_after(response.headers()));
+            } else {
+                limiter.on_success(group);
+            }
+        }
+        Ok(response)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::api::ApiGroup;
+    use crate::api::circuit::{BreakerSettings, CircuitState};
+    use crate::api::metrics::ErrorKind;
+    use crate::api::rate_limit::{LimitMode, RateLimit};
+    use crate::api::transport::InMemoryTransport;
+    use crate::api::{ExchangeApi, UserApi};
+    use reqwest::Method;
+    use std::str::FromStr;
+
+    fn in_memory_client(transport: &Arc<InMemoryTransport>) -> RichamsterClient {
+        ClientConfig::new()
+            .transport(transport.clone())
+            .retry_policy(RetryPolicy::new().initial_backoff(Duration::ZERO))
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn default_client_uses_richamster_host() {
+        let client = RichamsterClient::default();
+        let RequestData(url, method) = client.request_data(&Api::User(UserApi::Balances));
+        assert_eq!(
+            url.as_str(),
+            "https://richamster.com/public/v1/user/balances/"
+        );
+        assert_eq!(method, Method::GET);
+    }
+
+    #[test]
+    fn custom_base_url_without_trailing_slash() {
+        let client = ClientConfig::new()
+            .base_url(Url::from_str("http://127.0.0.1:8080/public/v1").unwrap())
+            .build()
+            .unwrap();
+        let RequestData(url, _) = client.request_data(&Api::User(UserApi::Detail));
+        assert_eq!(url.as_str(), "http://127.0.0.1:8080/public/v1/user/detail/");
+    }
+
+    #[test]
+    fn proxy_and_tls_settings_configure_default_transport() {
+        let client = ClientConfig::new()
+            .proxy(Proxy::https("http://127.0.0.1:3128").unwrap())
+            .no_proxy()
+            .built_in_root_certs(false)
+            .build();
+        assert!(client.is_ok());
+        let invalid_root = ClientConfig::new()
+            .add_root_certificate(RootCertificate::from_der(vec![1, 2, 3]))
+            .build();
+        assert!(invalid_root.is_err());
+    }
+
+    #[cfg(feature = "rustls-tls")]
+    #[test]
+    fn pinned_client_uses_rustls() {
+        let client = ClientConfig::new()
+            .pin_certificate("richamster.com", vec![[7; 32]])
+            .build();
+        assert!(client.is_ok());
+        let invalid_root = ClientConfig::new()
+            .pin_certificate("richamster.com", vec![[7; 32]])
+            .add_root_certificate(RootCertificate::from_der(vec![1, 2, 3]))
+            .build();
+        assert!(matches!(invalid_root, Err(RichamsterError::Tls(_))));
+    }
+
+    #[tokio::test]
+    async fn idempotent_request_is_retried() {
+        let transport = Arc::new(InMemoryTransport::new());
+        transport
+            .respond(
+                Method::GET,
+                "exchange/order-book/",
+                TransportResponse::new(StatusCode::BAD_GATEWAY, ""),
+            )
+            .respond(
+                Method::GET,
+                "exchange/order-book/",
+                TransportResponse::new(StatusCode::OK, "{}"),
+            );
+        let client = in_memory_client(&transport);
+        let api = Api::Exchange(ExchangeApi::OrderBook);
+        let RequestData(url, method) = client.request_data(&api);
+        let response = client
+            .execute(api, TransportRequest::new(method, url))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(transport.requests().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn non_idempotent_request_is_sent_once() {
+        let transport = Arc::new(InMemoryTransport::new());
+        transport.respond(
+            Method::POST,
+            "exchange/user/orders/",
+            TransportResponse::new(StatusCode::SERVICE_UNAVAILABLE, ""),
+        );
+        let client = in_memory_client(&transport);
+        let api = Api::Exchange(ExchangeApi::NewOrder);
+        let RequestData(url, method) = client.request_data(&api);
+        let response = client
+            .execute(api, TransportRequest::new(method, url))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(transport.requests().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn open_circuit_fails_fast() {
+        let transport = Arc::new(InMemoryTransport::new());
+        transport.respond(
+            Method::GET,
+            "user/orders/",
+            TransportResponse::new(StatusCode::BAD_GATEWAY, ""),
+        );
+        let client = ClientConfig::new()
+            .transport(transport.clone())
+            .retry_policy(RetryPolicy::none())
+            .circuit_breaker(CircuitBreaker::new(
+                BreakerSettings::new().consecutive_failures(2),
+            ))
+            .build()
+            .unwrap();
+        let api = Api::User(UserApi::Orders);
+        let RequestData(url, method) = client.request_data(&api);
+        for _ in 0..2 {
+            let response = client
+                .execute(api, TransportRequest::new(method.clone(), url.clone()))
+                .await
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::BAD_GATEWAY);
+        }
+        let breaker = client.circuit_breaker().unwrap();
+        assert_eq!(breaker.state(ApiGroup::PrivateData), CircuitState::Open);
+        let result = client
+            .execute(api, TransportRequest::new(method, url))
+            .await;
+        assert!(matches!(
+            result,
+            Err(RichamsterError::CircuitOpen(ApiGroup::PrivateData))
+        ));
+        assert_eq!(transport.requests().len(), 2);
+        assert_eq!(breaker.state(ApiGroup::PublicData), CircuitState::Closed);
+    }
+
+    #[tokio::test]
+    async fn rate_limited_request_does_not_take_the_probe() {
+        let transport = Arc::new(InMemoryTransport::new());
+        transport
+            .respond(
+                Method::GET,
+                "user/orders/",
+                TransportResponse::new(StatusCode::BAD_GATEWAY, ""),
+            )
+            .respond(
+                Method::GET,
+                "user/orders/",
+                TransportResponse::new(StatusCode::OK, "[]"),
+            );
+        let client = ClientConfig::new()
+            .transport(transport.clone())
+            .retry_policy(RetryPolicy::none())
+            .rate_limiter(RateLimiter::new().mode(LimitMode::Reject).limit(
+                ApiGroup::PrivateData,
+                RateLimit::new(1, Duration::from_millis(300)),
+            ))
+            .circuit_breaker(CircuitBreaker::new(
+                BreakerSettings::new()
+                    .consecutive_failures(1)
+                    .cool_down(Duration::from_millis(200)),
+            ))
+            .build()
+            .unwrap();
+        let api = Api::User(UserApi::Orders);
+        let RequestData(url, method) = client.request_data(&api);
+        let request = || TransportRequest::new(method.clone(), url.clone());
+        client.execute(api, request()).await.unwrap();
+        let breaker = client.circuit_breaker().unwrap();
+
+        tokio::time::sleep(Duration::from_millis(220)).await;
+        assert_eq!(breaker.state(ApiGroup::PrivateData), CircuitState::HalfOpen);
+        assert!(matches!(
+            client.execute(api, request()).await,
+            Err(RichamsterError::RateLimited(ApiGroup::PrivateData))
+        ));
+
+        tokio::time::sleep(Duration::from_millis(110)).await;
+        let response = client.execute(api, request()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(breaker.state(ApiGroup::PrivateData), CircuitState::Closed);
+        assert_eq!(transport.requests().len(), 2);
+    }
+
+    #[derive(Debug)]
+    struct StalledTransport;
+
+    #[async_trait::async_trait]
+    impl Transport for StalledTransport {
+        async fn send(
+            &self,
+            _request: TransportRequest,
+        ) -> Result<TransportResponse, RichamsterError> {
+            tokio::time::sleep(Duration::from_secs(60)).await;
+            Ok(TransportResponse::new(StatusCode::OK, ""))
+        }
+    }
+
+    fn stalled_client() -> RichamsterClient {
+        ClientConfig::new()
+            .transport(Arc::new(StalledTransport))
+            .retry_policy(RetryPolicy::none())
+            .timeout(Duration::from_millis(20))
+            .build()
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn stalled_request_times_out() {
+        let client = stalled_client();
+        let api = Api::Exchange(ExchangeApi::OrderBook);
+        let RequestData(url, method) = client.request_data(&api);
+        let result = client
+            .execute(api, TransportRequest::new(method, url))
+            .await;
+        assert!(matches!(result, Err(RichamsterError::Timeout)));
+    }
+
+    #[tokio::test]
+    async fn mutating_request_timeout_has_unknown_outcome() {
+        let client = stalled_client();
+        let api = Api::Exchange(ExchangeApi::NewOrder);
+        let RequestData(url, method) = client.request_data(&api);
+        let result = client
+            .execute(api, TransportRequest::new(method, url))
+            .await;
+        assert!(matches!(result, Err(RichamsterError::OutcomeUnknown(a)) if a == api));
+    }
+
+    #[tokio::test]
+    async fn per_call_timeout_overrides_client_timeout() {
+        let client = ClientConfig::new()
+            .transport(Arc::new(StalledTransport))
+            .retry_policy(RetryPolicy::none())
+            .build()
+            .unwrap();
+        assert_eq!(client.timeout(), None);
+        let api = Api::User(UserApi::Detail);
+        let RequestData(url, method) = client.request_data(&api);
+        let started = Instant::now();
+        let result = with_timeout(
+            Duration::from_millis(20),
+            client.execute(api, TransportRequest::new(method, url)),
+        )
+        .await;
+        assert!(matches!(result, Err(RichamsterError::Timeout)));
+        assert!(started.elapsed() < Duration::from_secs(10));
+    }
+
+    #[derive(Debug, Default)]
+    struct RecordingHook(std::sync::Mutex<Vec<RequestMetrics>>);
+
+    impl MetricsHook for RecordingHook {
+        fn on_request(&self, metrics: &RequestMetrics) {
+            self.0.lock().unwrap().push(metrics.clone());
+        }
+    }
+
+    #[tokio::test]
+    async fn metrics_hook_sees_every_attempt() {
+        let transport = Arc::new(InMemoryTransport::new());
+        transport
+            .respond(
+                Method::GET,
+                "exchange/order-book/",
+                TransportResponse::new(StatusCode::SERVICE_UNAVAILABLE, ""),
+            )
+            .respond(
+                Method::GET,
+                "exchange/order-book/",
+                TransportResponse::new(StatusCode::OK, "{}"),
+            );
+        let hook = Arc::new(RecordingHook::default());
+        let client = ClientConfig::new()
+            .transport(transport)
+            .retry_policy(RetryPolicy::new().initial_backoff(Duration::ZERO))
+            .metrics(hook.clone())
+            .build()
+            .unwrap();
+        let api = Api::Exchange(ExchangeApi::OrderBook);
+        let RequestData(url, method) = client.request_data(&api);
+        client
+            .execute(api, TransportRequest::new(method, url))
+            .await
+            .unwrap();
+        let recorded = hook.0.lock().unwrap();
+        assert_eq!(recorded.len(), 2);
+        assert_eq!(recorded[0].endpoint(), "order_book");
+        assert_eq!(recorded[0].error, Some(ErrorKind::ServerError));
+        assert_eq!(recorded[1].attempt, 2);
+        assert_eq!(recorded[1].status, Some(StatusCode::OK));
+        assert_eq!(recorded[1].response_bytes, 2);
+    }
+
+    #[tokio::test]
+    async fn default_headers_are_added() {
+        let transport = Arc::new(InMemoryTransport::new());
+        let mut headers = HeaderMap::new();
+        headers.insert("x-bot", "grid".parse().unwrap());
+        let client = ClientConfig::new()
+            .transport(transport.clone())
+            .default_headers(headers)
+            .build()
+            .unwrap();
+        let api = Api::User(UserApi::Detail);
+        let RequestData(url, method) = client.request_data(&api);
+        client
+            .execute(api, TransportRequest::new(method, url))
+            .await
+            .unwrap();
+        let request = transport.last_request().unwrap();
+        assert_eq!(request.header_value("x-bot"), Some("grid"));
+    }
+}
@@ -0,0 +1,180 @@
+use crate::api::{Api, ApiGroup, retry};
+use crate::errors::RichamsterError;
+use reqwest::{Method, StatusCode};
+use std::fmt::Debug;
+use std::time::Duration;
+
+/// Why a request did not produce a successful response.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, strum_macros::IntoStaticStr)]
+#[strum(serialize_all = "snake_case")]
+pub enum ErrorKind {
+    Timeout,
+    Connect,
+    Transport,
+    RateLimited,
+    CircuitOpen,
+    ClientError,
+    ServerError,
+}
+
+impl ErrorKind {
+    pub fn as_str(&self) -> &'static str {
+        self.into()
+    }
+
+    fn from_status(status: StatusCode) -> Option<Self> {
+        if status == StatusCode::TOO_MANY_REQUESTS {
+            Some(ErrorKind::RateLimited)
+        } else if status.is_client_error() {
+            Some(ErrorKind::ClientError)
+        } else if status.is_server_error() {
+            Some(ErrorKind::ServerError)
+        } else {
+            None
+        }
+    }
+
+    fn from_error(error: &RichamsterError) -> Self {
+        match error {
+            RichamsterError::Timeout | RichamsterError::OutcomeUnknown(_) => ErrorKind::Timeout,
+            RichamsterError::Reqwest(e) if e.is_timeout() => ErrorKind::Timeout,
+            RichamsterError::Reqwest(e) if retry::is_transient_error(e) => ErrorKind::Connect,
+            RichamsterError::RateLimited(_) => ErrorKind::RateLimited,
+            RichamsterError::CircuitOpen(_) => ErrorKind::CircuitOpen,
+            _ => ErrorKind::Transport,
+        }
+    }
+}
+
+/// Measurements of a single HTTP exchange. Retried calls report every attempt.
+#[derive(Clone, Debug)]
+pub struct RequestMetrics {
+    pub api: Api,
+    pub method: Method,
+    pub attempt: u32,
+    /// `None` when no response was received.
+    pub status: Option<StatusCode>,
+    pub duration: Duration,
+    pub request_bytes: usize,
+    pub response_bytes: usize,
+    pub error: Option<ErrorKind>,
+}
+
+impl RequestMetrics {
+    pub(crate) fn new(
+        api: Api,
+        method: Method,
+        attempt: u32,
+        request_bytes: usize,
+        duration: Duration,
+        result: Result<(StatusCode, usize), &RichamsterError>,
+    ) -> Self {
+        let (status, response_bytes, error) = match result {
+            Ok((status, bytes)) => (Some(status), bytes, ErrorKind::from_status(status)),
+            Err(e) => (None, 0, Some(ErrorKind::from_error(e))),
+        };
+        Self {
+            api,
+            method,
+            attempt,
+            status,
+            duration,
+            request_bytes,
+            response_bytes,
+            error,
+        }
+    }
+
+    /// Endpoint label, see [`Api::endpoint`].
+    pub fn endpoint(&self) -> &'static str {
+        self.api.endpoint()
+    }
+
+    pub fn group(&self) -> ApiGroup {
+        self.api.group()
+    }
+}
+
+/// Receives [`RequestMetrics`] after every request sent by a client configured
+/// with [`ClientConfig::metrics`](crate::api::client::ClientConfig::metrics).
+/// Called on the request path, so implementations should be cheap.
+pub trait MetricsHook: Debug + Send + Sync {
+    fn on_request(&self, metrics: &RequestMetrics);
+}
+
+/// Records requests through the [`metrics`] facade:
+///
+/// * `richamster_requests_total` counter
+/// * `richamster_request_duration_seconds` histogram
+/// * `richamster_request_bytes` and `richamster_response_bytes` histograms
+/// * `richamster_request_errors_total` counter, labelled with the error `kind`
+///
+/// All of them are labelled with `endpoint`, `group`, `method` and `status`
+/// (`none` when no response was received).
+#[cfg(feature = "metrics")]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct MetricsFacade;
+
+#[cfg(feature = "metrics")]
+impl MetricsHook for MetricsFacade {
+    fn on_request(&self, request: &RequestMetrics) {
+        let status = request
+            .status
+            .map(|s| s.as_u16().to_string())
+            .unwrap_or_else(|| "none".to_owned());
+        let labels = [
+            ("endpoint", request.endpoint().to_owned()),
+            ("group", request.group().to_string()),
+            ("method", request.method.to_string()),
+            ("status", status),
+        ];
+        metrics::counter!("richamster_requests_total", &labels).increment(1);
+        metrics::histogram!("richamster_request_duration_seconds", &labels)
+            .record(request.duration.as_secs_f64());
+        metrics::histogram!("richamster_request_bytes", &labels)
+            .record(request.request_bytes as f64);
+        metrics::histogram!("richamster_response_bytes", &labels)
+            .record(request.response_bytes as f64);
+        if let Some(kind) = request.error {
+            let [endpoint, group, method, status] = labels;
+            let labels = [
+                endpoint,
+                group,
+                method,
+                status,
+                ("kind", kind.as_str().to_owned()),
+            ];
+            metrics::counter!("richamster_request_errors_total", &labels).increment(1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::api::ExchangeApi;
+
+    #[test]
+    fn error_kind_from_status() {
+        let api = Api::Exchange(ExchangeApi::OrderBook);
+        let metrics =
+            |status| RequestMetrics::new(api, Method::GET, 1, 0, Duration::ZERO, Ok((status, 2)));
+        assert_eq!(metrics(StatusCode::OK).error, None);
+        assert_eq!(
+            metrics(StatusCode::TOO_MANY_REQUESTS).error,
+            Some(ErrorKind::RateLimited)
+        );
+        assert_eq!(
+            metrics(StatusCode::NOT_FOUND).error,
+            Some(ErrorKind::ClientError)
+        );
+        assert_eq!(
+            metrics(StatusCode::BAD_GATEWAY).error,
+            Some(ErrorKind::ServerError)
+        );
+        let limited = RichamsterError::RateLimited(ApiGroup::PublicData);
+        let failed = RequestMetrics::new(api, Method::GET, 1, 0, Duration::ZERO, Err(&limited));
+        assert_eq!(failed.status, None);
+        assert_eq!(failed.error.unwrap().as_str(), "rate_limited");
+    }
+}
@@ -1,15 +1,25 @@
 use once_cell::sync::Lazy;
-use reqwest::{Client, Method};
+use reqwest::Method;
 use std::str::FromStr;
 use url::Url;
 
+pub mod cassette;
+pub mod circuit;
+pub mod client;
+pub mod metrics;
+pub mod permissions;
+pub mod rate_limit;
+pub mod retry;
+pub mod signing;
+pub mod tls;
 pub mod token;
+pub mod trace;
+pub mod transport;
 
-static BASE_URL: Lazy<Url> =
+pub static DEFAULT_BASE_URL: Lazy<Url> =
     Lazy::new(|| Url::from_str("https://richamster.com/public/v1/").unwrap());
 
-pub static CLIENT: Lazy<Client> = Lazy::new(Client::new);
-
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum Api {
     Exchange(ExchangeApi),
     Feedback(FeedbackApi),
@@ -19,6 +29,8 @@ pub enum Api {
     Replenish(ReplenishApi),
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, strum_macros::IntoStaticStr)]
+#[strum(serialize_all = "snake_case")]
 pub enum ExchangeApi {
     Currencies,
     Favourites,
@@ -34,21 +46,29 @@ pub enum ExchangeApi {
     DestroyOrder,
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, strum_macros::IntoStaticStr)]
+#[strum(serialize_all = "snake_case")]
 pub enum WithdrawApi {
     Withdraw,
     WithdrawInfo,
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, strum_macros::IntoStaticStr)]
+#[strum(serialize_all = "snake_case")]
 pub enum ReplenishApi {
     ReplenishChannelsInfo,
     P2PReplenish,
     ReplenishInfo,
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, strum_macros::IntoStaticStr)]
+#[strum(serialize_all = "snake_case")]
 pub enum FeedbackApi {
     Messengers,
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, strum_macros::IntoStaticStr)]
+#[strum(serialize_all = "snake_case")]
 pub enum AuthenticationApi {
     Login,
     Register,
@@ -56,6 +76,8 @@ pub enum AuthenticationApi {
     TwoFactorLogin,
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, strum_macros::IntoStaticStr)]
+#[strum(serialize_all = "snake_case")]
 pub enum UserApi {
     Balances,
     Detail,
@@ -64,178 +86,263 @@ pub enum UserApi {
     Transfer,
 }
 
+/// Coarse classification of endpoints used to apply per-group client policies.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, strum_macros::Display)]
+pub enum ApiGroup {
+    PublicData,
+    PrivateData,
+    Trading,
+    Wallet,
+    Auth,
+}
+
+impl Api {
+    /// Snake case name of the endpoint, e.g. `order_book`, used as a metrics label.
+    pub fn endpoint(&self) -> &'static str {
+        match self {
+            Api::Exchange(api) => api.into(),
+            Api::Feedback(api) => api.into(),
+            Api::Authentication(api) => api.into(),
+            Api::Withdraw(api) => api.into(),
+            Api::User(api) => api.into(),
+            Api::Replenish(api) => api.into(),
+        }
+    }
+
+    pub fn group(&self) -> ApiGroup {
+        match self {
+            Api::Exchange(exchange) => match exchange {
+                ExchangeApi::Currencies
+                | ExchangeApi::Markets
+                | ExchangeApi::OrderBook
+                | ExchangeApi::OrdersHistory
+                | ExchangeApi::Restrictions
+                | ExchangeApi::TickerList => ApiGroup::PublicData,
+                ExchangeApi::UserOrders | ExchangeApi::CalculateMarketOrder => {
+                    ApiGroup::PrivateData
+                }
+                ExchangeApi::Favourites
+                | ExchangeApi::NewOrder
+                | ExchangeApi::ExecuteMarketOrder
+                | ExchangeApi::DestroyOrder => ApiGroup::Trading,
+            },
+            Api::Feedback(_) => ApiGroup::PublicData,
+            Api::Authentication(_) => ApiGroup::Auth,
+            Api::Withdraw(withdraw) => match withdraw {
+                WithdrawApi::WithdrawInfo => ApiGroup::PrivateData,
+                WithdrawApi::Withdraw => ApiGroup::Wallet,
+            },
+            Api::Replenish(replenish) => match replenish {
+                ReplenishApi::ReplenishInfo | ReplenishApi::ReplenishChannelsInfo => {
+                    ApiGroup::PrivateData
+                }
+                ReplenishApi::P2PReplenish => ApiGroup::Wallet,
+            },
+            Api::User(user) => match user {
+                UserApi::Balances | UserApi::Detail | UserApi::Orders | UserApi::Transactions => {
+                    ApiGroup::PrivateData
+                }
+                UserApi::Transfer => ApiGroup::Wallet,
+            },
+        }
+    }
+}
+
 pub trait RequestPath {
-    fn request_data(&self) -> RequestData;
-    fn full_url(&self, path: &str) -> Url;
+    fn request_data(&self, base_url: &Url) -> RequestData;
+    fn full_url(&self, base_url: &Url, path: &str) -> Url;
 }
 
 pub struct RequestData(pub Url, pub Method);
 
 impl RequestPath for Api {
-    fn request_data(&self) -> RequestData {
+    fn request_data(&self, base_url: &Url) -> RequestData {
         match self {
             Api::Exchange(exchange) => match exchange {
                 ExchangeApi::Currencies => {
-                    RequestData(self.full_url("exchange/currencies/"), Method::GET)
+                    RequestData(self.full_url(base_url, "exchange/currencies/"), Method::GET)
                 }
                 ExchangeApi::Favourites => RequestData(
-                    self.full_url("exchange/favourites/{id}/toggle/"),
+                    self.full_url(base_url, "exchange/favourites/{id}/toggle/"),
                     Method::POST,
                 ),
                 ExchangeApi::Markets => {
-                    RequestData(self.full_url("exchange/markets/"), Method::GET)
+                    RequestData(self.full_url(base_url, "exchange/markets/"), Method::GET)
                 }
                 ExchangeApi::OrderBook => {
-                    RequestData(self.full_url("exchange/order-book/"), Method::GET)
-                }
-                ExchangeApi::OrdersHistory => {
-                    RequestData(self.full_url("exchange/orders/history/"), Method::GET)
-                }
-                ExchangeApi::Restrictions => {
-                    RequestData(self.full_url("exchange/restrictions/"), Method::GET)
+                    RequestData(self.full_url(base_url, "exchange/order-book/"), Method::GET)
                 }
+                ExchangeApi::OrdersHistory => RequestData(
+                    self.full_url(base_url, "exchange/orders/history/"),
+                    Method::GET,
+                ),
+                ExchangeApi::Restrictions => RequestData(
+                    self.full_url(base_url, "exchange/restrictions/"),
+                    Method::GET,
+                ),
                 ExchangeApi::TickerList => {
-                    RequestData(self.full_url("exchange/ticker/"), Method::GET)
-                }
-                ExchangeApi::UserOrders => {
-                    RequestData(self.full_url("exchange/user/orders/"), Method::GET)
-                }
-                ExchangeApi::NewOrder => {
-                    RequestData(self.full_url("exchange/user/orders/"), Method::POST)
-                }
-                ExchangeApi::CalculateMarketOrder => {
-                    RequestData(self.full_url("exchange/user/orders/market/"), Method::GET)
-                }
-                ExchangeApi::ExecuteMarketOrder => {
-                    RequestData(self.full_url("exchange/user/orders/market/"), Method::POST)
-                }
-                ExchangeApi::DestroyOrder => {
-                    RequestData(self.full_url("exchange/user/orders/"), Method::DELETE)
+                    RequestData(self.full_url(base_url, "exchange/ticker/"), Method::GET)
                 }
+                ExchangeApi::UserOrders => RequestData(
+                    self.full_url(base_url, "exchange/user/orders/"),
+                    Method::GET,
+                ),
+                ExchangeApi::NewOrder => RequestData(
+                    self.full_url(base_url, "exchange/user/orders/"),
+                    Method::POST,
+                ),
+                ExchangeApi::CalculateMarketOrder => RequestData(
+                    self.full_url(base_url, "exchange/user/orders/market/"),
+                    Method::GET,
+                ),
+                ExchangeApi::ExecuteMarketOrder => RequestData(
+                    self.full_url(base_url, "exchange/user/orders/market/"),
+                    Method::POST,
+                ),
+                ExchangeApi::DestroyOrder => RequestData(
+                    self.full_url(base_url, "exchange/user/orders/"),
+                    Method::DELETE,
+                ),
             },
             Api::Feedback(feedback) => match feedback {
                 FeedbackApi::Messengers => {
-                    RequestData(self.full_url("feedback/messengers/"), Method::GET)
+                    RequestData(self.full_url(base_url, "feedback/messengers/"), Method::GET)
                 }
             },
             Api::Authentication(authentication) => match authentication {
-                AuthenticationApi::Login => RequestData(self.full_url("login/"), Method::POST),
+                AuthenticationApi::Login => {
+                    RequestData(self.full_url(base_url, "login/"), Method::POST)
+                }
                 AuthenticationApi::Register => {
-                    RequestData(self.full_url("register/"), Method::POST)
+                    RequestData(self.full_url(base_url, "register/"), Method::POST)
                 }
                 AuthenticationApi::RefreshToken => {
-                    RequestData(self.full_url("token/refresh/"), Method::POST)
+                    RequestData(self.full_url(base_url, "token/refresh/"), Method::POST)
                 }
                 AuthenticationApi::TwoFactorLogin => {
-                    RequestData(self.full_url("two-factor-login/"), Method::POST)
+                    RequestData(self.full_url(base_url, "two-factor-login/"), Method::POST)
                 }
             },
             Api::Withdraw(withdraw) => match withdraw {
-                WithdrawApi::Withdraw => RequestData(self.full_url("withdraw/"), Method::POST),
-                WithdrawApi::WithdrawInfo => RequestData(self.full_url("withdraw/"), Method::GET),
+                WithdrawApi::Withdraw => {
+                    RequestData(self.full_url(base_url, "withdraw/"), Method::POST)
+                }
+                WithdrawApi::WithdrawInfo => {
+                    RequestData(self.full_url(base_url, "withdraw/"), Method::GET)
+                }
             },
             Api::Replenish(replenish) => match replenish {
                 ReplenishApi::ReplenishInfo => {
-                    RequestData(self.full_url("replenish/"), Method::GET)
+                    RequestData(self.full_url(base_url, "replenish/"), Method::GET)
                 }
                 ReplenishApi::P2PReplenish => {
-                    RequestData(self.full_url("replenish/p2p/"), Method::POST)
-                }
-                ReplenishApi::ReplenishChannelsInfo => {
-                    RequestData(self.full_url("replenish/blockchain/"), Method::GET)
+                    RequestData(self.full_url(base_url, "replenish/p2p/"), Method::POST)
                 }
+                ReplenishApi::ReplenishChannelsInfo => RequestData(
+                    self.full_url(base_url, "replenish/blockchain/"),
+                    Method::GET,
+                ),
             },
             Api::User(user) => match user {
-                UserApi::Balances => RequestData(self.full_url("user/balances/"), Method::GET),
-                UserApi::Detail => RequestData(self.full_url("user/detail/"), Method::GET),
-                UserApi::Orders => RequestData(self.full_url("user/orders/"), Method::GET),
+                UserApi::Balances => {
+                    RequestData(self.full_url(base_url, "user/balances/"), Method::GET)
+                }
+                UserApi::Detail => {
+                    RequestData(self.full_url(base_url, "user/detail/"), Method::GET)
+                }
+                UserApi::Orders => {
+                    RequestData(self.full_url(base_url, "user/orders/"), Method::GET)
+                }
                 UserApi::Transactions => {
-                    RequestData(self.full_url("user/transactions/"), Method::GET)
+                    RequestData(self.full_url(base_url, "user/transactions/"), Method::GET)
+                }
+                UserApi::Transfer => {
+                    RequestData(self.full_url(base_url, "user/transfer/"), Method::POST)
                 }
-                UserApi::Transfer => RequestData(self.full_url("user/transfer/"), Method::POST),
             },
         }
     }
 
-    fn full_url(&self, path: &str) -> Url {
-        BASE_URL.join(path).unwrap()
+    fn full_url(&self, base_url: &Url, path: &str) -> Url {
+        base_url.join(path).unwrap()
     }
 }
 
 #[cfg(test)]
-
 mod test {
     use super::*;
     use percent_encoding::percent_decode_str;
 
     #[test]
     fn exchange_join_path() {
-        let req_data = Api::Exchange(ExchangeApi::CalculateMarketOrder).request_data();
+        let req_data =
+            Api::Exchange(ExchangeApi::CalculateMarketOrder).request_data(&DEFAULT_BASE_URL);
         assert_eq!(
             percent_decode_str(req_data.0.as_str()).decode_utf8_lossy(),
             "https://richamster.com/public/v1/exchange/user/orders/market/"
         );
         assert_eq!(req_data.1, Method::GET);
-        let req_data = Api::Exchange(ExchangeApi::OrdersHistory).request_data();
+        let req_data = Api::Exchange(ExchangeApi::OrdersHistory).request_data(&DEFAULT_BASE_URL);
         assert_eq!(
             percent_decode_str(req_data.0.as_str()).decode_utf8_lossy(),
             "https://richamster.com/public/v1/exchange/orders/history/"
         );
         assert_eq!(req_data.1, Method::GET);
-        let req_data = Api::Exchange(ExchangeApi::NewOrder).request_data();
+        let req_data = Api::Exchange(ExchangeApi::NewOrder).request_data(&DEFAULT_BASE_URL);
         assert_eq!(
             percent_decode_str(req_data.0.as_str()).decode_utf8_lossy(),
             "https://richamster.com/public/v1/exchange/user/orders/"
         );
         assert_eq!(req_data.1, Method::POST);
-        let req_data = Api::Exchange(ExchangeApi::UserOrders).request_data();
+        let req_data = Api::Exchange(ExchangeApi::UserOrders).request_data(&DEFAULT_BASE_URL);
         assert_eq!(
             percent_decode_str(req_data.0.as_str()).decode_utf8_lossy(),
             "https://richamster.com/public/v1/exchange/user/orders/"
         );
         assert_eq!(req_data.1, Method::GET);
-        let req_data = Api::Exchange(ExchangeApi::DestroyOrder).request_data();
+        let req_data = Api::Exchange(ExchangeApi::DestroyOrder).request_data(&DEFAULT_BASE_URL);
         assert_eq!(
             percent_decode_str(req_data.0.as_str()).decode_utf8_lossy(),
             "https://richamster.com/public/v1/exchange/user/orders/"
         );
         assert_eq!(req_data.1, Method::DELETE);
-        let req_data = Api::Exchange(ExchangeApi::OrderBook).request_data();
+        let req_data = Api::Exchange(ExchangeApi::OrderBook).request_data(&DEFAULT_BASE_URL);
         assert_eq!(
             percent_decode_str(req_data.0.as_str()).decode_utf8_lossy(),
             "https://richamster.com/public/v1/exchange/order-book/"
         );
         assert_eq!(req_data.1, Method::GET);
-        let req_data = Api::Exchange(ExchangeApi::Restrictions).request_data();
+        let req_data = Api::Exchange(ExchangeApi::Restrictions).request_data(&DEFAULT_BASE_URL);
         assert_eq!(
             percent_decode_str(req_data.0.as_str()).decode_utf8_lossy(),
             "https://richamster.com/public/v1/exchange/restrictions/"
         );
         assert_eq!(req_data.1, Method::GET);
-        let req_data = Api::Exchange(ExchangeApi::Currencies).request_data();
+        let req_data = Api::Exchange(ExchangeApi::Currencies).request_data(&DEFAULT_BASE_URL);
         assert_eq!(
             percent_decode_str(req_data.0.as_str()).decode_utf8_lossy(),
             "https://richamster.com/public/v1/exchange/currencies/"
         );
         assert_eq!(req_data.1, Method::GET);
-        let req_data = Api::Exchange(ExchangeApi::Favourites).request_data();
+        let req_data = Api::Exchange(ExchangeApi::Favourites).request_data(&DEFAULT_BASE_URL);
         assert_eq!(
             percent_decode_str(req_data.0.as_str()).decode_utf8_lossy(),
             "https://richamster.com/public/v1/exchange/favourites/{id}/toggle/"
         );
-        let req_data = Api::Exchange(ExchangeApi::ExecuteMarketOrder).request_data();
+        let req_data =
+            Api::Exchange(ExchangeApi::ExecuteMarketOrder).request_data(&DEFAULT_BASE_URL);
         assert_eq!(
             percent_decode_str(req_data.0.as_str()).decode_utf8_lossy(),
             "https://richamster.com/public/v1/exchange/user/orders/market/"
         );
         assert_eq!(req_data.1, Method::POST);
-        let req_data = Api::Exchange(ExchangeApi::TickerList).request_data();
+        let req_data = Api::Exchange(ExchangeApi::TickerList).request_data(&DEFAULT_BASE_URL);
         assert_eq!(
             percent_decode_str(req_data.0.as_str()).decode_utf8_lossy(),
             "https://richamster.com/public/v1/exchange/ticker/"
         );
         assert_eq!(req_data.1, Method::GET);
-        let req_data = Api::Exchange(ExchangeApi::OrderBook).request_data();
+        let req_data = Api::Exchange(ExchangeApi::OrderBook).request_data(&DEFAULT_BASE_URL);
         assert_eq!(
             percent_decode_str(req_data.0.as_str()).decode_utf8_lossy(),
             "https://richamster.com/public/v1/exchange/order-book/"
@@ -245,25 +352,29 @@ mod test {
 
     #[test]
     fn auth_join_path() {
-        let req_data = Api::Authentication(AuthenticationApi::Login).request_data();
+        let req_data =
+            Api::Authentication(AuthenticationApi::Login).request_data(&DEFAULT_BASE_URL);
         assert_eq!(
             percent_decode_str(req_data.0.as_str()).decode_utf8_lossy(),
             "https://richamster.com/public/v1/login/"
         );
         assert_eq!(req_data.1, Method::POST);
-        let req_data = Api::Authentication(AuthenticationApi::Register).request_data();
+        let req_data =
+            Api::Authentication(AuthenticationApi::Register).request_data(&DEFAULT_BASE_URL);
         assert_eq!(
             percent_decode_str(req_data.0.as_str()).decode_utf8_lossy(),
             "https://richamster.com/public/v1/register/"
         );
         assert_eq!(req_data.1, Method::POST);
-        let req_data = Api::Authentication(AuthenticationApi::TwoFactorLogin).request_data();
+        let req_data =
+            Api::Authentication(AuthenticationApi::TwoFactorLogin).request_data(&DEFAULT_BASE_URL);
         assert_eq!(
             percent_decode_str(req_data.0.as_str()).decode_utf8_lossy(),
             "https://richamster.com/public/v1/two-factor-login/"
         );
         assert_eq!(req_data.1, Method::POST);
-        let req_data = Api::Authentication(AuthenticationApi::RefreshToken).request_data();
+        let req_data =
+            Api::Authentication(AuthenticationApi::RefreshToken).request_data(&DEFAULT_BASE_URL);
         assert_eq!(
             percent_decode_str(req_data.0.as_str()).decode_utf8_lossy(),
             "https://richamster.com/public/v1/token/refresh/"
@@ -273,7 +384,7 @@ mod test {
 
     #[test]
     fn feedback_join_path() {
-        let req_data = Api::Feedback(FeedbackApi::Messengers).request_data();
+        let req_data = Api::Feedback(FeedbackApi::Messengers).request_data(&DEFAULT_BASE_URL);
         assert_eq!(
             percent_decode_str(req_data.0.as_str()).decode_utf8_lossy(),
             "https://richamster.com/public/v1/feedback/messengers/"
@@ -283,31 +394,31 @@ mod test {
 
     #[test]
     fn user_join_path() {
-        let req_data = Api::User(UserApi::Detail).request_data();
+        let req_data = Api::User(UserApi::Detail).request_data(&DEFAULT_BASE_URL);
         assert_eq!(
             percent_decode_str(req_data.0.as_str()).decode_utf8_lossy(),
             "https://richamster.com/public/v1/user/detail/"
         );
         assert_eq!(req_data.1, Method::GET);
-        let req_data = Api::User(UserApi::Balances).request_data();
+        let req_data = Api::User(UserApi::Balances).request_data(&DEFAULT_BASE_URL);
         assert_eq!(
             percent_decode_str(req_data.0.as_str()).decode_utf8_lossy(),
             "https://richamster.com/public/v1/user/balances/"
         );
         assert_eq!(req_data.1, Method::GET);
-        let req_data = Api::User(UserApi::Orders).request_data();
+        let req_data = Api::User(UserApi::Orders).request_data(&DEFAULT_BASE_URL);
         assert_eq!(
             percent_decode_str(req_data.0.as_str()).decode_utf8_lossy(),
             "https://richamster.com/public/v1/user/orders/"
         );
         assert_eq!(req_data.1, Method::GET);
-        let req_data = Api::User(UserApi::Transactions).request_data();
+        let req_data = Api::User(UserApi::Transactions).request_data(&DEFAULT_BASE_URL);
         assert_eq!(
             percent_decode_str(req_data.0.as_str()).decode_utf8_lossy(),
             "https://richamster.com/public/v1/user/transactions/"
         );
         assert_eq!(req_data.1, Method::GET);
-        let req_data = Api::User(UserApi::Transfer).request_data();
+        let req_data = Api::User(UserApi::Transfer).request_data(&DEFAULT_BASE_URL);
         assert_eq!(
             percent_decode_str(req_data.0.as_str()).decode_utf8_lossy(),
             "https://richamster.com/public/v1/user/transfer/"
@@ -317,13 +428,13 @@ mod test {
 
     #[test]
     fn withdraw_join_path() {
-        let req_data = Api::Withdraw(WithdrawApi::Withdraw).request_data();
+        let req_data = Api::Withdraw(WithdrawApi::Withdraw).request_data(&DEFAULT_BASE_URL);
         assert_eq!(
             percent_decode_str(req_data.0.as_str()).decode_utf8_lossy(),
             "https://richamster.com/public/v1/withdraw/"
         );
         assert_eq!(req_data.1, Method::POST);
-        let req_data = Api::Withdraw(WithdrawApi::WithdrawInfo).request_data();
+        let req_data = Api::Withdraw(WithdrawApi::WithdrawInfo).request_data(&DEFAULT_BASE_URL);
         assert_eq!(
             percent_decode_str(req_data.0.as_str()).decode_utf8_lossy(),
             "https://richamster.com/public/v1/withdraw/"
@@ -331,21 +442,56 @@ mod test {
         assert_eq!(req_data.1, Method::GET);
     }
 
+    #[test]
+    fn api_groups() {
+        assert_eq!(
+            Api::Exchange(ExchangeApi::OrderBook).group(),
+            ApiGroup::PublicData
+        );
+        assert_eq!(
+            Api::Exchange(ExchangeApi::UserOrders).group(),
+            ApiGroup::PrivateData
+        );
+        assert_eq!(
+            Api::Exchange(ExchangeApi::NewOrder).group(),
+            ApiGroup::Trading
+        );
+        assert_eq!(Api::User(UserApi::Transfer).group(), ApiGroup::Wallet);
+        assert_eq!(
+            Api::Authentication(AuthenticationApi::Login).group(),
+            ApiGroup::Auth
+        );
+    }
+
+    #[test]
+    fn endpoint_labels() {
+        assert_eq!(
+            Api::Exchange(ExchangeApi::OrderBook).endpoint(),
+            "order_book"
+        );
+        assert_eq!(Api::Exchange(ExchangeApi::NewOrder).endpoint(), "new_order");
+        assert_eq!(
+            Api::Replenish(ReplenishApi::P2PReplenish).endpoint(),
+            "p2p_replenish"
+        );
+    }
+
     #[test]
     fn replenish_join_path() {
-        let req_data = Api::Replenish(ReplenishApi::P2PReplenish).request_data();
+        let req_data = Api::Replenish(ReplenishApi::P2PReplenish).request_data(&DEFAULT_BASE_URL);
         assert_eq!(
             percent_decode_str(req_data.0.as_str()).decode_utf8_lossy(),
             "https://richamster.com/public/v1/replenish/p2p/"
         );
         assert_eq!(req_data.1, Method::POST);
-        let req_data = Api::Replenish(ReplenishApi::ReplenishInfo).request_data();
+        let req_data = Api::Replenish(ReplenishApi::ReplenishInfo).request_data(&DEFAULT_BASE_URL);
         assert_eq!(
             percent_decode_str(req_data.0.as_str()).decode_utf8_lossy(),
             "https://richamster.com/public/v1/replenish/"
         );
         assert_eq!(req_data.1, Method::GET);
-        let req_data = Api::Replenish(ReplenishApi::ReplenishChannelsInfo).request_data();
+        let req_data =
+            Api::Replenish(ReplenishApi::ReplenishChannelsInfo).request_data(&DEFAULT_BASE_URL);
         assert_eq!(
             percent_decode_str(req_data.0.as_str()).decode_utf8_lossy(),
             "https://richamster.com/public/v1/replenish/blockchain/"
@@ -0,0 +1,151 @@
+use crate::api::{Api, ApiGroup};
+use crate::errors::RichamsterError;
+
+/// What a request needs to be allowed by [`Permissions`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, strum_macros::Display)]
+pub enum Capability {
+    /// Market data, account data, login and token refresh.
+    Read,
+    /// Placing and cancelling orders and changing exchange settings such as
+    /// favourite pairs.
+    Trading,
+    /// Moving funds: withdrawals, transfers and P2P deposits.
+    Wallet,
+}
+
+impl Capability {
+    pub fn required_by(api: Api) -> Self {
+        match api.group() {
+            ApiGroup::PublicData | ApiGroup::PrivateData | ApiGroup::Auth => Capability::Read,
+            ApiGroup::Trading => Capability::Trading,
+            ApiGroup::Wallet => Capability::Wallet,
+        }
+    }
+}
+
+/// Capabilities a service handle is allowed to use, all of them by default.
+/// Reading is always allowed; a request needing a capability that is not
+/// granted fails with [`RichamsterError::PermissionDenied`] before anything is
+/// sent. Set with the `restrict` method of a service, which only narrows them.
+///
+/// ```
+/// # use richamster_api::api::permissions::{Capability, Permissions};
+/// # use richamster_api::richamster::Richamster;
+/// let richamster = Richamster::with_keys("api key".to_owned(), "secret key".to_owned());
+/// let reporting = richamster.clone().restrict(Permissions::read_only());
+/// let trading = richamster.restrict(Permissions::read_only().grant(Capability::Trading));
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Permissions {
+    trading: bool,
+    wallet: bool,
+}
+
+impl Default for Permissions {
+    fn default() -> Self {
+        Self::all()
+    }
+}
+
+impl Permissions {
+    pub fn all() -> Self {
+        Self {
+            trading: true,
+            wallet: true,
+        }
+    }
+
+    pub fn read_only() -> Self {
+        Self {
+            trading: false,
+            wallet: false,
+        }
+    }
+
+    pub fn grant(mut self, capability: Capability) -> Self {
+        self.set(capability, true);
+        self
+    }
+
+    pub fn revoke(mut self, capability: Capability) -> Self {
+        self.set(capability, false);
+        self
+    }
+
+    fn set(&mut self, capability: Capability, allowed: bool) {
+        match capability {
+            Capability::Read => {}
+            Capability::Trading => self.trading = allowed,
+            Capability::Wallet => self.wallet = allowed,
+        }
+    }
+
+    /// Capabilities allowed by both `self` and `other`.
+    pub fn intersect(self, other: Permissions) -> Self {
+        Self {
+            trading: self.trading && other.trading,
+            wallet: self.wallet && other.wallet,
+        }
+    }
+
+    pub fn allows(&self, capability: Capability) -> bool {
+        match capability {
+            Capability::Read => true,
+            Capability::Trading => self.trading,
+            Capability::Wallet => self.wallet,
+        }
+    }
+
+    pub(crate) fn check(&self, api: Api) -> Result<(), RichamsterError> {
+        let capability = Capability::required_by(api);
+        if self.allows(capability) {
+            Ok(())
+        } else {
+            Err(RichamsterError::PermissionDenied(api, capability))
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::api::{ExchangeApi, UserApi, WithdrawApi};
+
+    #[test]
+    fn read_only_rejects_trading_and_wallet_endpoints() {
+        let permissions = Permissions::read_only();
+        assert!(permissions.check(Api::User(UserApi::Balances)).is_ok());
+        assert!(
+            permissions
+                .check(Api::Exchange(ExchangeApi::UserOrders))
+                .is_ok()
+        );
+        assert!(matches!(
+            permissions.check(Api::Exchange(ExchangeApi::NewOrder)),
+            Err(RichamsterError::PermissionDenied(_, Capability::Trading))
+        ));
+        assert!(matches!(
+            permissions.check(Api::User(UserApi::Transfer)),
+            Err(RichamsterError::PermissionDenied(_, Capability::Wallet))
+        ));
+
+        let trading = permissions.grant(Capability::Trading);
+        assert!(
+            trading
+                .check(Api::Exchange(ExchangeApi::DestroyOrder))
+                .is_ok()
+        );
+        assert!(trading.check(Api::Withdraw(WithdrawApi::Withdraw)).is_err());
+        assert_eq!(trading.intersect(Permissions::all()), trading);
+        assert_eq!(Permissions::all().intersect(permissions), permissions);
+        assert!(
+            permissions
+                .check(Api::Exchange(ExchangeApi::Favourites))
+                .is_err()
+        );
+        assert_eq!(
+            Permissions::all().revoke(Capability::Read),
+            Permissions::all()
+        );
+    }
+}
@@ -0,0 +1,213 @@
+use crate::api::ApiGroup;
+use crate::errors::RichamsterError;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Sustained request rate with an allowed burst.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RateLimit {
+    burst: u32,
+    per_second: f64,
+}
+
+impl RateLimit {
+    pub fn per_second(requests: u32) -> Self {
+        Self::new(requests, Duration::from_secs(1))
+    }
+
+    pub fn per_minute(requests: u32) -> Self {
+        Self::new(requests, Duration::from_secs(60))
+    }
+
+    /// `requests` may be sent within every `period`, all of them at once if the
+    /// budget was not used before.
+    pub fn new(requests: u32, period: Duration) -> Self {
+        let requests = requests.max(1);
+        Self {
+            burst: requests,
+            per_second: requests as f64 / period.as_secs_f64(),
+        }
+    }
+
+    pub fn burst(mut self, burst: u32) -> Self {
+        self.burst = burst.max(1);
+        self
+    }
+}
+
+/// What happens to a request when its group ran out of budget.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LimitMode {
+    #[default]
+    Queue,
+    Reject,
+}
+
+#[derive(Debug)]
+struct TokenBucket {
+    limit: RateLimit,
+    tokens: f64,
+    rate: f64,
+    updated_at: Instant,
+    paused_until: Option<Instant>,
+}
+
+impl TokenBucket {
+    fn new(limit: RateLimit) -> Self {
+        Self {
+            limit,
+            tokens: limit.burst as f64,
+            rate: limit.per_second,
+            updated_at: Instant::now(),
+            paused_until: None,
+        }
+    }
+
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.updated_at).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.rate).min(self.limit.burst as f64);
+        self.updated_at = now;
+    }
+
+    /// Takes a token or tells how long to wait until one is available.
+    fn try_take(&mut self, now: Instant) -> Result<(), Duration> {
+        if let Some(paused_until) = self.paused_until {
+            if paused_until > now {
+                return Err(paused_until - now);
+            }
+            self.paused_until = None;
+            self.updated_at = now;
+        }
+        self.refill(now);
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64((1.0 - self.tokens) / self.rate))
+        }
+    }
+
+    fn throttled(&mut self, now: Instant, retry_after: Option<Duration>) {
+        self.refill(now);
+        self.rate = (self.rate / 2.0).max(self.limit.per_second / 16.0);
+        self.tokens = 0.0;
+        let pause = retry_after.unwrap_or_else(|| Duration::from_secs_f64(1.0 / self.rate));
+        self.paused_until = Some(now + pause);
+    }
+
+    fn succeeded(&mut self) {
+        if self.rate < self.limit.per_second {
+            self.rate = (self.rate + self.limit.per_second / 10.0).min(self.limit.per_second);
+        }
+    }
+}
+
+/// Client side token bucket limiter with independent budgets per [`ApiGroup`].
+/// Groups without a configured limit are not throttled. A `429 Too Many Requests`
+/// halves the rate of the affected group and pauses it for `Retry-After`; the rate
+/// then recovers gradually with every successful response.
+#[derive(Debug, Default)]
+pub struct RateLimiter {
+    mode: LimitMode,
+    buckets: HashMap<ApiGroup, Mutex<TokenBucket>>,
+}
+
+impl RateLimiter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn mode(mut self, mode: LimitMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    pub fn limit(mut self, group: ApiGroup, limit: RateLimit) -> Self {
+        self.buckets
+            .insert(group, Mutex::new(TokenBucket::new(limit)));
+        self
+    }
+
+    /// Currently allowed requests per second for the group, lowered after throttling.
+    pub fn current_rate(&self, group: ApiGroup) -> Option<f64> {
+        self.buckets
+            .get(&group)
+            .map(|bucket| bucket.lock().unwrap().rate)
+    }
+
+    pub async fn acquire(&self, group: ApiGroup) -> Result<(), RichamsterError> {
+        let Some(bucket) = self.buckets.get(&group) else {
+            return Ok(());
+        };
+        loop {
+            let wait = match bucket.lock().unwrap().try_take(Instant::now()) {
+                Ok(()) => return Ok(()),
+                Err(wait) => wait,
+            };
+            if self.mode == LimitMode::Reject {
+                return Err(RichamsterError::RateLimited(group));
+            }
+            tokio::time::sleep(wait).await;
+        }
+    }
+
+    pub fn on_throttled(&self, group: ApiGroup, retry_after: Option<Duration>) {
+        if let Some(bucket) = self.buckets.get(&group) {
+            bucket
+                .lock()
+                .unwrap()
+                .throttled(Instant::now(), retry_after);
+        }
+    }
+
+    pub fn on_success(&self, group: ApiGroup) {
+        if let Some(bucket) = self.buckets.get(&group) {
+            bucket.lock().unwrap().succeeded();
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn bucket_allows_burst_then_waits() {
+        let mut bucket = TokenBucket::new(RateLimit::per_second(2));
+        let now = Instant::now();
+        assert!(bucket.try_take(now).is_ok());
+        assert!(bucket.try_take(now).is_ok());
+        let wait = bucket.try_take(now).unwrap_err();
+        assert!(wait <= Duration::from_millis(500));
+        assert!(bucket.try_take(now + Duration::from_millis(500)).is_ok());
+    }
+
+    #[test]
+    fn throttling_halves_rate_and_pauses() {
+        let mut bucket = TokenBucket::new(RateLimit::per_second(10));
+        let now = Instant::now();
+        bucket.throttled(now, Some(Duration::from_secs(2)));
+        assert_eq!(bucket.rate, 5.0);
+        assert_eq!(bucket.try_take(now).unwrap_err(), Duration::from_secs(2));
+        bucket.succeeded();
+        assert_eq!(bucket.rate, 6.0);
+        for _ in 0..10 {
+            bucket.succeeded();
+        }
+        assert_eq!(bucket.rate, 10.0);
+    }
+
+    #[tokio::test]
+    async fn reject_mode_fails_fast() {
+        let limiter = RateLimiter::new()
+            .mode(LimitMode::Reject)
+            .limit(ApiGroup::Trading, RateLimit::per_minute(1));
+        assert!(limiter.acquire(ApiGroup::Trading).await.is_ok());
+        assert!(matches!(
+            limiter.acquire(ApiGroup::Trading).await,
+            Err(RichamsterError::RateLimited(ApiGroup::Trading))
+        ));
+        assert!(limiter.acquire(ApiGroup::PublicData).await.is_ok());
+    }
+}
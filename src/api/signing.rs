@@ -0,0 +1,134 @@
+use crate::api::transport::TransportRequest;
+use std::time::{SystemTime, UNIX_EPOCH};
+use url::form_urlencoded;
+
+pub const HEADER_SIGNATURE_VERSION: &str = "Signature-Version";
+pub const HEADER_TIMESTAMP: &str = "Timestamp";
+pub const HEADER_NONCE: &str = "Nonce";
+
+const V2_PREFIX: &str = "RICHAMSTER-HMAC-SHA256-V2";
+
+/// What the `Signature` header of a request authenticated with an API key covers.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SigningScheme {
+    /// HMAC of the JSON body alone, the scheme the Richamster API verifies.
+    /// Every request without a body has the same signature, so signed GET
+    /// requests can be replayed.
+    #[default]
+    Legacy,
+    /// HMAC of the [canonical request](canonical_request): method, path, sorted
+    /// query, body hash, and the `Timestamp` and `Nonce` headers, announced with
+    /// `Signature-Version: 2`. Only for servers known to verify it, the
+    /// Richamster API is not documented to.
+    V2,
+}
+
+impl SigningScheme {
+    /// Scheme a signed request claims: V2 when it carries `Signature-Version: 2`.
+    pub fn of(request: &TransportRequest) -> Self {
+        match request.header_value(HEADER_SIGNATURE_VERSION) {
+            Some("2") => SigningScheme::V2,
+            _ => SigningScheme::Legacy,
+        }
+    }
+
+    /// Adds the headers the scheme signs besides the request itself. Called
+    /// again for every attempt, so a retried request gets a fresh nonce.
+    pub(crate) fn stamp(self, request: TransportRequest) -> TransportRequest {
+        match self {
+            SigningScheme::Legacy => request,
+            SigningScheme::V2 => {
+                let timestamp = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .unwrap_or_default()
+                    .as_millis();
+                request
+                    .header(HEADER_SIGNATURE_VERSION, "2")
+                    .header(HEADER_TIMESTAMP, timestamp.to_string())
+                    .header(HEADER_NONCE, format!("{:032x}", fastrand::u128(..)))
+            }
+        }
+    }
+}
+
+/// String the HMAC is computed over. For [`SigningScheme::V2`] these are the
+/// lines:
+///
+/// ```text
+/// RICHAMSTER-HMAC-SHA256-V2
+/// GET
+/// /public/v1/user/balances/
+/// currency=BTC&page=2
+/// 1718000000000
+/// 3f9c...
+/// e3b0c442...
+/// ```
+///
+/// i.e. the method, the path, the query with its pairs sorted, the `Timestamp`
+/// and `Nonce` headers and the hex SHA-256 of the body (empty when there is none).
+pub fn canonical_request(scheme: SigningScheme, request: &TransportRequest) -> String {
+    let body = request.body.as_deref().unwrap_or("");
+    match scheme {
+        SigningScheme::Legacy => body.to_owned(),
+        SigningScheme::V2 => {
+            let mut pairs: Vec<_> = request.url.query_pairs().collect();
+            pairs.sort();
+            let query = form_urlencoded::Serializer::new(String::new())
+                .extend_pairs(pairs)
+                .finish();
+            [
+                V2_PREFIX,
+                request.method.as_str(),
+                request.url.path(),
+                &query,
+                request.header_value(HEADER_TIMESTAMP).unwrap_or(""),
+                request.header_value(HEADER_NONCE).unwrap_or(""),
+                &hex::encode(hmac_sha256::Hash::hash(body.as_bytes())),
+            ]
+            .join("\n")
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use reqwest::Method;
+    use url::Url;
+
+    fn request(url: &str) -> TransportRequest {
+        TransportRequest::new(Method::GET, Url::parse(url).unwrap())
+            .header(HEADER_TIMESTAMP, "1718000000000")
+            .header(HEADER_NONCE, "n1")
+    }
+
+    #[test]
+    fn query_order_does_not_change_canonical_request() {
+        let a = request("https://richamster.com/public/v1/user/orders/?pair=BTC%2FUAH&page=2");
+        let b = request("https://richamster.com/public/v1/user/orders/?page=2&pair=BTC%2FUAH");
+        assert_eq!(
+            canonical_request(SigningScheme::V2, &a),
+            canonical_request(SigningScheme::V2, &b)
+        );
+        assert_eq!(
+            canonical_request(SigningScheme::V2, &a),
+            "RICHAMSTER-HMAC-SHA256-V2\nGET\n/public/v1/user/orders/\npage=2&pair=BTC%2FUAH\n\
+             1718000000000\nn1\ne3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+    }
+
+    #[test]
+    fn stamped_requests_differ() {
+        let url = Url::parse("https://richamster.com/public/v1/user/balances/").unwrap();
+        let request = TransportRequest::new(Method::GET, url);
+        let first = SigningScheme::V2.stamp(request.clone());
+        let second = SigningScheme::V2.stamp(request.clone());
+        assert_eq!(SigningScheme::of(&first), SigningScheme::V2);
+        assert_ne!(
+            canonical_request(SigningScheme::V2, &first),
+            canonical_request(SigningScheme::V2, &second)
+        );
+        assert_eq!(SigningScheme::Legacy.stamp(request.clone()), request);
+        assert_eq!(canonical_request(SigningScheme::Legacy, &request), "");
+    }
+}
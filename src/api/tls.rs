@@ -0,0 +1,203 @@
+use crate::errors::RichamsterError;
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+
+const PEM_BEGIN: &str = "-----BEGIN CERTIFICATE-----";
+const PEM_END: &str = "-----END CERTIFICATE-----";
+
+/// TLS implementation used by the default transport.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TlsBackend {
+    /// Whatever reqwest was built with as its default.
+    #[default]
+    Default,
+    /// The platform TLS library: OpenSSL, Secure Transport or SChannel.
+    #[cfg(feature = "native-tls")]
+    NativeTls,
+    /// rustls, required for certificate pinning.
+    #[cfg(feature = "rustls-tls")]
+    Rustls,
+}
+
+/// Certificate in DER form, trusted as an additional root.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RootCertificate(Vec<u8>);
+
+impl RootCertificate {
+    pub fn from_der(der: impl Into<Vec<u8>>) -> Self {
+        Self(der.into())
+    }
+
+    /// Every certificate of a PEM bundle.
+    pub fn from_pem_bundle(pem: &[u8]) -> Result<Vec<Self>, RichamsterError> {
+        let pem = std::str::from_utf8(pem)
+            .map_err(|_| RichamsterError::Tls("PEM bundle is not valid UTF-8".to_owned()))?;
+        let mut certificates = vec![];
+        let mut rest = pem;
+        while let Some(start) = rest.find(PEM_BEGIN) {
+            let body = &rest[start + PEM_BEGIN.len()..];
+            let end = body
+                .find(PEM_END)
+                .ok_or_else(|| RichamsterError::Tls("unterminated PEM certificate".to_owned()))?;
+            let encoded: String = body[..end].split_whitespace().collect();
+            let der = STANDARD
+                .decode(encoded)
+                .map_err(|e| RichamsterError::Tls(format!("invalid PEM certificate: {e}")))?;
+            certificates.push(Self(der));
+            rest = &body[end + PEM_END.len()..];
+        }
+        if certificates.is_empty() {
+            return Err(RichamsterError::Tls(
+                "no certificate found in PEM bundle".to_owned(),
+            ));
+        }
+        Ok(certificates)
+    }
+
+    pub fn der(&self) -> &[u8] {
+        &self.0
+    }
+
+    /// SHA-256 of the DER encoding, the value expected by
+    /// [`ClientConfig::pin_certificate`](crate::api::client::ClientConfig::pin_certificate).
+    pub fn fingerprint(&self) -> [u8; 32] {
+        hmac_sha256::Hash::hash(&self.0)
+    }
+}
+
+/// SHA-256 fingerprints of the leaf certificates accepted for a host.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CertificatePin {
+    pub host: String,
+    pub fingerprints: Vec<[u8; 32]>,
+}
+
+#[cfg(feature = "rustls-tls")]
+pub(crate) mod pinning {
+    use super::{CertificatePin, RootCertificate};
+    use crate::errors::RichamsterError;
+    use rustls::client::WebPkiServerVerifier;
+    use rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
+    use rustls::crypto::ring;
+    use rustls::pki_types::{CertificateDer, ServerName, UnixTime};
+    use rustls::{DigitallySignedStruct, RootCertStore, SignatureScheme};
+    use std::sync::Arc;
+
+    /// Validates the chain as usual, then rejects a pinned host whose leaf
+    /// certificate matches none of its fingerprints.
+    #[derive(Debug)]
+    struct PinnedVerifier {
+        inner: Arc<WebPkiServerVerifier>,
+        pins: Vec<CertificatePin>,
+    }
+
+    impl ServerCertVerifier for PinnedVerifier {
+        fn verify_server_cert(
+            &self,
+            end_entity: &CertificateDer<'_>,
+            intermediates: &[CertificateDer<'_>],
+            server_name: &ServerName<'_>,
+            ocsp_response: &[u8],
+            now: UnixTime,
+        ) -> Result<ServerCertVerified, rustls::Error> {
+            let verified = self.inner.verify_server_cert(
+                end_entity,
+                intermediates,
+                server_name,
+                ocsp_response,
+                now,
+            )?;
+            let host = server_name.to_str();
+            let fingerprint = hmac_sha256::Hash::hash(end_entity.as_ref());
+            let rejected = self
+                .pins
+                .iter()
+                .filter(|pin| pin.host.eq_ignore_ascii_case(&host))
+                .any(|pin| !pin.fingerprints.contains(&fingerprint));
+            if rejected {
+                return Err(rustls::Error::General(format!(
+                    "certificate of {host} does not match its pin"
+                )));
+            }
+            Ok(verified)
+        }
+
+        fn verify_tls12_signature(
+            &self,
+            message: &[u8],
+            cert: &CertificateDer<'_>,
+            dss: &DigitallySignedStruct,
+        ) -> Result<HandshakeSignatureValid, rustls::Error> {
+            self.inner.verify_tls12_signature(message, cert, dss)
+        }
+
+        fn verify_tls13_signature(
+            &self,
+            message: &[u8],
+            cert: &CertificateDer<'_>,
+            dss: &DigitallySignedStruct,
+        ) -> Result<HandshakeSignatureValid, rustls::Error> {
+            self.inner.verify_tls13_signature(message, cert, dss)
+        }
+
+        fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+            self.inner.supported_verify_schemes()
+        }
+    }
+
+    /// rustls configuration trusting the webpki roots, unless disabled, and
+    /// `roots`, with `pins` enforced on top of chain validation.
+    pub(crate) fn client_config(
+        roots: &[RootCertificate],
+        built_in_roots: bool,
+        pins: Vec<CertificatePin>,
+    ) -> Result<rustls::ClientConfig, RichamsterError> {
+        let tls_error = |e: &dyn std::fmt::Display| RichamsterError::Tls(e.to_string());
+        let mut store = RootCertStore::empty();
+        if built_in_roots {
+            store.extend(webpki_roots::TLS_SERVER_ROOTS.iter().cloned());
+        }
+        for root in roots {
+            store
+                .add(CertificateDer::from(root.der().to_vec()))
+                .map_err(|e| tls_error(&e))?;
+        }
+        let provider = Arc::new(ring::default_provider());
+        let inner = WebPkiServerVerifier::builder_with_provider(Arc::new(store), provider.clone())
+            .build()
+            .map_err(|e| tls_error(&e))?;
+        let verifier = PinnedVerifier { inner, pins };
+        let mut config = rustls::ClientConfig::builder_with_provider(provider)
+            .with_safe_default_protocol_versions()
+            .map_err(|e| tls_error(&e))?
+            .dangerous()
+            .with_custom_certificate_verifier(Arc::new(verifier))
+            .with_no_client_auth();
+        config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+        Ok(config)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const CERTIFICATE: &str =
+        "-----BEGIN CERTIFICATE-----\nAQID\nBA==\n-----END CERTIFICATE-----\n";
+
+    #[test]
+    fn pem_bundle_is_decoded() {
+        let bundle = format!("{CERTIFICATE}junk\n{CERTIFICATE}");
+        let certificates = RootCertificate::from_pem_bundle(bundle.as_bytes()).unwrap();
+        assert_eq!(certificates.len(), 2);
+        assert_eq!(certificates[0].der(), &[1, 2, 3, 4]);
+        assert_eq!(
+            certificates[0].fingerprint(),
+            hmac_sha256::Hash::hash(&[1, 2, 3, 4])
+        );
+        assert!(matches!(
+            RootCertificate::from_pem_bundle(b"not a certificate"),
+            Err(RichamsterError::Tls(_))
+        ));
+    }
+}
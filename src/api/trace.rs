@@ -0,0 +1,123 @@
+use reqwest::header::HeaderMap;
+use serde_json::Value;
+use std::sync::atomic::{AtomicU64, Ordering};
+use tracing::{Level, event};
+
+const REDACTED: &str = "[REDACTED]";
+
+const SENSITIVE_HEADERS: [&str; 5] = [
+    "authorization",
+    "api-key",
+    "signature",
+    "cookie",
+    "set-cookie",
+];
+
+const SENSITIVE_FIELDS: [&str; 11] = [
+    "password",
+    "password1",
+    "password2",
+    "pin_code",
+    "otp_token",
+    "ephemeral_token",
+    "token",
+    "access",
+    "refresh",
+    "secret_key",
+    "bank_card",
+];
+
+static REQUEST_ID: AtomicU64 = AtomicU64::new(1);
+
+/// Process wide sequence number attached to the span of every API call.
+pub(crate) fn next_request_id() -> u64 {
+    REQUEST_ID.fetch_add(1, Ordering::Relaxed)
+}
+
+/// Emits the redacted body at the level chosen with
+/// [`ClientConfig::log_bodies`](crate::api::client::ClientConfig::log_bodies).
+pub(crate) fn log_body(level: Level, direction: &str, body: &str) {
+    let body = redact_body(body);
+    match level {
+        Level::ERROR => event!(Level::ERROR, direction, %body, "body"),
+        Level::WARN => event!(Level::WARN, direction, %body, "body"),
+        Level::INFO => event!(Level::INFO, direction, %body, "body"),
+        Level::DEBUG => event!(Level::DEBUG, direction, %body, "body"),
+        _ => event!(Level::TRACE, direction, %body, "body"),
+    }
+}
+
+/// Header names and values with credentials replaced, for logging.
+pub fn redact_headers(headers: &HeaderMap) -> Vec<(String, String)> {
+    headers
+        .iter()
+        .map(|(name, value)| {
+            let value = if SENSITIVE_HEADERS.contains(&name.as_str()) {
+                REDACTED.to_owned()
+            } else {
+                value.to_str().unwrap_or("<binary>").to_owned()
+            };
+            (name.as_str().to_owned(), value)
+        })
+        .collect()
+}
+
+/// JSON body with passwords, PIN codes, OTP and JWT tokens replaced, for logging.
+/// Bodies that are not JSON are not logged, only their length.
+pub fn redact_body(body: &str) -> String {
+    match serde_json::from_str::<Value>(body) {
+        Ok(mut value) => {
+            redact_value(&mut value);
+            value.to_string()
+        }
+        Err(_) if body.is_empty() => String::new(),
+        Err(_) => format!("<{} bytes>", body.len()),
+    }
+}
+
+pub(crate) fn redact_value(value: &mut Value) {
+    match value {
+        Value::Object(fields) => {
+            for (key, field) in fields.iter_mut() {
+                if SENSITIVE_FIELDS.contains(&key.as_str()) {
+                    *field = Value::String(REDACTED.to_owned());
+                } else {
+                    redact_value(field);
+                }
+            }
+        }
+        Value::Array(items) => items.iter_mut().for_each(redact_value),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn credentials_headers_are_redacted() {
+        let mut headers = HeaderMap::new();
+        headers.insert("Authorization", "JWT abc".parse().unwrap());
+        headers.insert("Api-Key", "key".parse().unwrap());
+        headers.insert("Signature", "0f0f".parse().unwrap());
+        headers.insert("Content-Type", "application/json".parse().unwrap());
+        let redacted = redact_headers(&headers);
+        assert!(redacted.contains(&("authorization".to_owned(), REDACTED.to_owned())));
+        assert!(redacted.contains(&("api-key".to_owned(), REDACTED.to_owned())));
+        assert!(redacted.contains(&("signature".to_owned(), REDACTED.to_owned())));
+        assert!(redacted.contains(&("content-type".to_owned(), "application/json".to_owned())));
+    }
+
+    #[test]
+    fn sensitive_fields_are_redacted_recursively() {
+        let body =
+            r#"{"email":"a@b.c","password":"hunter2","nested":[{"pin_code":"1234","sum":"1"}]}"#;
+        let redacted = redact_body(body);
+        assert!(!redacted.contains("hunter2"));
+        assert!(!redacted.contains("1234"));
+        assert!(redacted.contains("a@b.c"));
+        assert!(redacted.contains(r#""sum":"1""#));
+        assert_eq!(redact_body("<html>"), "<6 bytes>");
+    }
+}
@@ -0,0 +1,233 @@
+use crate::errors::RichamsterError;
+use async_trait::async_trait;
+use reqwest::header::{CONTENT_TYPE, HeaderMap, HeaderName, HeaderValue};
+use reqwest::{Client, Method, StatusCode};
+use serde::de::DeserializeOwned;
+use std::collections::{HashMap, VecDeque};
+use std::fmt::Debug;
+use std::sync::Mutex;
+use url::Url;
+
+/// Fully prepared request, including authentication headers.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TransportRequest {
+    pub method: Method,
+    pub url: Url,
+    pub headers: HeaderMap,
+    pub body: Option<String>,
+}
+
+impl TransportRequest {
+    pub fn new(method: Method, url: Url) -> Self {
+        Self {
+            method,
+            url,
+            headers: HeaderMap::new(),
+            body: None,
+        }
+    }
+
+    pub fn header(mut self, name: &str, value: impl AsRef<str>) -> Self {
+        if let (Ok(name), Ok(value)) = (
+            HeaderName::from_bytes(name.as_bytes()),
+            HeaderValue::from_str(value.as_ref()),
+        ) {
+            self.headers.insert(name, value);
+        }
+        self
+    }
+
+    pub fn json_body(mut self, body: String) -> Self {
+        self.headers
+            .insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+        self.body = Some(body);
+        self
+    }
+
+    pub fn header_value(&self, name: &str) -> Option<&str> {
+        self.headers.get(name).and_then(|v| v.to_str().ok())
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct TransportResponse {
+    status: StatusCode,
+    headers: HeaderMap,
+    body: String,
+}
+
+impl TransportResponse {
+    pub fn new(status: StatusCode, body: impl Into<String>) -> Self {
+        Self {
+            status,
+            headers: HeaderMap::new(),
+            body: body.into(),
+        }
+    }
+
+    pub fn with_headers(mut self, headers: HeaderMap) -> Self {
+        self.headers = headers;
+        self
+    }
+
+    pub fn status(&self) -> StatusCode {
+        self.status
+    }
+
+    pub fn headers(&self) -> &HeaderMap {
+        &self.headers
+    }
+
+    pub fn text(&self) -> &str {
+        &self.body
+    }
+
+    pub fn json<T: DeserializeOwned>(&self) -> Result<T, serde_json::Error> {
+        serde_json::from_str(&self.body)
+    }
+}
+
+/// Executes prepared requests. The services never talk to the network directly,
+/// which allows replacing HTTP with anything able to produce a status, headers and body.
+#[async_trait]
+pub trait Transport: Debug + Send + Sync {
+    async fn send(&self, request: TransportRequest) -> Result<TransportResponse, RichamsterError>;
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct ReqwestTransport {
+    client: Client,
+}
+
+impl ReqwestTransport {
+    pub fn new(client: Client) -> Self {
+        Self { client }
+    }
+}
+
+#[async_trait]
+impl Transport for ReqwestTransport {
+    async fn send(&self, request: TransportRequest) -> Result<TransportResponse, RichamsterError> {
+        let mut builder = self
+            .client
+            .request(request.method, request.url)
+            .headers(request.headers);
+        if let Some(body) = request.body {
+            builder = builder.body(body);
+        }
+        let response = builder.send().await?;
+        let status = response.status();
+        let headers = response.headers().clone();
+        let body = response.text().await?;
+        Ok(TransportResponse::new(status, body).with_headers(headers))
+    }
+}
+
+/// Transport serving canned responses and recording every request it receives.
+/// Responses are registered per method and URL path suffix (for example
+/// `user/balances/`) and consumed in order; the last one for a route is repeated.
+/// Requests without a matching response get `404 Not Found`.
+#[derive(Debug, Default)]
+pub struct InMemoryTransport {
+    responses: Mutex<HashMap<(Method, String), VecDeque<TransportResponse>>>,
+    requests: Mutex<Vec<TransportRequest>>,
+}
+
+impl InMemoryTransport {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn respond(&self, method: Method, path: &str, response: TransportResponse) -> &Self {
+        self.responses
+            .lock()
+            .unwrap()
+            .entry((method, path.to_owned()))
+            .or_default()
+            .push_back(response);
+        self
+    }
+
+    pub fn requests(&self) -> Vec<TransportRequest> {
+        self.requests.lock().unwrap().clone()
+    }
+
+    pub fn last_request(&self) -> Option<TransportRequest> {
+        self.requests.lock().unwrap().last().cloned()
+    }
+
+    fn next_response(&self, request: &TransportRequest) -> Option<TransportResponse> {
+        let mut responses = self.responses.lock().unwrap();
+        let (_, queue) = responses
+            .iter_mut()
+            .filter(|((method, path), _)| {
+                *method == request.method && request.url.path().ends_with(path.as_str())
+            })
+            .max_by_key(|((_, path), _)| path.len())?;
+        if queue.len() > 1 {
+            queue.pop_front()
+        } else {
+            queue.front().cloned()
+        }
+    }
+}
+
+#[async_trait]
+impl Transport for InMemoryTransport {
+    async fn send(&self, request: TransportRequest) -> Result<TransportResponse, RichamsterError> {
+        let response = self
+            .next_response(&request)
+            .unwrap_or_else(|| TransportResponse::new(StatusCode::NOT_FOUND, ""));
+        self.requests.lock().unwrap().push(request);
+        Ok(response)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::str::FromStr;
+
+    fn request(method: Method, url: &str) -> TransportRequest {
+        TransportRequest::new(method, Url::from_str(url).unwrap())
+    }
+
+    #[tokio::test]
+    async fn in_memory_transport_replays_responses_in_order() {
+        let transport = InMemoryTransport::new();
+        transport
+            .respond(
+                Method::GET,
+                "user/balances/",
+                TransportResponse::new(StatusCode::SERVICE_UNAVAILABLE, ""),
+            )
+            .respond(
+                Method::GET,
+                "user/balances/",
+                TransportResponse::new(StatusCode::OK, "[]"),
+            );
+        let url = "https://richamster.com/public/v1/user/balances/";
+        let first = transport.send(request(Method::GET, url)).await.unwrap();
+        let second = transport.send(request(Method::GET, url)).await.unwrap();
+        let third = transport.send(request(Method::GET, url)).await.unwrap();
+        assert_eq!(first.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(second.status(), StatusCode::OK);
+        assert_eq!(third.text(), "[]");
+        let missing = transport.send(request(Method::POST, url)).await.unwrap();
+        assert_eq!(missing.status(), StatusCode::NOT_FOUND);
+        assert_eq!(transport.requests().len(), 4);
+    }
+
+    #[test]
+    fn json_body_sets_content_type() {
+        let request = request(Method::POST, "https://richamster.com/")
+            .json_body("{}".to_owned())
+            .header("api-key", "key");
+        assert_eq!(
+            request.header_value("content-type"),
+            Some("application/json")
+        );
+        assert_eq!(request.header_value("Api-Key"), Some("key"));
+        assert_eq!(request.body.as_deref(), Some("{}"));
+    }
+}
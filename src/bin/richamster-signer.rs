@@ -0,0 +1,76 @@
+//! Signing daemon holding the API secret key, so processes sending requests
+//! only need the socket, see `richamster_api::richamster::signer::UnixSocketSigner`.
+//!
+//! ```text
+//! richamster-signer <socket path>
+//! ```
+//!
+//! The secret key is read from the file named by `RICHAMSTER_SECRET_KEY_FILE`,
+//! or else from the first line of standard input. The socket is created with
+//! owner-only permissions. An existing socket is only replaced when no signer
+//! answers on it.
+
+#[cfg(unix)]
+#[tokio::main(flavor = "current_thread")]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    use richamster_api::richamster::common::SecretKey;
+    use richamster_api::richamster::signer::serve;
+    use std::os::unix::fs::{DirBuilderExt, FileTypeExt, PermissionsExt};
+    use std::os::unix::net::UnixStream;
+    use std::path::Path;
+    use std::sync::Arc;
+    use tokio::net::UnixListener;
+    use zeroize::Zeroizing;
+
+    let Some(socket) = std::env::args().nth(1) else {
+        eprintln!("usage: richamster-signer <socket path>");
+        std::process::exit(2);
+    };
+    let secret = Zeroizing::new(match std::env::var_os("RICHAMSTER_SECRET_KEY_FILE") {
+        Some(path) => std::fs::read_to_string(path)?,
+        None => {
+            let mut line = String::new();
+            std::io::stdin().read_line(&mut line)?;
+            line
+        }
+    });
+    let key = SecretKey::new(secret.trim().to_owned());
+    drop(secret);
+
+    // Only a stale socket may be replaced, never a live signer or another file.
+    let socket = Path::new(&socket);
+    match std::fs::symlink_metadata(socket) {
+        Ok(metadata) if !metadata.file_type().is_socket() => {
+            return Err(format!("{} exists and is not a socket", socket.display()).into());
+        }
+        Ok(_) if UnixStream::connect(socket).is_ok() => {
+            return Err(format!("{} is in use by a running signer", socket.display()).into());
+        }
+        Ok(_) => {}
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+        Err(e) => return Err(e.into()),
+    }
+
+    // Bind inside a directory only the owner can enter, then move the socket
+    // into place, so it is never reachable with looser permissions.
+    let mut private = socket.as_os_str().to_owned();
+    private.push(format!(".{}.d", std::process::id()));
+    let private = Path::new(&private);
+    std::fs::DirBuilder::new().mode(0o700).create(private)?;
+    let staged = private.join("socket");
+    let bound = UnixListener::bind(&staged).and_then(|listener| {
+        std::fs::set_permissions(&staged, std::fs::Permissions::from_mode(0o600))?;
+        std::fs::rename(&staged, socket)?;
+        Ok(listener)
+    });
+    let _ = std::fs::remove_file(&staged);
+    std::fs::remove_dir(private)?;
+    serve(bound?, Arc::new(key)).await?;
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn main() {
+    eprintln!("richamster-signer needs Unix domain sockets");
+    std::process::exit(1);
+}
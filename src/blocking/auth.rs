@@ -0,0 +1,54 @@
+use crate::api::client::RichamsterClient;
+use crate::blocking::block_on;
+use crate::errors::RichamsterError;
+use crate::models::auth::{
+    LoginResponse, OtpLoginResponse, RegisterUser, RegisterUserResponse, TokenData,
+};
+use crate::richamster::auth;
+
+#[derive(Clone, Debug, Default)]
+pub struct Auth {
+    inner: auth::Auth,
+}
+
+impl Auth {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn client(mut self, client: RichamsterClient) -> Self {
+        self.inner = self.inner.client(client);
+        self
+    }
+}
+
+impl From<auth::Auth> for Auth {
+    fn from(inner: auth::Auth) -> Self {
+        Self { inner }
+    }
+}
+
+impl Auth {
+    pub fn login(
+        &self,
+        email: impl AsRef<str>,
+        password: impl AsRef<str>,
+    ) -> Result<LoginResponse, RichamsterError> {
+        block_on(self.inner.login(email, password))
+    }
+
+    pub fn register_user(
+        &self,
+        register_user: RegisterUser,
+    ) -> Result<RegisterUserResponse, RichamsterError> {
+        block_on(self.inner.register_user(register_user))
+    }
+
+    pub fn two_factor_login(&self, otp_token: String) -> Result<OtpLoginResponse, RichamsterError> {
+        block_on(self.inner.two_factor_login(otp_token))
+    }
+
+    pub fn refresh_token(&self, jwt_token: String) -> Result<TokenData, RichamsterError> {
+        block_on(self.inner.refresh_token(jwt_token))
+    }
+}
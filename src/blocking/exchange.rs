@@ -0,0 +1,181 @@
+use crate::api::client::RichamsterClient;
+use crate::api::permissions::Permissions;
+use crate::api::token;
+use crate::api::token::CurrencyPair;
+use crate::blocking::block_on;
+use crate::errors::RichamsterError;
+use crate::models::common::OrderType;
+use crate::models::exchange::{
+    CurrencyInfoResponse, CurrencyPairRestriction, FavouritePairResponse, Market,
+    MarketOrderCalculator, MarketOrderResponse, NewOrder, OrderBookFilter, OrdersBook,
+    OrdersFilter, OrdersHistory, TickerResponse,
+};
+use crate::richamster::common::SharedAuthState;
+use crate::richamster::credentials::CredentialProvider;
+use crate::richamster::exchange::{self, OrderDedup};
+use crate::richamster::journal::OrderJournal;
+use crate::richamster::markets::MarketRegistry;
+use crate::richamster::session::Session;
+use crate::richamster::signer::Signer;
+use std::sync::Arc;
+use url::Url;
+
+#[derive(Clone, Debug, Default)]
+pub struct Exchange {
+    inner: exchange::Exchange,
+}
+
+impl Exchange {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_jwt_token(token: String) -> Self {
+        exchange::Exchange::with_jwt_token(token).into()
+    }
+
+    pub fn with_keys(api_key: String, secret_key: String) -> Self {
+        exchange::Exchange::with_keys(api_key, secret_key).into()
+    }
+
+    pub fn with_jwt_and_keys(jwt: String, api_key: String, secret_key: String) -> Self {
+        exchange::Exchange::with_jwt_and_keys(jwt, api_key, secret_key).into()
+    }
+
+    pub fn with_session(session: Session) -> Self {
+        exchange::Exchange::with_session(session).into()
+    }
+
+    pub fn with_signer(api_key: String, signer: Arc<dyn Signer>) -> Self {
+        exchange::Exchange::with_signer(api_key, signer).into()
+    }
+
+    pub fn with_credentials(provider: &impl CredentialProvider) -> Result<Self, RichamsterError> {
+        exchange::Exchange::with_credentials(provider).map(Into::into)
+    }
+
+    pub fn auth_state(&self) -> &SharedAuthState {
+        self.inner.auth_state()
+    }
+
+    pub fn restrict(mut self, permissions: Permissions) -> Self {
+        self.inner = self.inner.restrict(permissions);
+        self
+    }
+
+    pub fn client(mut self, client: RichamsterClient) -> Self {
+        self.inner = self.inner.client(client);
+        self
+    }
+
+    pub fn market_registry(mut self, markets: MarketRegistry) -> Self {
+        self.inner = self.inner.market_registry(markets);
+        self
+    }
+
+    pub fn markets(&self) -> &MarketRegistry {
+        self.inner.markets()
+    }
+
+    pub fn order_journal(mut self, journal: OrderJournal) -> Self {
+        self.inner = self.inner.order_journal(journal);
+        self
+    }
+
+    pub fn journal(&self) -> &OrderJournal {
+        self.inner.journal()
+    }
+}
+
+impl From<exchange::Exchange> for Exchange {
+    fn from(inner: exchange::Exchange) -> Self {
+        Self { inner }
+    }
+}
+
+impl Exchange {
+    pub fn restrictions_list(&self) -> Result<Vec<CurrencyPairRestriction>, RichamsterError> {
+        block_on(self.inner.restrictions_list())
+    }
+
+    pub fn ticker_list(
+        &self,
+        pair: Option<CurrencyPair>,
+    ) -> Result<TickerResponse, RichamsterError> {
+        block_on(self.inner.ticker_list(pair))
+    }
+
+    pub fn favourites_pair_toggle(
+        &self,
+        pair: CurrencyPair,
+    ) -> Result<FavouritePairResponse, RichamsterError> {
+        block_on(self.inner.favourites_pair_toggle(pair))
+    }
+
+    pub fn currencies_list(
+        &self,
+        token: Option<token::Token>,
+    ) -> Result<CurrencyInfoResponse, RichamsterError> {
+        block_on(self.inner.currencies_list(token))
+    }
+
+    pub fn markets_list(&self) -> Result<Vec<Market>, RichamsterError> {
+        block_on(self.inner.markets_list())
+    }
+
+    pub fn order_book(&self, filter: OrderBookFilter) -> Result<OrdersBook, RichamsterError> {
+        block_on(self.inner.order_book(filter))
+    }
+
+    pub fn orders_history(&self, filter: OrdersFilter) -> Result<OrdersHistory, RichamsterError> {
+        block_on(self.inner.orders_history(filter))
+    }
+
+    pub fn next_orders_history(&self, url: Url) -> Result<OrdersHistory, RichamsterError> {
+        block_on(self.inner.next_orders_history(url))
+    }
+
+    pub fn destroy_user_order(&self, id: i32) -> Result<(), RichamsterError> {
+        block_on(self.inner.destroy_user_order(id))
+    }
+
+    pub fn user_orders(&self, filter: OrdersFilter) -> Result<OrdersHistory, RichamsterError> {
+        block_on(self.inner.user_orders(filter))
+    }
+
+    pub fn create_order(&self, order: NewOrder) -> Result<NewOrder, RichamsterError> {
+        block_on(self.inner.create_order(order))
+    }
+
+    /// See [`exchange::Exchange::create_order_with_dedup`]. The lookup receives the
+    /// async service it is called from.
+    pub fn create_order_with_dedup<D: OrderDedup>(
+        &self,
+        order: NewOrder,
+        dedup: &D,
+    ) -> Result<NewOrder, RichamsterError> {
+        block_on(self.inner.create_order_with_dedup(order, dedup))
+    }
+
+    pub fn calculate_market_order(
+        &self,
+        pair: CurrencyPair,
+        amount: f64,
+        order_type: OrderType,
+    ) -> Result<MarketOrderCalculator, RichamsterError> {
+        block_on(self.inner.calculate_market_order(pair, amount, order_type))
+    }
+
+    pub fn execute_market_order(
+        &self,
+        pair: CurrencyPair,
+        amount: f64,
+        order_type: OrderType,
+        total: Option<f64>,
+    ) -> Result<MarketOrderResponse, RichamsterError> {
+        block_on(
+            self.inner
+                .execute_market_order(pair, amount, order_type, total),
+        )
+    }
+}
@@ -0,0 +1,119 @@
+use crate::api::client::RichamsterClient;
+use crate::api::permissions::Permissions;
+use crate::blocking::auth::Auth;
+use crate::blocking::exchange::Exchange;
+use crate::blocking::feedback::Feedback;
+use crate::blocking::login::LoginFlow;
+use crate::blocking::replenish::Replenish;
+use crate::blocking::user::User;
+use crate::blocking::withdraw::Withdraw;
+use crate::errors::RichamsterError;
+use crate::richamster::common::{AuthState, SharedAuthState};
+use crate::richamster::credentials::CredentialProvider;
+use crate::richamster::facade;
+use crate::richamster::session::Session;
+use crate::richamster::signer::Signer;
+use std::sync::Arc;
+
+#[derive(Clone, Debug, Default)]
+pub struct Richamster {
+    inner: facade::Richamster,
+}
+
+impl From<facade::Richamster> for Richamster {
+    fn from(inner: facade::Richamster) -> Self {
+        Self { inner }
+    }
+}
+
+impl Richamster {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_auth_state(auth_state: AuthState) -> Self {
+        facade::Richamster::with_auth_state(auth_state).into()
+    }
+
+    pub fn with_jwt_token(token: String) -> Self {
+        facade::Richamster::with_jwt_token(token).into()
+    }
+
+    pub fn with_keys(api_key: String, secret_key: String) -> Self {
+        facade::Richamster::with_keys(api_key, secret_key).into()
+    }
+
+    pub fn with_jwt_and_keys(jwt: String, api_key: String, secret_key: String) -> Self {
+        facade::Richamster::with_jwt_and_keys(jwt, api_key, secret_key).into()
+    }
+
+    pub fn with_session(session: Session) -> Self {
+        facade::Richamster::with_session(session).into()
+    }
+
+    pub fn with_signer(api_key: String, signer: Arc<dyn Signer>) -> Self {
+        facade::Richamster::with_signer(api_key, signer).into()
+    }
+
+    pub fn with_credentials(provider: &impl CredentialProvider) -> Result<Self, RichamsterError> {
+        facade::Richamster::with_credentials(provider).map(Into::into)
+    }
+
+    pub fn client(mut self, client: RichamsterClient) -> Self {
+        self.inner = self.inner.client(client);
+        self
+    }
+
+    pub fn auth_state(&self) -> &SharedAuthState {
+        self.inner.auth_state()
+    }
+
+    pub fn restrict(mut self, permissions: Permissions) -> Self {
+        self.inner = self.inner.restrict(permissions);
+        self
+    }
+
+    pub fn exchange(&self) -> Exchange {
+        self.inner.exchange().into()
+    }
+
+    pub fn user(&self) -> User {
+        self.inner.user().into()
+    }
+
+    pub fn wallet(&self) -> Wallet {
+        let wallet = self.inner.wallet();
+        Wallet {
+            withdraw: wallet.withdraw.into(),
+            replenish: wallet.replenish.into(),
+        }
+    }
+
+    pub fn auth(&self) -> Auth {
+        self.inner.auth().into()
+    }
+
+    pub fn login(&self) -> LoginFlow {
+        self.inner.login().into()
+    }
+
+    pub fn feedback(&self) -> Feedback {
+        self.inner.feedback().into()
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Wallet {
+    withdraw: Withdraw,
+    replenish: Replenish,
+}
+
+impl Wallet {
+    pub fn withdraw(&self) -> &Withdraw {
+        &self.withdraw
+    }
+
+    pub fn replenish(&self) -> &Replenish {
+        &self.replenish
+    }
+}
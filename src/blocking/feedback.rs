@@ -0,0 +1,33 @@
+use crate::api::client::RichamsterClient;
+use crate::blocking::block_on;
+use crate::errors::RichamsterError;
+use crate::models::feedback::Messenger;
+use crate::richamster::feedback;
+
+#[derive(Clone, Debug, Default)]
+pub struct Feedback {
+    inner: feedback::Feedback,
+}
+
+impl Feedback {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn client(mut self, client: RichamsterClient) -> Self {
+        self.inner = self.inner.client(client);
+        self
+    }
+}
+
+impl From<feedback::Feedback> for Feedback {
+    fn from(inner: feedback::Feedback) -> Self {
+        Self { inner }
+    }
+}
+
+impl Feedback {
+    pub fn messengers_list(&self) -> Result<Vec<Messenger>, RichamsterError> {
+        block_on(self.inner.messengers_list())
+    }
+}
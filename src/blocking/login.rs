@@ -0,0 +1,74 @@
+use crate::api::client::RichamsterClient;
+use crate::blocking::block_on;
+use crate::errors::RichamsterError;
+use crate::richamster::login;
+use crate::richamster::session::Session;
+#[cfg(feature = "totp")]
+use crate::richamster::totp::Totp;
+
+#[derive(Debug)]
+pub enum LoginStep {
+    Authenticated(Session),
+    TwoFactorRequired(PendingTwoFactor),
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct LoginFlow {
+    inner: login::LoginFlow,
+}
+
+impl From<login::LoginFlow> for LoginFlow {
+    fn from(inner: login::LoginFlow) -> Self {
+        Self { inner }
+    }
+}
+
+impl LoginFlow {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn client(mut self, client: RichamsterClient) -> Self {
+        self.inner = self.inner.client(client);
+        self
+    }
+
+    pub fn start(
+        &self,
+        email: impl AsRef<str>,
+        password: impl AsRef<str>,
+    ) -> Result<LoginStep, RichamsterError> {
+        Ok(match block_on(self.inner.start(email, password))? {
+            login::LoginStep::Authenticated(session) => LoginStep::Authenticated(session),
+            login::LoginStep::TwoFactorRequired(inner) => {
+                LoginStep::TwoFactorRequired(PendingTwoFactor { inner })
+            }
+        })
+    }
+
+    #[cfg(feature = "totp")]
+    pub fn login_with_totp(
+        &self,
+        email: impl AsRef<str>,
+        password: impl AsRef<str>,
+        totp: &Totp,
+    ) -> Result<Session, RichamsterError> {
+        block_on(self.inner.login_with_totp(email, password, totp))
+    }
+}
+
+#[derive(Debug)]
+pub struct PendingTwoFactor {
+    inner: Box<login::PendingTwoFactor>,
+}
+
+impl PendingTwoFactor {
+    pub fn complete(self, otp_token: impl Into<String>) -> Result<Session, RichamsterError> {
+        block_on(self.inner.complete(otp_token))
+    }
+
+    #[cfg(feature = "totp")]
+    pub fn complete_with_totp(self, totp: &Totp) -> Result<Session, RichamsterError> {
+        block_on(self.inner.complete_with_totp(totp))
+    }
+}
@@ -0,0 +1,29 @@
+//! Synchronous versions of the [`richamster`](crate::richamster) services.
+//!
+//! Every call drives the async implementation to completion on a runtime owned
+//! by this module, so the services must not be used from within an async context.
+
+use once_cell::sync::Lazy;
+use tokio::runtime::{Builder, Runtime};
+
+pub mod auth;
+pub mod exchange;
+pub mod facade;
+pub mod feedback;
+pub mod login;
+pub mod replenish;
+pub mod user;
+pub mod withdraw;
+
+pub use facade::{Richamster, Wallet};
+
+static RUNTIME: Lazy<Runtime> = Lazy::new(|| {
+    Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .expect("failed to build runtime for blocking services")
+});
+
+pub(crate) fn block_on<F: Future>(future: F) -> F::Output {
+    RUNTIME.block_on(future)
+}
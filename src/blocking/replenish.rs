@@ -0,0 +1,89 @@
+use crate::api::client::RichamsterClient;
+use crate::api::permissions::Permissions;
+use crate::api::token::Token;
+use crate::blocking::block_on;
+use crate::errors::RichamsterError;
+use crate::models::common::CurrencyChannel;
+use crate::models::replenish::{P2PReplenish, ReplenishInfo};
+use crate::richamster::common::SharedAuthState;
+use crate::richamster::credentials::CredentialProvider;
+use crate::richamster::replenish;
+use crate::richamster::session::Session;
+use crate::richamster::signer::Signer;
+use std::sync::Arc;
+
+#[derive(Clone, Debug, Default)]
+pub struct Replenish {
+    inner: replenish::Replenish,
+}
+
+impl Replenish {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_jwt_token(token: String) -> Self {
+        replenish::Replenish::with_jwt_token(token).into()
+    }
+
+    pub fn with_keys(api_key: String, secret_key: String) -> Self {
+        replenish::Replenish::with_keys(api_key, secret_key).into()
+    }
+
+    pub fn with_jwt_and_keys(jwt: String, api_key: String, secret_key: String) -> Self {
+        replenish::Replenish::with_jwt_and_keys(jwt, api_key, secret_key).into()
+    }
+
+    pub fn with_session(session: Session) -> Self {
+        replenish::Replenish::with_session(session).into()
+    }
+
+    pub fn with_signer(api_key: String, signer: Arc<dyn Signer>) -> Self {
+        replenish::Replenish::with_signer(api_key, signer).into()
+    }
+
+    pub fn with_credentials(provider: &impl CredentialProvider) -> Result<Self, RichamsterError> {
+        replenish::Replenish::with_credentials(provider).map(Into::into)
+    }
+
+    pub fn auth_state(&self) -> &SharedAuthState {
+        self.inner.auth_state()
+    }
+
+    pub fn restrict(mut self, permissions: Permissions) -> Self {
+        self.inner = self.inner.restrict(permissions);
+        self
+    }
+
+    pub fn client(mut self, client: RichamsterClient) -> Self {
+        self.inner = self.inner.client(client);
+        self
+    }
+}
+
+impl From<replenish::Replenish> for Replenish {
+    fn from(inner: replenish::Replenish) -> Self {
+        Self { inner }
+    }
+}
+
+impl Replenish {
+    pub fn replenish_info(
+        &self,
+        currency_name: Token,
+        currency_channel: String,
+    ) -> Result<ReplenishInfo, RichamsterError> {
+        block_on(self.inner.replenish_info(currency_name, currency_channel))
+    }
+
+    pub fn replenish_channels_info(
+        &self,
+        currency_name: Token,
+    ) -> Result<Vec<CurrencyChannel>, RichamsterError> {
+        block_on(self.inner.replenish_channels_info(currency_name))
+    }
+
+    pub fn replenish_p2p(&self, replenish: P2PReplenish) -> Result<P2PReplenish, RichamsterError> {
+        block_on(self.inner.replenish_p2p(replenish))
+    }
+}
@@ -0,0 +1,149 @@
+use crate::api::client::RichamsterClient;
+use crate::api::permissions::Permissions;
+use crate::api::token;
+use crate::blocking::block_on;
+use crate::errors::RichamsterError;
+use crate::models::user::{
+    TransactionsFilter, TransferQuery, UserBalance, UserDetail, UserOrderResponse,
+    UserOrdersFilter, UserTransactionResponce,
+};
+use crate::richamster::common::SharedAuthState;
+use crate::richamster::credentials::CredentialProvider;
+use crate::richamster::session::Session;
+use crate::richamster::signer::Signer;
+use crate::richamster::user;
+use std::sync::Arc;
+
+#[derive(Clone, Debug, Default)]
+pub struct User {
+    inner: user::User,
+}
+
+impl User {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_jwt_token(token: String) -> Self {
+        user::User::with_jwt_token(token).into()
+    }
+
+    pub fn with_keys(api_key: String, secret_key: String) -> Self {
+        user::User::with_keys(api_key, secret_key).into()
+    }
+
+    pub fn with_session(session: Session) -> Self {
+        user::User::with_session(session).into()
+    }
+
+    pub fn with_signer(api_key: String, signer: Arc<dyn Signer>) -> Self {
+        user::User::with_signer(api_key, signer).into()
+    }
+
+    pub fn with_credentials(provider: &impl CredentialProvider) -> Result<Self, RichamsterError> {
+        user::User::with_credentials(provider).map(Into::into)
+    }
+
+    pub fn with_jwt_and_keys(jwt: String, api_key: String, secret_key: String) -> Self {
+        user::User::with_jwt_and_keys(jwt, api_key, secret_key).into()
+    }
+
+    pub fn auth_state(&self) -> &SharedAuthState {
+        self.inner.auth_state()
+    }
+
+    pub fn restrict(mut self, permissions: Permissions) -> Self {
+        self.inner = self.inner.restrict(permissions);
+        self
+    }
+
+    pub fn client(mut self, client: RichamsterClient) -> Self {
+        self.inner = self.inner.client(client);
+        self
+    }
+}
+
+impl From<user::User> for User {
+    fn from(inner: user::User) -> Self {
+        Self { inner }
+    }
+}
+
+impl User {
+    pub fn balances(
+        &self,
+        currency: Option<token::Token>,
+    ) -> Result<Vec<UserBalance>, RichamsterError> {
+        block_on(self.inner.balances(currency))
+    }
+
+    pub fn detail_info(&self) -> Result<UserDetail, RichamsterError> {
+        block_on(self.inner.detail_info())
+    }
+
+    pub fn transactions_list(
+        &self,
+        parameters: TransactionsFilter,
+    ) -> Result<UserTransactionResponce, RichamsterError> {
+        block_on(self.inner.transactions_list(parameters))
+    }
+
+    pub fn orders(
+        &self,
+        parameters: UserOrdersFilter,
+    ) -> Result<UserOrderResponse, RichamsterError> {
+        block_on(self.inner.orders(parameters))
+    }
+
+    pub fn transfer(&self, transfer_query: TransferQuery) -> Result<(), RichamsterError> {
+        block_on(self.inner.transfer(transfer_query))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::api::client::ClientConfig;
+    use crate::api::transport::{InMemoryTransport, TransportResponse};
+    use reqwest::{Method, StatusCode};
+    use std::sync::Arc;
+
+    #[test]
+    fn balances_without_runtime() {
+        let transport = Arc::new(InMemoryTransport::new());
+        transport.respond(
+            Method::GET,
+            "user/balances/",
+            TransportResponse::new(StatusCode::OK, "[]"),
+        );
+        let client = ClientConfig::new()
+            .transport(transport.clone())
+            .build()
+            .unwrap();
+        let user = User::with_jwt_token("jwt".to_owned()).client(client);
+        assert!(user.balances(None).unwrap().is_empty());
+        assert_eq!(
+            transport
+                .last_request()
+                .unwrap()
+                .header_value("Authorization"),
+            Some("JWT jwt")
+        );
+    }
+
+    #[test]
+    fn errors_are_shared_with_async_api() {
+        let transport = Arc::new(InMemoryTransport::new());
+        transport.respond(
+            Method::GET,
+            "user/detail/",
+            TransportResponse::new(StatusCode::UNAUTHORIZED, ""),
+        );
+        let client = ClientConfig::new().transport(transport).build().unwrap();
+        let user = User::new().client(client);
+        assert!(matches!(
+            user.detail_info(),
+            Err(RichamsterError::UnauthorizedAccess)
+        ));
+    }
+}
@@ -0,0 +1,81 @@
+use crate::api::client::RichamsterClient;
+use crate::api::permissions::Permissions;
+use crate::api::token::Token;
+use crate::blocking::block_on;
+use crate::errors::RichamsterError;
+use crate::models::withdraw::{WithdrawData, WithdrawInfoResponse, WithdrawResponse};
+use crate::richamster::common::SharedAuthState;
+use crate::richamster::credentials::CredentialProvider;
+use crate::richamster::session::Session;
+use crate::richamster::signer::Signer;
+use crate::richamster::withdraw;
+use std::sync::Arc;
+
+#[derive(Clone, Debug, Default)]
+pub struct Withdraw {
+    inner: withdraw::Withdraw,
+}
+
+impl Withdraw {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_jwt_token(token: String) -> Self {
+        withdraw::Withdraw::with_jwt_token(token).into()
+    }
+
+    pub fn with_keys(api_key: String, secret_key: String) -> Self {
+        withdraw::Withdraw::with_keys(api_key, secret_key).into()
+    }
+
+    pub fn with_jwt_and_keys(jwt: String, api_key: String, secret_key: String) -> Self {
+        withdraw::Withdraw::with_jwt_and_keys(jwt, api_key, secret_key).into()
+    }
+
+    pub fn with_session(session: Session) -> Self {
+        withdraw::Withdraw::with_session(session).into()
+    }
+
+    pub fn with_signer(api_key: String, signer: Arc<dyn Signer>) -> Self {
+        withdraw::Withdraw::with_signer(api_key, signer).into()
+    }
+
+    pub fn with_credentials(provider: &impl CredentialProvider) -> Result<Self, RichamsterError> {
+        withdraw::Withdraw::with_credentials(provider).map(Into::into)
+    }
+
+    pub fn auth_state(&self) -> &SharedAuthState {
+        self.inner.auth_state()
+    }
+
+    pub fn restrict(mut self, permissions: Permissions) -> Self {
+        self.inner = self.inner.restrict(permissions);
+        self
+    }
+
+    pub fn client(mut self, client: RichamsterClient) -> Self {
+        self.inner = self.inner.client(client);
+        self
+    }
+}
+
+impl From<withdraw::Withdraw> for Withdraw {
+    fn from(inner: withdraw::Withdraw) -> Self {
+        Self { inner }
+    }
+}
+
+impl Withdraw {
+    pub fn withdraw_info(&self, token: Token) -> Result<WithdrawInfoResponse, RichamsterError> {
+        block_on(self.inner.withdraw_info(token))
+    }
+
+    pub fn withdraw(
+        &self,
+        token: Token,
+        withdraw: WithdrawData,
+    ) -> Result<WithdrawResponse, RichamsterError> {
+        block_on(self.inner.withdraw(token, withdraw))
+    }
+}
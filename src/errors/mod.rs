@@ -1,11 +1,14 @@
+use crate::api::permissions::Capability;
+use crate::api::retry;
 use crate::api::token::{CurrencyPair, Token};
+use crate::api::{Api, ApiGroup};
 use crate::models::auth::{
     LoginResponseError, NonFieldsError, OtpLoginResponseError, RegisterUserError,
 };
 use crate::models::exchange::NewOrderError;
 use crate::models::feedback::ContactUsError;
 use crate::models::withdraw::WithdrawError;
-use reqwest::StatusCode;
+use reqwest::{Method, StatusCode};
 use thiserror::Error;
 use url::ParseError;
 
@@ -14,19 +17,19 @@ pub enum RichamsterError {
     #[error("Reqwest error: {0}")]
     Reqwest(#[from] reqwest::Error),
     #[error("No active account found: {0}")]
-    InvalidCredential(LoginResponseError),
+    InvalidCredential(Box<LoginResponseError>),
     #[error("Unauthorized access")]
     UnauthorizedAccess,
     #[error("Invalid authorization type")]
     InvalidAuthorizationType,
     #[error("Login response error: {0}")]
-    Login(LoginResponseError),
+    Login(Box<LoginResponseError>),
     #[error("Two factor response error: {0}")]
-    Otp(OtpLoginResponseError),
+    Otp(Box<OtpLoginResponseError>),
     #[error("Serde error: {0}")]
     Serde(#[from] serde_json::Error),
     #[error("Register user error: {0}")]
-    Register(RegisterUserError),
+    Register(Box<RegisterUserError>),
     #[error("Unsupported response code: {0:?}; details: {1}")]
     UnsupportedResponseCode(StatusCode, String),
     #[error("Contact us error: {0}")]
@@ -42,9 +45,50 @@ pub enum RichamsterError {
     #[error("Order {0} not found")]
     OrderNotFound(i32),
     #[error("Withdraw error: {0}")]
-    WithdrawError(WithdrawError),
+    WithdrawError(Box<WithdrawError>),
     #[error("Creation order error: {0}")]
     NewOrderError(NewOrderError),
     #[error("Replenish info not found for token: {0}, id: {1}")]
     ReplenishInfoNotFound(Token, String),
+    #[error("Rate limit exceeded for {0} requests")]
+    RateLimited(ApiGroup),
+    #[error("Circuit open for {0} requests")]
+    CircuitOpen(ApiGroup),
+    #[error("Request timed out")]
+    Timeout,
+    #[error("Outcome of {0:?} request is unknown, it timed out after being sent")]
+    OutcomeUnknown(Api),
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("No recorded response for {0} {1}")]
+    UnexpectedRequest(Method, String),
+    #[error("TLS configuration error: {0}")]
+    Tls(String),
+    #[error("Invalid TOTP secret, expected base32")]
+    InvalidTotpSecret,
+    #[error("Credentials error: {0}")]
+    Credentials(String),
+    #[error("Secret store error: {0}")]
+    SecretStore(String),
+    #[error("Signer error: {0}")]
+    Signer(String),
+    #[error("Permission denied: {0:?} requires the {1} capability")]
+    PermissionDenied(Api, Capability),
+}
+
+impl RichamsterError {
+    /// Whether the failure is likely temporary and the call may succeed when repeated.
+    pub fn is_transient(&self) -> bool {
+        match self {
+            RichamsterError::ServiceUnavailable
+            | RichamsterError::Timeout
+            | RichamsterError::OutcomeUnknown(_)
+            | RichamsterError::CircuitOpen(_) => true,
+            RichamsterError::UnsupportedResponseCode(status, _) => {
+                retry::is_transient_status(*status)
+            }
+            RichamsterError::Reqwest(e) => retry::is_transient_error(e),
+            _ => false,
+        }
+    }
 }
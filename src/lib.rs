@@ -1,5 +1,9 @@
 pub mod api;
+#[cfg(feature = "blocking")]
+pub mod blocking;
 pub mod errors;
 pub(crate) mod macros;
+#[cfg(feature = "mock-server")]
+pub mod mock;
 pub mod models;
 pub mod richamster;
@@ -1,32 +1,30 @@
 #[macro_export]
 macro_rules! send_request {
-    ( $url:expr, $method:expr ) => {{ $crate::api::CCLIENT.request($method, $url).send().await? }};
-    ( $url:expr, $method:expr, $auth_state:expr ) => {{
-        $crate::api::CLIENT
-            .request($method, $url)
-            .compose(&$auth_state, None)
-            .send()
+    ( $client:expr, $api:expr, $url:expr, $method:expr ) => {{
+        $client
+            .execute(
+                $api,
+                $crate::api::transport::TransportRequest::new($method, $url),
+            )
             .await?
     }};
-    ( $url:expr, $method:expr, $auth_state:expr, $body:expr) => {{
-        $crate::api::CLIENT
-            .request($method, $url)
-            .body($body.clone())
-            .header("Content-Type", "application/json")
-            .compose(&$auth_state, Some($body.as_str()))
-            .send()
+    ( $client:expr, $api:expr, $url:expr, $method:expr, $auth_state:expr ) => {{
+        $auth_state
+            .send(
+                &$client,
+                $api,
+                $crate::api::transport::TransportRequest::new($method, $url),
+            )
             .await?
     }};
-}
-
-#[macro_export]
-macro_rules! prepare_request {
-    ( $url:expr, $request_type:ident) => {{ $crate::api::CCLIENT.$request_type($url) }};
-    ( $url:expr, $payload:expr, $request_type:ident) => {{
-        $crate::api::CCLIENT
-            .$request_type($url)
-            .body($payload.clone())
-            .header("Content-Type", "application/json")
+    ( $client:expr, $api:expr, $url:expr, $method:expr, $auth_state:expr, $body:expr) => {{
+        $auth_state
+            .send(
+                &$client,
+                $api,
+                $crate::api::transport::TransportRequest::new($method, $url).json_body($body),
+            )
+            .await?
     }};
 }
 
@@ -35,15 +33,14 @@ macro_rules! process_response {
     ( $response:expr, $de_type:ty ) => {{
         match $response.status() {
             StatusCode::OK => {
-                let res = $response.text().await?;
-                let response: $de_type = serde_json::from_str(res.as_str())?;
+                let response: $de_type = $response.json()?;
                 Ok(response)
             }
             StatusCode::UNAUTHORIZED => Err(RichamsterError::UnauthorizedAccess),
-            status => {
-                let str = $response.text().await?;
-                Err(RichamsterError::UnsupportedResponseCode(status, str))
-            }
+            status => Err(RichamsterError::UnsupportedResponseCode(
+                status,
+                $response.text().to_owned(),
+            )),
         }
     }};
 }
@@ -0,0 +1,547 @@
+use crate::api::client::{ClientConfig, RichamsterClient};
+use crate::api::retry::RetryPolicy;
+use crate::api::signing::{SigningScheme, canonical_request};
+use crate::api::transport::{TransportRequest, TransportResponse};
+use crate::api::{
+    Api, ApiGroup, AuthenticationApi, ExchangeApi, FeedbackApi, ReplenishApi, UserApi, WithdrawApi,
+};
+use hmac_sha256::HMAC;
+use http_body_util::{BodyExt, Full};
+use hyper::body::{Bytes, Incoming};
+use hyper::server::conn::http1;
+use hyper::service::service_fn;
+use hyper_util::rt::TokioIo;
+use reqwest::{Method, StatusCode};
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex, RwLock};
+use tokio::net::TcpListener;
+use tokio::task::JoinHandle;
+use url::Url;
+
+pub mod fixtures;
+pub mod simulator;
+
+const API_PREFIX: &str = "/public/v1/";
+
+/// Produces responses for requests that passed routing and authentication.
+pub trait MockHandler: Send + Sync + 'static {
+    fn handle(&self, api: Api, request: &TransportRequest) -> TransportResponse;
+}
+
+/// Serves [`fixtures::fixture`] for every endpoint.
+#[derive(Debug, Default)]
+pub struct Fixtures;
+
+impl MockHandler for Fixtures {
+    fn handle(&self, api: Api, _request: &TransportRequest) -> TransportResponse {
+        fixtures::fixture(api)
+    }
+}
+
+/// Credentials the server expects. When set, every private endpoint requires
+/// either a matching `Authorization: JWT ..` header or a matching `Api-Key`
+/// together with the HMAC `Signature` of the canonical request. Signatures are
+/// verified in `signing_scheme`, [`SigningScheme::Legacy`] like the real API,
+/// whatever scheme the request announces.
+#[derive(Clone, Debug, Default)]
+pub struct MockCredentials {
+    pub jwt: Option<String>,
+    pub api_key: Option<String>,
+    pub secret_key: Option<String>,
+    pub signing_scheme: SigningScheme,
+}
+
+impl MockCredentials {
+    pub fn jwt(jwt: impl Into<String>) -> Self {
+        Self {
+            jwt: Some(jwt.into()),
+            ..Self::default()
+        }
+    }
+
+    pub fn keys(api_key: impl Into<String>, secret_key: impl Into<String>) -> Self {
+        Self {
+            api_key: Some(api_key.into()),
+            secret_key: Some(secret_key.into()),
+            ..Self::default()
+        }
+    }
+
+    pub fn signing_scheme(mut self, signing_scheme: SigningScheme) -> Self {
+        self.signing_scheme = signing_scheme;
+        self
+    }
+
+    fn authorizes(&self, request: &TransportRequest) -> bool {
+        let jwt_valid = match (&self.jwt, request.header_value("Authorization")) {
+            (Some(jwt), Some(header)) => header == format!("JWT {}", jwt),
+            _ => false,
+        };
+        let keys_valid = match (
+            &self.api_key,
+            &self.secret_key,
+            request.header_value("Api-Key"),
+            request.header_value("Signature"),
+        ) {
+            (Some(api_key), Some(secret_key), Some(header_key), Some(signature)) => {
+                let payload = canonical_request(self.signing_scheme, request);
+                api_key == header_key
+                    && signature == hex::encode(HMAC::mac(payload, secret_key.as_bytes()))
+            }
+            _ => false,
+        };
+        jwt_valid || keys_valid
+    }
+}
+
+struct MockState {
+    base_url: Url,
+    handler: Box<dyn MockHandler>,
+    credentials: RwLock<Option<MockCredentials>>,
+    overrides: Mutex<HashMap<Api, TransportResponse>>,
+    requests: Mutex<Vec<TransportRequest>>,
+}
+
+impl MockState {
+    fn respond(&self, request: TransportRequest) -> TransportResponse {
+        self.requests.lock().unwrap().push(request.clone());
+        let Some(api) = route(&request.method, &request.url) else {
+            return TransportResponse::new(StatusCode::NOT_FOUND, r#"{"detail":"Not found."}"#);
+        };
+        if let Some(response) = self.overrides.lock().unwrap().get(&api) {
+            return response.clone();
+        }
+        let private = !matches!(api.group(), ApiGroup::PublicData | ApiGroup::Auth);
+        let authorized = match self.credentials.read().unwrap().as_ref() {
+            Some(credentials) if private => credentials.authorizes(&request),
+            _ => true,
+        };
+        if !authorized {
+            return TransportResponse::new(
+                StatusCode::UNAUTHORIZED,
+                r#"{"detail":"Authentication credentials were not provided."}"#,
+            );
+        }
+        self.handler.handle(api, &request)
+    }
+}
+
+/// Local HTTP server imitating the Richamster public API. It is stopped when dropped.
+pub struct MockServer {
+    addr: SocketAddr,
+    state: Arc<MockState>,
+    task: JoinHandle<()>,
+}
+
+impl MockServer {
+    /// Starts a server answering every endpoint with its fixture.
+    pub async fn start() -> std::io::Result<Self> {
+        Self::start_with(Fixtures).await
+    }
+
+    pub async fn start_with(handler: impl MockHandler) -> std::io::Result<Self> {
+        let listener = TcpListener::bind(("127.0.0.1", 0)).await?;
+        let addr = listener.local_addr()?;
+        let base_url = Url::parse(&format!("http://{}{}", addr, API_PREFIX)).unwrap();
+        let state = Arc::new(MockState {
+            base_url,
+            handler: Box::new(handler),
+            credentials: RwLock::new(None),
+            overrides: Mutex::new(HashMap::new()),
+            requests: Mutex::new(Vec::new()),
+        });
+        let task = tokio::spawn(serve(listener, state.clone()));
+        Ok(Self { addr, state, task })
+    }
+
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    pub fn base_url(&self) -> &Url {
+        &self.state.base_url
+    }
+
+    /// Client pointed at this server, without retries so failures surface immediately.
+    pub fn client(&self) -> RichamsterClient {
+        ClientConfig::new()
+            .base_url(self.base_url().clone())
+            .retry_policy(RetryPolicy::none())
+            .build()
+            .expect("mock client configuration is valid")
+    }
+
+    pub fn expect_credentials(&self, credentials: MockCredentials) -> &Self {
+        *self.state.credentials.write().unwrap() = Some(credentials);
+        self
+    }
+
+    /// Answers every request routed to `api` with `response` instead of the handler.
+    pub fn override_response(&self, api: Api, response: TransportResponse) -> &Self {
+        self.state.overrides.lock().unwrap().insert(api, response);
+        self
+    }
+
+    pub fn reset_override(&self, api: Api) -> &Self {
+        self.state.overrides.lock().unwrap().remove(&api);
+        self
+    }
+
+    pub fn requests(&self) -> Vec<TransportRequest> {
+        self.state.requests.lock().unwrap().clone()
+    }
+}
+
+impl Drop for MockServer {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+async fn serve(listener: TcpListener, state: Arc<MockState>) {
+    loop {
+        let Ok((stream, _)) = listener.accept().await else {
+            continue;
+        };
+        let state = state.clone();
+        tokio::spawn(async move {
+            let service = service_fn(move |request| {
+                let state = state.clone();
+                async move { Ok::<_, Infallible>(handle(&state, request).await) }
+            });
+            let _ = http1::Builder::new()
+                .serve_connection(TokioIo::new(stream), service)
+                .await;
+        });
+    }
+}
+
+async fn handle(
+    state: &MockState,
+    request: hyper::Request<Incoming>,
+) -> hyper::Response<Full<Bytes>> {
+    let (parts, body) = request.into_parts();
+    let body = body
+        .collect()
+        .await
+        .map(|collected| collected.to_bytes())
+        .unwrap_or_default();
+    let path_and_query = parts
+        .uri
+        .path_and_query()
+        .map(|p| p.as_str())
+        .unwrap_or("/");
+    let url = state
+        .base_url
+        .join(path_and_query)
+        .unwrap_or_else(|_| state.base_url.clone());
+    let request = TransportRequest {
+        method: parts.method,
+        url,
+        headers: parts.headers,
+        body: (!body.is_empty()).then(|| String::from_utf8_lossy(&body).into_owned()),
+    };
+    let response = state.respond(request);
+    let mut builder = hyper::Response::builder().status(response.status());
+    for (name, value) in response.headers() {
+        builder = builder.header(name, value);
+    }
+    if !response.text().is_empty() {
+        builder = builder.header("Content-Type", "application/json");
+    }
+    builder
+        .body(Full::new(Bytes::from(response.text().to_owned())))
+        .unwrap()
+}
+
+/// Maps a request to the endpoint that serves it.
+pub fn route(method: &Method, url: &Url) -> Option<Api> {
+    let path = url.path().strip_prefix(API_PREFIX)?;
+    let segments: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+    let api = match (method, segments.as_slice()) {
+        (&Method::GET, ["exchange", "currencies"]) => Api::Exchange(ExchangeApi::Currencies),
+        (&Method::POST, ["exchange", "favourites", _, "toggle"]) => {
+            Api::Exchange(ExchangeApi::Favourites)
+        }
+        (&Method::GET, ["exchange", "markets"]) => Api::Exchange(ExchangeApi::Markets),
+        (&Method::GET, ["exchange", "order-book"]) => Api::Exchange(ExchangeApi::OrderBook),
+        (&Method::GET, ["exchange", "orders", "history"]) => {
+            Api::Exchange(ExchangeApi::OrdersHistory)
+        }
+        (&Method::GET, ["exchange", "restrictions"]) => Api::Exchange(ExchangeApi::Restrictions),
+        (&Method::GET, ["exchange", "ticker"]) => Api::Exchange(ExchangeApi::TickerList),
+        (&Method::GET, ["exchange", "user", "orders"]) => Api::Exchange(ExchangeApi::UserOrders),
+        (&Method::POST, ["exchange", "user", "orders"]) => Api::Exchange(ExchangeApi::NewOrder),
+        (&Method::GET, ["exchange", "user", "orders", "market"]) => {
+            Api::Exchange(ExchangeApi::CalculateMarketOrder)
+        }
+        (&Method::POST, ["exchange", "user", "orders", "market"]) => {
+            Api::Exchange(ExchangeApi::ExecuteMarketOrder)
+        }
+        (&Method::DELETE, ["exchange", "user", "orders", _]) => {
+            Api::Exchange(ExchangeApi::DestroyOrder)
+        }
+        (&Method::GET, ["feedback", "messengers"]) => Api::Feedback(FeedbackApi::Messengers),
+        (&Method::POST, ["login"]) => Api::Authentication(AuthenticationApi::Login),
+        (&Method::POST, ["register"]) => Api::Authentication(AuthenticationApi::Register),
+        (&Method::POST, ["token", "refresh"]) => {
+            Api::Authentication(AuthenticationApi::RefreshToken)
+        }
+        (&Method::POST, ["two-factor-login"]) => {
+            Api::Authentication(AuthenticationApi::TwoFactorLogin)
+        }
+        (&Method::GET, ["withdraw", _]) => Api::Withdraw(WithdrawApi::WithdrawInfo),
+        (&Method::POST, ["withdraw", _]) => Api::Withdraw(WithdrawApi::Withdraw),
+        (&Method::GET, ["replenish", "blockchain", _]) => {
+            Api::Replenish(ReplenishApi::ReplenishChannelsInfo)
+        }
+        (&Method::POST, ["replenish", "p2p"]) => Api::Replenish(ReplenishApi::P2PReplenish),
+        (&Method::GET, ["replenish", _, _]) => Api::Replenish(ReplenishApi::ReplenishInfo),
+        (&Method::GET, ["user", "balances"]) => Api::User(UserApi::Balances),
+        (&Method::GET, ["user", "detail"]) => Api::User(UserApi::Detail),
+        (&Method::GET, ["user", "orders"]) => Api::User(UserApi::Orders),
+        (&Method::GET, ["user", "transactions"]) => Api::User(UserApi::Transactions),
+        (&Method::POST, ["user", "transfer"]) => Api::User(UserApi::Transfer),
+        _ => return None,
+    };
+    Some(api)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::api::token::{CurrencyPair, Token};
+    use crate::errors::RichamsterError;
+    use crate::models::common::OrderType;
+    use crate::models::exchange::{NewOrder, OrderBookFilter};
+    use crate::models::user::TransactionsFilter;
+    use crate::models::withdraw::WithdrawData;
+    use crate::richamster::auth::Auth;
+    use crate::richamster::exchange::Exchange;
+    use crate::richamster::feedback::Feedback;
+    use crate::richamster::replenish::Replenish;
+    use crate::richamster::user::User;
+    use crate::richamster::withdraw::Withdraw;
+
+    #[test]
+    fn every_endpoint_is_routed() {
+        let client = RichamsterClient::default();
+        let apis = [
+            Api::Exchange(ExchangeApi::Currencies),
+            Api::Exchange(ExchangeApi::Markets),
+            Api::Exchange(ExchangeApi::OrderBook),
+            Api::Exchange(ExchangeApi::OrdersHistory),
+            Api::Exchange(ExchangeApi::Restrictions),
+            Api::Exchange(ExchangeApi::TickerList),
+            Api::Exchange(ExchangeApi::UserOrders),
+            Api::Exchange(ExchangeApi::NewOrder),
+            Api::Exchange(ExchangeApi::CalculateMarketOrder),
+            Api::Exchange(ExchangeApi::ExecuteMarketOrder),
+            Api::Feedback(FeedbackApi::Messengers),
+            Api::Authentication(AuthenticationApi::Login),
+            Api::Authentication(AuthenticationApi::Register),
+            Api::Authentication(AuthenticationApi::RefreshToken),
+            Api::Authentication(AuthenticationApi::TwoFactorLogin),
+            Api::Replenish(ReplenishApi::P2PReplenish),
+            Api::User(UserApi::Balances),
+            Api::User(UserApi::Detail),
+            Api::User(UserApi::Orders),
+            Api::User(UserApi::Transactions),
+            Api::User(UserApi::Transfer),
+        ];
+        for api in apis {
+            let crate::api::RequestData(url, method) = client.request_data(&api);
+            assert_eq!(route(&method, &url), Some(api));
+        }
+        let base = client.base_url();
+        let parametrized = [
+            (
+                "exchange/favourites/1/toggle/",
+                Method::POST,
+                Api::Exchange(ExchangeApi::Favourites),
+            ),
+            (
+                "exchange/user/orders/15",
+                Method::DELETE,
+                Api::Exchange(ExchangeApi::DestroyOrder),
+            ),
+            (
+                "withdraw/BTC",
+                Method::GET,
+                Api::Withdraw(WithdrawApi::WithdrawInfo),
+            ),
+            (
+                "withdraw/BTC",
+                Method::POST,
+                Api::Withdraw(WithdrawApi::Withdraw),
+            ),
+            (
+                "replenish/BTC/bitcoin/",
+                Method::GET,
+                Api::Replenish(ReplenishApi::ReplenishInfo),
+            ),
+            (
+                "replenish/blockchain/BTC",
+                Method::GET,
+                Api::Replenish(ReplenishApi::ReplenishChannelsInfo),
+            ),
+        ];
+        for (path, method, api) in parametrized {
+            assert_eq!(route(&method, &base.join(path).unwrap()), Some(api));
+        }
+    }
+
+    #[tokio::test]
+    async fn services_run_against_fixtures() {
+        let server = MockServer::start().await.unwrap();
+        let client = server.client();
+        let exchange = Exchange::new().client(client.clone());
+        let pair = CurrencyPair::new(Token::BTC, Token::UAH);
+        assert_eq!(exchange.markets_list().await.unwrap().len(), 3);
+        exchange
+            .order_book(OrderBookFilter::new(pair))
+            .await
+            .unwrap();
+        exchange.ticker_list(Some(pair)).await.unwrap();
+        exchange.restrictions_list().await.unwrap();
+        exchange.currencies_list(None).await.unwrap();
+        exchange
+            .calculate_market_order(pair, 0.01, OrderType::Buying)
+            .await
+            .unwrap();
+        exchange
+            .execute_market_order(pair, 0.01, OrderType::Buying, None)
+            .await
+            .unwrap();
+        exchange.destroy_user_order(813).await.unwrap();
+        exchange.favourites_pair_toggle(pair).await.unwrap();
+        let order = NewOrder::new(
+            "0.02".to_owned(),
+            "1655000".to_owned(),
+            pair,
+            OrderType::Selling,
+        );
+        assert_eq!(exchange.create_order(order).await.unwrap().pk, Some(813));
+
+        let user = User::new().client(client.clone());
+        assert_eq!(user.balances(None).await.unwrap().len(), 2);
+        user.detail_info().await.unwrap();
+        user.orders(Default::default()).await.unwrap();
+        user.transactions_list(TransactionsFilter {
+            currency: None,
+            transaction_type: None,
+            closed_at_gte: None,
+            closed_at_lte: None,
+        })
+        .await
+        .unwrap();
+
+        let withdraw = Withdraw::new().client(client.clone());
+        withdraw.withdraw_info(Token::BTC).await.unwrap();
+        withdraw
+            .withdraw(
+                Token::BTC,
+                WithdrawData {
+                    address: "bc1q".to_owned(),
+                    sum: "0.01".to_owned(),
+                    pin_code: "0000".to_owned(),
+                    finserver_channel_name: "BTC".to_owned(),
+                },
+            )
+            .await
+            .unwrap();
+
+        let replenish = Replenish::with_jwt_token("jwt".to_owned()).client(client.clone());
+        replenish
+            .replenish_info(Token::BTC, "bitcoin".to_owned())
+            .await
+            .unwrap();
+        replenish.replenish_channels_info(Token::BTC).await.unwrap();
+
+        Auth::new()
+            .client(client.clone())
+            .login("a@b.c", "pass")
+            .await
+            .unwrap();
+        Feedback::new()
+            .client(client)
+            .messengers_list()
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn private_endpoints_verify_credentials() {
+        let server = MockServer::start().await.unwrap();
+        server.expect_credentials(MockCredentials::keys("api", "secret"));
+        let anonymous = User::new().client(server.client());
+        assert!(matches!(
+            anonymous.balances(None).await,
+            Err(RichamsterError::UnauthorizedAccess)
+        ));
+        let wrong_secret =
+            User::with_keys("api".to_owned(), "other".to_owned()).client(server.client());
+        assert!(matches!(
+            wrong_secret.balances(None).await,
+            Err(RichamsterError::UnauthorizedAccess)
+        ));
+        let user = User::with_keys("api".to_owned(), "secret".to_owned()).client(server.client());
+        assert!(user.balances(None).await.is_ok());
+        let exchange =
+            Exchange::with_keys("api".to_owned(), "secret".to_owned()).client(server.client());
+        let order = NewOrder::new(
+            "0.02".to_owned(),
+            "1655000".to_owned(),
+            CurrencyPair::new(Token::BTC, Token::UAH),
+            OrderType::Selling,
+        );
+        assert!(exchange.create_order(order).await.is_ok());
+        let v2_client = ClientConfig::new()
+            .base_url(server.base_url().clone())
+            .retry_policy(RetryPolicy::none())
+            .signing_scheme(SigningScheme::V2)
+            .build()
+            .unwrap();
+        let v2_user = User::with_keys("api".to_owned(), "secret".to_owned()).client(v2_client);
+        assert!(matches!(
+            v2_user.balances(None).await,
+            Err(RichamsterError::UnauthorizedAccess)
+        ));
+        server.expect_credentials(
+            MockCredentials::keys("api", "secret").signing_scheme(SigningScheme::V2),
+        );
+        assert!(v2_user.balances(None).await.is_ok());
+        assert!(user.balances(None).await.is_err());
+        let jwt_user = User::with_jwt_token("jwt".to_owned()).client(server.client());
+        assert!(jwt_user.detail_info().await.is_err());
+        server.expect_credentials(MockCredentials::jwt("jwt"));
+        assert!(jwt_user.detail_info().await.is_ok());
+        let public = Exchange::new().client(server.client());
+        assert!(public.markets_list().await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn responses_can_be_overridden_per_route() {
+        let server = MockServer::start().await.unwrap();
+        let api = Api::Exchange(ExchangeApi::Markets);
+        server.override_response(api, TransportResponse::new(StatusCode::OK, "[]"));
+        let exchange = Exchange::new().client(server.client());
+        assert!(exchange.markets_list().await.unwrap().is_empty());
+        server.override_response(
+            api,
+            TransportResponse::new(StatusCode::SERVICE_UNAVAILABLE, "maintenance"),
+        );
+        assert!(matches!(
+            exchange.markets_list().await,
+            Err(RichamsterError::UnsupportedResponseCode(
+                StatusCode::SERVICE_UNAVAILABLE,
+                _
+            ))
+        ));
+        server.reset_override(api);
+        assert_eq!(exchange.markets_list().await.unwrap().len(), 3);
+        assert_eq!(server.requests().len(), 3);
+    }
+}
@@ -0,0 +1,1078 @@
+use crate::api::token::{CurrencyPair, Token};
+use crate::api::transport::{TransportRequest, TransportResponse};
+use crate::api::{Api, ExchangeApi, UserApi};
+use crate::mock::{MockHandler, fixtures};
+use crate::models::common::OrderType;
+use crate::models::exchange::{MarketOrderInfo, NewOrder};
+use chrono::{DateTime, Local};
+use reqwest::StatusCode;
+use serde_json::{Value, json};
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::ops::{Add, AddAssign, Sub, SubAssign};
+use std::sync::{Arc, Mutex};
+
+const SCALE: i128 = 100_000_000;
+
+/// Fixed point number with 8 decimal places, enough for every Richamster currency.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+struct Fixed(i128);
+
+impl Fixed {
+    const ZERO: Fixed = Fixed(0);
+
+    /// Parses a plain decimal number digit by digit, rounding half up past the
+    /// eighth decimal place.
+    fn parse(value: &str) -> Option<Self> {
+        let value = value.trim();
+        let (negative, digits) = match value.strip_prefix('-') {
+            Some(digits) => (true, digits),
+            None => (false, value.strip_prefix('+').unwrap_or(value)),
+        };
+        let (integer, fraction) = digits.split_once('.').unwrap_or((digits, ""));
+        if integer.is_empty() && fraction.is_empty()
+            || !integer
+                .bytes()
+                .chain(fraction.bytes())
+                .all(|b| b.is_ascii_digit())
+        {
+            return None;
+        }
+        let mut units: i128 = 0;
+        for digit in integer
+            .bytes()
+            .chain(fraction.bytes().chain(std::iter::repeat(b'0')).take(8))
+        {
+            units = units
+                .checked_mul(10)?
+                .checked_add(i128::from(digit - b'0'))?;
+        }
+        if fraction
+            .as_bytes()
+            .get(8)
+            .is_some_and(|digit| *digit >= b'5')
+        {
+            units = units.checked_add(1)?;
+        }
+        Some(Fixed(if negative { -units } else { units }))
+    }
+
+    /// `None` when the result does not fit.
+    fn mul(self, other: Fixed) -> Option<Fixed> {
+        Some(Fixed(self.0.checked_mul(other.0)? / SCALE))
+    }
+
+    /// `None` when the result does not fit or `other` is zero.
+    fn div(self, other: Fixed) -> Option<Fixed> {
+        Some(Fixed(self.0.checked_mul(SCALE)?.checked_div(other.0)?))
+    }
+
+    fn to_f64(self) -> f64 {
+        self.0 as f64 / SCALE as f64
+    }
+}
+
+impl Display for Fixed {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let sign = if self.0 < 0 { "-" } else { "" };
+        let abs = self.0.abs();
+        write!(f, "{}{}.{:08}", sign, abs / SCALE, abs % SCALE)
+    }
+}
+
+impl Add for Fixed {
+    type Output = Fixed;
+
+    fn add(self, other: Fixed) -> Fixed {
+        Fixed(self.0 + other.0)
+    }
+}
+
+impl Sub for Fixed {
+    type Output = Fixed;
+
+    fn sub(self, other: Fixed) -> Fixed {
+        Fixed(self.0 - other.0)
+    }
+}
+
+impl AddAssign for Fixed {
+    fn add_assign(&mut self, other: Fixed) {
+        self.0 += other.0;
+    }
+}
+
+impl SubAssign for Fixed {
+    fn sub_assign(&mut self, other: Fixed) {
+        self.0 -= other.0;
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+struct Balance {
+    total: Fixed,
+    locked: Fixed,
+}
+
+#[derive(Clone, Debug)]
+struct RestingOrder {
+    id: i32,
+    account: String,
+    side: OrderType,
+    price: Fixed,
+    remaining: Fixed,
+    /// Funds still locked for `remaining`, released in full when the order closes.
+    locked: Fixed,
+    created_at: DateTime<Local>,
+}
+
+#[derive(Clone, Debug)]
+struct Trade {
+    id: i32,
+    pair: CurrencyPair,
+    side: OrderType,
+    price: Fixed,
+    volume: Fixed,
+    at: DateTime<Local>,
+}
+
+/// Resting orders of one pair, each side kept in price-time priority.
+#[derive(Debug, Default)]
+struct Book {
+    buying: Vec<RestingOrder>,
+    selling: Vec<RestingOrder>,
+}
+
+impl Book {
+    fn side(&self, side: OrderType) -> &Vec<RestingOrder> {
+        match side {
+            OrderType::Buying => &self.buying,
+            OrderType::Selling => &self.selling,
+        }
+    }
+
+    fn side_mut(&mut self, side: OrderType) -> &mut Vec<RestingOrder> {
+        match side {
+            OrderType::Buying => &mut self.buying,
+            OrderType::Selling => &mut self.selling,
+        }
+    }
+
+    fn insert(&mut self, order: RestingOrder) {
+        let side = order.side;
+        let queue = self.side_mut(side);
+        let position = queue
+            .iter()
+            .position(|resting| match side {
+                OrderType::Buying => order.price > resting.price,
+                OrderType::Selling => order.price < resting.price,
+            })
+            .unwrap_or(queue.len());
+        queue.insert(position, order);
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Fill {
+    maker: usize,
+    price: Fixed,
+    volume: Fixed,
+}
+
+/// Funds an order on `side` locks for `amount` at `price`: the quote currency
+/// when buying, the base currency when selling.
+fn lock_for(side: OrderType, amount: Fixed, price: Fixed) -> Option<Fixed> {
+    match side {
+        OrderType::Buying => amount.mul(price),
+        OrderType::Selling => Some(amount),
+    }
+}
+
+fn opposite(side: OrderType) -> OrderType {
+    match side {
+        OrderType::Buying => OrderType::Selling,
+        OrderType::Selling => OrderType::Buying,
+    }
+}
+
+/// Walks the queue a taker on `side` would consume. Stops at the `limit` price
+/// and, for market buys, once `budget` of the quote currency is spent.
+fn plan(
+    queue: &[RestingOrder],
+    side: OrderType,
+    amount: Fixed,
+    limit: Option<Fixed>,
+    budget: Option<Fixed>,
+) -> Option<Vec<Fill>> {
+    let mut fills = Vec::new();
+    let mut left = amount;
+    let mut budget = budget;
+    for (maker, resting) in queue.iter().enumerate() {
+        let crosses = match (side, limit) {
+            (_, None) => true,
+            (OrderType::Buying, Some(limit)) => resting.price <= limit,
+            (OrderType::Selling, Some(limit)) => resting.price >= limit,
+        };
+        if left <= Fixed::ZERO || !crosses {
+            break;
+        }
+        let mut volume = left.min(resting.remaining);
+        if let Some(budget) = budget.as_mut() {
+            volume = volume.min(budget.div(resting.price)?);
+            *budget -= volume.mul(resting.price)?;
+        }
+        if volume <= Fixed::ZERO {
+            break;
+        }
+        left -= volume;
+        fills.push(Fill {
+            maker,
+            price: resting.price,
+            volume,
+        });
+    }
+    Some(fills)
+}
+
+#[derive(Debug, Default)]
+struct ExchangeState {
+    markets: Vec<CurrencyPair>,
+    books: HashMap<CurrencyPair, Book>,
+    balances: HashMap<(String, Token), Balance>,
+    trades: Vec<Trade>,
+    commission: Fixed,
+    last_order_id: i32,
+    last_trade_id: i32,
+}
+
+impl ExchangeState {
+    fn balance(&mut self, account: &str, token: Token) -> &mut Balance {
+        self.balances
+            .entry((account.to_owned(), token))
+            .or_default()
+    }
+
+    fn available(&self, account: &str, token: Token) -> Fixed {
+        self.balances
+            .get(&(account.to_owned(), token))
+            .map(|b| b.total - b.locked)
+            .unwrap_or_default()
+    }
+
+    fn fee(&self, value: Fixed) -> Option<Fixed> {
+        value.mul(self.commission)
+    }
+
+    /// Applies fills to both parties of every trade and removes exhausted makers.
+    /// Returns the traded volume, its quote value and the taker commission, or
+    /// `None` without changing anything when an amount does not fit.
+    fn settle(
+        &mut self,
+        account: &str,
+        pair: CurrencyPair,
+        side: OrderType,
+        fills: &[Fill],
+    ) -> Option<(Fixed, Fixed, Fixed)> {
+        let [base, quote] = pair.0;
+        let now = Local::now();
+        let queue = self
+            .books
+            .get(&pair)
+            .map(|book| book.side(opposite(side)).as_slice())
+            .unwrap_or_default();
+        let mut amounts = Vec::with_capacity(fills.len());
+        for fill in fills {
+            let maker = &queue[fill.maker];
+            let cost = fill.volume.mul(fill.price)?;
+            let locked = lock_for(maker.side, maker.remaining - fill.volume, maker.price)?;
+            amounts.push((cost, self.fee(fill.volume)?, self.fee(cost)?, locked));
+        }
+        let (mut volume, mut value, mut commission) = (Fixed::ZERO, Fixed::ZERO, Fixed::ZERO);
+        for (fill, (cost, fee_base, fee_quote, locked)) in fills.iter().zip(amounts) {
+            let (maker, released) = {
+                let queue = self.books.get_mut(&pair).unwrap().side_mut(opposite(side));
+                let maker = &mut queue[fill.maker];
+                maker.remaining -= fill.volume;
+                let released = maker.locked - locked;
+                maker.locked = locked;
+                (maker.account.clone(), released)
+            };
+            match side {
+                OrderType::Buying => {
+                    let taker_quote = self.balance(account, quote);
+                    taker_quote.total -= cost;
+                    self.balance(account, base).total += fill.volume - fee_base;
+                    let maker_base = self.balance(&maker, base);
+                    maker_base.total -= fill.volume;
+                    maker_base.locked -= released;
+                    self.balance(&maker, quote).total += cost - fee_quote;
+                    commission += fee_base;
+                }
+                OrderType::Selling => {
+                    let taker_base = self.balance(account, base);
+                    taker_base.total -= fill.volume;
+                    self.balance(account, quote).total += cost - fee_quote;
+                    let maker_quote = self.balance(&maker, quote);
+                    maker_quote.total -= cost;
+                    maker_quote.locked -= released;
+                    self.balance(&maker, base).total += fill.volume - fee_base;
+                    commission += fee_quote;
+                }
+            }
+            self.last_trade_id += 1;
+            self.trades.push(Trade {
+                id: self.last_trade_id,
+                pair,
+                side,
+                price: fill.price,
+                volume: fill.volume,
+                at: now,
+            });
+            volume += fill.volume;
+            value += cost;
+        }
+        if let Some(book) = self.books.get_mut(&pair) {
+            book.side_mut(opposite(side))
+                .retain(|o| o.remaining > Fixed::ZERO);
+        }
+        Some((volume, value, commission))
+    }
+
+    fn place_order(&mut self, account: &str, order: &NewOrder) -> Result<Value, Value> {
+        let pair = order
+            .currency_pair
+            .parse::<CurrencyPair>()
+            .ok()
+            .filter(|pair| self.markets.contains(pair))
+            .ok_or_else(|| rejected("currency_pair", "invalid", "Unknown currency pair"))?;
+        let side = order
+            .o_type
+            .or(order.order_type)
+            .ok_or_else(|| rejected("type", "required", "This field is required."))?;
+        let amount = Fixed::parse(&order.amount)
+            .filter(|a| *a > Fixed::ZERO)
+            .ok_or_else(|| rejected("amount", "invalid", "A positive number is required."))?;
+        let price = Fixed::parse(&order.unit_price)
+            .filter(|p| *p > Fixed::ZERO)
+            .ok_or_else(|| rejected("unit_price", "invalid", "A positive number is required."))?;
+        let [base, quote] = pair.0;
+        let token = match side {
+            OrderType::Buying => quote,
+            OrderType::Selling => base,
+        };
+        let required = lock_for(side, amount, price).ok_or_else(too_large)?;
+        if self.available(account, token) < required {
+            return Err(rejected(
+                "amount",
+                "insufficient_funds",
+                "Insufficient funds.",
+            ));
+        }
+
+        let queue = self.books.entry(pair).or_default().side(opposite(side));
+        let fills = plan(queue, side, amount, Some(price), None).ok_or_else(too_large)?;
+        let (filled, _, commission) = self
+            .settle(account, pair, side, &fills)
+            .ok_or_else(too_large)?;
+        self.last_order_id += 1;
+        let created_at = Local::now();
+        let remaining = amount - filled;
+        // Never more than `required`, which fits
+        let locked = lock_for(side, remaining, price).unwrap_or(required);
+        self.balance(account, token).locked += locked;
+        if remaining > Fixed::ZERO {
+            self.books.entry(pair).or_default().insert(RestingOrder {
+                id: self.last_order_id,
+                account: account.to_owned(),
+                side,
+                price,
+                remaining,
+                locked,
+                created_at,
+            });
+        }
+        Ok(json!({
+            "pk": self.last_order_id,
+            "order_type": side,
+            "amount": amount.to_string(),
+            "unit_price": price.to_string(),
+            "currency_pair": pair.to_string(),
+            "commission": commission.to_string(),
+            "closed_at": (remaining <= Fixed::ZERO).then(|| created_at.to_rfc3339()),
+            "type": side,
+            "created_at": created_at.to_rfc3339(),
+            "is_partial": filled > Fixed::ZERO && remaining > Fixed::ZERO
+        }))
+    }
+
+    fn cancel_order(&mut self, account: &str, id: i32) -> bool {
+        let found = self.books.iter_mut().find_map(|(pair, book)| {
+            [OrderType::Buying, OrderType::Selling]
+                .into_iter()
+                .find_map(|side| {
+                    let queue = book.side_mut(side);
+                    let index = queue
+                        .iter()
+                        .position(|o| o.id == id && o.account == account)?;
+                    Some((*pair, queue.remove(index)))
+                })
+        });
+        let Some((pair, order)) = found else {
+            return false;
+        };
+        let [base, quote] = pair.0;
+        let token = match order.side {
+            OrderType::Buying => quote,
+            OrderType::Selling => base,
+        };
+        self.balance(account, token).locked -= order.locked;
+        true
+    }
+
+    fn market_order(
+        &mut self,
+        account: &str,
+        info: &MarketOrderInfo,
+        execute: bool,
+    ) -> Result<Value, Value> {
+        let pair = info
+            .currency_pair
+            .checked_sub(1)
+            .and_then(|i| usize::try_from(i).ok())
+            .and_then(|i| self.markets.get(i).copied())
+            .ok_or_else(|| rejected("currency_pair", "invalid", "Unknown market."))?;
+        let amount = Fixed::parse(&info.amount)
+            .filter(|a| *a > Fixed::ZERO)
+            .ok_or_else(|| rejected("amount", "invalid", "A positive number is required."))?;
+        let budget = match (info.order_type, info.total.as_deref()) {
+            (OrderType::Buying, Some(total)) => Some(
+                Fixed::parse(total)
+                    .ok_or_else(|| rejected("total", "invalid", "A number is required."))?,
+            ),
+            _ => None,
+        };
+        let side = info.order_type;
+        let queue = self.books.entry(pair).or_default().side(opposite(side));
+        let fills = plan(queue, side, amount, None, budget).ok_or_else(too_large)?;
+        let covered = fills.iter().fold(Fixed::ZERO, |sum, f| sum + f.volume);
+        let total = fills
+            .iter()
+            .try_fold(Fixed::ZERO, |sum, f| Some(sum + f.volume.mul(f.price)?))
+            .ok_or_else(too_large)?;
+        if !execute {
+            let average = if covered > Fixed::ZERO {
+                total.div(covered).ok_or_else(too_large)?
+            } else {
+                Fixed::ZERO
+            };
+            return Ok(json!({
+                "average_price": average.to_f64(),
+                "covered": covered.to_f64(),
+                "total_sum": total.to_f64()
+            }));
+        }
+        if fills.is_empty() {
+            return Err(rejected("amount", "no_liquidity", "Order book is empty."));
+        }
+        let [base, quote] = pair.0;
+        let (token, required) = match side {
+            OrderType::Buying => (quote, total),
+            OrderType::Selling => (base, covered),
+        };
+        if self.available(account, token) < required {
+            return Err(rejected(
+                "amount",
+                "insufficient_funds",
+                "Insufficient funds.",
+            ));
+        }
+        let (_, value, _) = self
+            .settle(account, pair, side, &fills)
+            .ok_or_else(too_large)?;
+        Ok(json!({"total_sum": value.to_string(), "in_orders": fills.len()}))
+    }
+
+    fn order_book(&self, query: &HashMap<String, String>) -> Option<Value> {
+        let pair = query.get("pair")?.parse::<CurrencyPair>().ok()?;
+        let book = self.books.get(&pair);
+        let levels = |side: OrderType| -> Value {
+            let mut levels: Vec<(Fixed, Fixed)> = Vec::new();
+            for order in book.map(|b| b.side(side).as_slice()).unwrap_or_default() {
+                match levels.last_mut() {
+                    Some((price, volume)) if *price == order.price => *volume += order.remaining,
+                    _ => levels.push((order.price, order.remaining)),
+                }
+            }
+            levels
+                .into_iter()
+                .map(|(price, volume)| {
+                    json!({
+                        "volume": volume.to_string(),
+                        "unit_price": price.to_string(),
+                        "sum": volume.mul(price).map(|sum| sum.to_string()),
+                        "side": side,
+                        "pair": pair.to_string()
+                    })
+                })
+                .collect()
+        };
+        let wanted = |side: OrderType| query.get("side").is_none_or(|s| *s == side.to_string());
+        Some(json!({
+            "buying": wanted(OrderType::Buying).then(|| levels(OrderType::Buying)),
+            "selling": wanted(OrderType::Selling).then(|| levels(OrderType::Selling))
+        }))
+    }
+
+    fn trades_history(&self, query: &HashMap<String, String>) -> Value {
+        let pair = query
+            .get("pair")
+            .and_then(|p| p.parse::<CurrencyPair>().ok());
+        let results: Vec<Value> = self
+            .trades
+            .iter()
+            .rev()
+            .filter(|t| pair.is_none_or(|p| p == t.pair))
+            .take(page_size(query))
+            .map(|t| history_record(t.id, t.at, Some(t.at), t.side, t.volume, t.price, t.pair))
+            .collect();
+        json!({"next": null, "previous": null, "results": results})
+    }
+
+    fn open_orders(&self, account: &str, query: &HashMap<String, String>) -> Value {
+        let pair = query
+            .get("pair")
+            .and_then(|p| p.parse::<CurrencyPair>().ok());
+        let mut orders: Vec<(CurrencyPair, &RestingOrder)> = self
+            .books
+            .iter()
+            .filter(|(p, _)| pair.is_none_or(|pair| pair == **p))
+            .flat_map(|(p, book)| {
+                book.buying
+                    .iter()
+                    .chain(book.selling.iter())
+                    .map(move |o| (*p, o))
+            })
+            .filter(|(_, o)| o.account == account)
+            .collect();
+        orders.sort_by_key(|(_, o)| std::cmp::Reverse(o.id));
+        let results: Vec<Value> = orders
+            .into_iter()
+            .take(page_size(query))
+            .map(|(pair, o)| {
+                history_record(o.id, o.created_at, None, o.side, o.remaining, o.price, pair)
+            })
+            .collect();
+        json!({"next": null, "previous": null, "results": results})
+    }
+
+    fn markets_list(&self) -> Value {
+        let markets: Vec<Value> = self
+            .markets
+            .iter()
+            .enumerate()
+            .map(|(i, pair)| {
+                let mut trades = self.trades.iter().filter(|t| t.pair == *pair);
+                let volume = trades.clone().fold(Fixed::ZERO, |sum, t| sum + t.volume);
+                let last_price = trades.next_back().map(|t| t.price).unwrap_or_default();
+                json!({
+                    "id": i + 1,
+                    "is_favourite": false,
+                    "abbreviation": pair.to_string(),
+                    "volume": volume.to_f64(),
+                    "price_deviation": 0,
+                    "last_price": last_price.to_string()
+                })
+            })
+            .collect();
+        Value::Array(markets)
+    }
+
+    fn balances(&self, account: &str, query: &HashMap<String, String>) -> Value {
+        let currency = query.get("currency").and_then(|c| c.parse::<Token>().ok());
+        let mut balances: Vec<(Token, Balance)> = self
+            .balances
+            .iter()
+            .filter(|((owner, token), _)| owner == account && currency.is_none_or(|c| c == *token))
+            .map(|((_, token), balance)| (*token, *balance))
+            .collect();
+        balances.sort_by_key(|(token, _)| *token as i32);
+        let balances: Vec<Value> = balances
+            .into_iter()
+            .map(|(token, balance)| {
+                let is_fiat = token == Token::UAH;
+                json!({
+                    "currency": {
+                        "id": token as i32 + 1,
+                        "abbreviation": token.as_ref(),
+                        "is_fiat": is_fiat,
+                        "is_auction_currency": false,
+                        "is_market": true,
+                        "precision": if is_fiat { 2 } else { 8 },
+                        "can_input": true,
+                        "can_output": true
+                    },
+                    "balance": balance.total.to_string(),
+                    "active_balance": (balance.total - balance.locked).to_string(),
+                    "in_orders": balance.locked.to_string(),
+                    "in_usdt": "0",
+                    "in_btc": "0",
+                    "in_grn": "0"
+                })
+            })
+            .collect();
+        Value::Array(balances)
+    }
+}
+
+fn history_record(
+    pk: i32,
+    created_at: DateTime<Local>,
+    closed_at: Option<DateTime<Local>>,
+    side: OrderType,
+    volume: Fixed,
+    price: Fixed,
+    pair: CurrencyPair,
+) -> Value {
+    json!({
+        "pk": pk,
+        "created_at": created_at.timestamp().to_string(),
+        "closed_at": closed_at.map(|at| at.timestamp().to_string()),
+        "side": side,
+        "volume": volume.to_string(),
+        "unit_price": price.to_string(),
+        "sum": volume.mul(price).map(|sum| sum.to_string()),
+        "pair": pair.to_string()
+    })
+}
+
+fn page_size(query: &HashMap<String, String>) -> usize {
+    query
+        .get("page_size")
+        .and_then(|s| s.parse().ok())
+        .unwrap_or(50)
+}
+
+/// Body of a `400 Bad Request`, shaped like [`NewOrderError`](crate::models::exchange::NewOrderError).
+fn too_large() -> Value {
+    rejected(
+        "amount",
+        "invalid",
+        "Ensure the order value is not too large.",
+    )
+}
+
+fn rejected(attr: &str, code: &str, detail: &str) -> Value {
+    json!({
+        "type": "validation_error",
+        "errors": [{"code": code, "detail": detail, "attr": attr}]
+    })
+}
+
+fn respond(status: StatusCode, result: Result<Value, Value>) -> TransportResponse {
+    match result {
+        Ok(body) => TransportResponse::new(status, body.to_string()),
+        Err(body) => TransportResponse::new(StatusCode::BAD_REQUEST, body.to_string()),
+    }
+}
+
+/// Accounts are told apart by the `Api-Key` header, or by the JWT for token
+/// authenticated requests.
+fn account_of(request: &TransportRequest) -> String {
+    request
+        .header_value("Api-Key")
+        .or_else(|| {
+            request
+                .header_value("Authorization")
+                .map(|v| v.trim_start_matches("JWT "))
+        })
+        .unwrap_or("anonymous")
+        .to_owned()
+}
+
+/// [`MockHandler`] keeping order books, trades and balances in memory. Limit and
+/// market orders are matched with price-time priority at the resting order price,
+/// both sides pay the commission on the currency they receive. Endpoints outside
+/// trading and balances are answered with fixtures.
+///
+/// Clones share state, so a test can keep a handle after passing one to
+/// [`MockServer::start_with`](crate::mock::MockServer::start_with).
+#[derive(Clone, Debug)]
+pub struct SimulatedExchange {
+    state: Arc<Mutex<ExchangeState>>,
+}
+
+impl Default for SimulatedExchange {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SimulatedExchange {
+    /// Exchange without markets and a 0.2% commission.
+    pub fn new() -> Self {
+        let state = ExchangeState {
+            commission: Fixed::parse("0.002").unwrap(),
+            ..ExchangeState::default()
+        };
+        Self {
+            state: Arc::new(Mutex::new(state)),
+        }
+    }
+
+    /// Lists the pair; market ids follow the order pairs are added in, starting at 1.
+    pub fn market(self, pair: CurrencyPair) -> Self {
+        {
+            let mut state = self.state.lock().unwrap();
+            if !state.markets.contains(&pair) {
+                state.markets.push(pair);
+            }
+        }
+        self
+    }
+
+    /// Commission in percent of the received amount, `0.2` by default.
+    pub fn commission(self, percent: f64) -> Self {
+        self.state.lock().unwrap().commission = Fixed::parse(&(percent / 100.0).to_string())
+            .expect("commission must be a finite number");
+        self
+    }
+
+    pub fn deposit(&self, account: &str, token: Token, amount: &str) {
+        let amount = Fixed::parse(amount).expect("deposit amount must be a number");
+        self.state.lock().unwrap().balance(account, token).total += amount;
+    }
+
+    /// Places a limit order on behalf of `account`, typically to provide liquidity.
+    pub fn place_order(&self, account: &str, order: &NewOrder) -> Result<NewOrder, String> {
+        let placed = self.state.lock().unwrap().place_order(account, order);
+        match placed {
+            Ok(order) => serde_json::from_value(order).map_err(|e| e.to_string()),
+            Err(body) => Err(body.to_string()),
+        }
+    }
+
+    /// Total balance, formatted with 8 decimal places.
+    pub fn balance(&self, account: &str, token: Token) -> String {
+        let mut state = self.state.lock().unwrap();
+        state.balance(account, token).total.to_string()
+    }
+
+    /// Part of the balance locked by open orders, formatted with 8 decimal places.
+    pub fn in_orders(&self, account: &str, token: Token) -> String {
+        let mut state = self.state.lock().unwrap();
+        state.balance(account, token).locked.to_string()
+    }
+}
+
+impl MockHandler for SimulatedExchange {
+    fn handle(&self, api: Api, request: &TransportRequest) -> TransportResponse {
+        let mut state = self.state.lock().unwrap();
+        let account = account_of(request);
+        let query: HashMap<String, String> = request.url.query_pairs().into_owned().collect();
+        let body = request.body.as_deref().unwrap_or("");
+        match api {
+            Api::Exchange(ExchangeApi::Markets) => {
+                respond(StatusCode::OK, Ok(state.markets_list()))
+            }
+            Api::Exchange(ExchangeApi::OrderBook) => match state.order_book(&query) {
+                Some(book) => respond(StatusCode::OK, Ok(book)),
+                None => respond(
+                    StatusCode::OK,
+                    Err(rejected("pair", "invalid", "Unknown currency pair")),
+                ),
+            },
+            Api::Exchange(ExchangeApi::OrdersHistory) => {
+                respond(StatusCode::OK, Ok(state.trades_history(&query)))
+            }
+            Api::Exchange(ExchangeApi::UserOrders) => {
+                respond(StatusCode::OK, Ok(state.open_orders(&account, &query)))
+            }
+            Api::Exchange(ExchangeApi::NewOrder) => match serde_json::from_str::<NewOrder>(body) {
+                Ok(order) => respond(StatusCode::CREATED, state.place_order(&account, &order)),
+                Err(e) => respond(
+                    StatusCode::CREATED,
+                    Err(rejected("non_field_errors", "parse_error", &e.to_string())),
+                ),
+            },
+            Api::Exchange(ExchangeApi::DestroyOrder) => {
+                let id = request
+                    .url
+                    .path_segments()
+                    .and_then(|mut segments| segments.next_back())
+                    .and_then(|id| id.parse().ok());
+                match id {
+                    Some(id) if state.cancel_order(&account, id) => {
+                        TransportResponse::new(StatusCode::NO_CONTENT, "")
+                    }
+                    _ => {
+                        TransportResponse::new(StatusCode::NOT_FOUND, r#"{"detail":"Not found."}"#)
+                    }
+                }
+            }
+            Api::Exchange(ExchangeApi::CalculateMarketOrder) => {
+                let info = MarketOrderInfo {
+                    amount: query.get("amount").cloned().unwrap_or_default(),
+                    currency_pair: query
+                        .get("currency_pair")
+                        .and_then(|id| id.parse().ok())
+                        .unwrap_or_default(),
+                    order_type: match query.get("type").map(String::as_str) {
+                        Some("selling") => OrderType::Selling,
+                        _ => OrderType::Buying,
+                    },
+                    total: None,
+                };
+                respond(StatusCode::OK, state.market_order(&account, &info, false))
+            }
+            Api::Exchange(ExchangeApi::ExecuteMarketOrder) => {
+                match serde_json::from_str::<MarketOrderInfo>(body) {
+                    Ok(info) => respond(
+                        StatusCode::CREATED,
+                        state.market_order(&account, &info, true),
+                    ),
+                    Err(e) => respond(
+                        StatusCode::CREATED,
+                        Err(rejected("non_field_errors", "parse_error", &e.to_string())),
+                    ),
+                }
+            }
+            Api::User(UserApi::Balances) => {
+                respond(StatusCode::OK, Ok(state.balances(&account, &query)))
+            }
+            _ => fixtures::fixture(api),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::errors::RichamsterError;
+    use crate::mock::MockServer;
+    use crate::models::exchange::{OrderBookFilter, OrdersFilter};
+    use crate::richamster::exchange::Exchange;
+    use crate::richamster::user::User;
+
+    const BTC_UAH: CurrencyPair = CurrencyPair([Token::BTC, Token::UAH]);
+
+    fn order(amount: &str, price: &str, side: OrderType) -> NewOrder {
+        NewOrder::new(amount.to_owned(), price.to_owned(), BTC_UAH, side)
+    }
+
+    fn exchange_with_liquidity() -> SimulatedExchange {
+        let simulator = SimulatedExchange::new().market(BTC_UAH);
+        simulator.deposit("maker", Token::BTC, "1");
+        simulator.deposit("maker", Token::UAH, "1000000");
+        for (amount, price) in [("0.1", "1650000"), ("0.2", "1651000")] {
+            simulator
+                .place_order("maker", &order(amount, price, OrderType::Selling))
+                .unwrap();
+        }
+        simulator
+            .place_order("maker", &order("0.5", "1600000", OrderType::Buying))
+            .unwrap();
+        simulator
+    }
+
+    #[test]
+    fn fixed_point_arithmetic() {
+        let price = Fixed::parse("1650000").unwrap();
+        let amount = Fixed::parse("0.15").unwrap();
+        assert_eq!(amount.mul(price).unwrap().to_string(), "247500.00000000");
+        assert_eq!(Fixed(i128::MAX).mul(price), None);
+        assert_eq!(price.div(Fixed::ZERO), None);
+        assert_eq!(Fixed::parse("0.1").unwrap().to_string(), "0.10000000");
+        assert_eq!(Fixed(-5).to_string(), "-0.00000005");
+        assert_eq!(Fixed::parse("0.29").unwrap(), Fixed(29_000_000));
+        assert_eq!(
+            Fixed::parse("12345678901234.00000001").unwrap(),
+            Fixed(1_234_567_890_123_400_000_001)
+        );
+        assert_eq!(Fixed::parse(".000000015").unwrap(), Fixed(2));
+        assert_eq!(Fixed::parse("-3").unwrap(), Fixed(-300_000_000));
+        for invalid in ["", ".", "1e3", "1.2.3", "NaN", "inf", "--1"] {
+            assert_eq!(Fixed::parse(invalid), None, "{invalid}");
+        }
+    }
+
+    #[test]
+    fn closed_orders_release_their_whole_lock() {
+        let simulator = SimulatedExchange::new().market(BTC_UAH);
+        simulator.deposit("maker", Token::UAH, "1");
+        simulator.deposit("taker", Token::BTC, "1");
+        // 0.3 at 0.66666667 locks 0.2, while each 0.1 fill costs 0.06666666
+        let resting = simulator
+            .place_order("maker", &order("0.3", "0.66666667", OrderType::Buying))
+            .unwrap();
+        assert_eq!(simulator.in_orders("maker", Token::UAH), "0.20000000");
+        for _ in 0..3 {
+            simulator
+                .place_order("taker", &order("0.1", "0.66666667", OrderType::Selling))
+                .unwrap();
+        }
+        assert_eq!(simulator.in_orders("maker", Token::UAH), "0.00000000");
+        assert!(
+            !simulator
+                .state
+                .lock()
+                .unwrap()
+                .cancel_order("maker", resting.pk.unwrap())
+        );
+
+        for _ in 0..3 {
+            simulator
+                .place_order("taker", &order("0.1", "0.66666667", OrderType::Selling))
+                .unwrap();
+        }
+        simulator
+            .place_order("maker", &order("0.3", "0.66666667", OrderType::Buying))
+            .unwrap();
+        assert_eq!(simulator.in_orders("maker", Token::UAH), "0.00000000");
+        assert_eq!(simulator.in_orders("taker", Token::BTC), "0.00000000");
+    }
+
+    #[test]
+    fn limit_order_matches_in_price_time_priority() {
+        let simulator = exchange_with_liquidity();
+        simulator.deposit("taker", Token::UAH, "500000");
+        let placed = simulator
+            .place_order("taker", &order("0.15", "1651000", OrderType::Buying))
+            .unwrap();
+        assert!(placed.closed_at.is_some());
+        // 0.1 at 1650000 and 0.05 at 1651000
+        assert_eq!(simulator.balance("taker", Token::UAH), "252450.00000000");
+        assert_eq!(simulator.balance("taker", Token::BTC), "0.14970000");
+        assert_eq!(simulator.in_orders("taker", Token::UAH), "0.00000000");
+        assert_eq!(simulator.balance("maker", Token::UAH), "1247054.90000000");
+        assert_eq!(simulator.in_orders("maker", Token::BTC), "0.15000000");
+    }
+
+    #[test]
+    fn unfilled_remainder_rests_and_can_be_cancelled() {
+        let simulator = exchange_with_liquidity();
+        simulator.deposit("taker", Token::BTC, "1");
+        let placed = simulator
+            .place_order("taker", &order("0.7", "1600000", OrderType::Selling))
+            .unwrap();
+        assert_eq!(placed.is_partial, Some(true));
+        assert_eq!(simulator.in_orders("taker", Token::BTC), "0.20000000");
+        let mut state = simulator.state.lock().unwrap();
+        assert!(state.cancel_order("taker", placed.pk.unwrap()));
+        assert!(!state.cancel_order("taker", placed.pk.unwrap()));
+        assert_eq!(state.balance("taker", Token::BTC).locked, Fixed::ZERO);
+    }
+
+    #[test]
+    fn insufficient_funds_are_rejected() {
+        let simulator = exchange_with_liquidity();
+        simulator.deposit("taker", Token::UAH, "100");
+        let error = simulator
+            .place_order("taker", &order("0.1", "1650000", OrderType::Buying))
+            .unwrap_err();
+        assert!(error.contains("insufficient_funds"));
+        assert_eq!(simulator.balance("maker", Token::BTC), "1.00000000");
+    }
+
+    #[tokio::test]
+    async fn overflowing_orders_are_rejected_and_server_keeps_answering() {
+        let simulator = exchange_with_liquidity();
+        simulator.deposit("alice", Token::UAH, "1000000");
+        let server = MockServer::start_with(simulator.clone()).await.unwrap();
+        let exchange =
+            Exchange::with_keys("alice".to_owned(), "secret".to_owned()).client(server.client());
+
+        let huge = "1000000000000000000";
+        assert!(matches!(
+            exchange
+                .create_order(order(huge, huge, OrderType::Buying))
+                .await,
+            Err(RichamsterError::NewOrderError(_))
+        ));
+        {
+            let mut state = simulator.state.lock().unwrap();
+            let info = MarketOrderInfo {
+                amount: "1".to_owned(),
+                currency_pair: i32::MIN,
+                order_type: OrderType::Buying,
+                total: None,
+            };
+            assert!(state.market_order("alice", &info, true).is_err());
+            let info = MarketOrderInfo {
+                currency_pair: 1,
+                total: Some(format!("1{}", "0".repeat(28))),
+                ..info
+            };
+            assert!(state.market_order("alice", &info, false).is_err());
+        }
+
+        let book = exchange
+            .order_book(OrderBookFilter::new(BTC_UAH))
+            .await
+            .unwrap();
+        assert_eq!(book.selling.unwrap().len(), 2);
+        assert_eq!(simulator.in_orders("alice", Token::UAH), "0.00000000");
+    }
+
+    #[tokio::test]
+    async fn services_trade_against_simulator() {
+        let simulator = exchange_with_liquidity();
+        simulator.deposit("alice", Token::UAH, "1000000");
+        let server = MockServer::start_with(simulator.clone()).await.unwrap();
+        let exchange =
+            Exchange::with_keys("alice".to_owned(), "secret".to_owned()).client(server.client());
+
+        let book = exchange
+            .order_book(OrderBookFilter::new(BTC_UAH))
+            .await
+            .unwrap();
+        assert_eq!(book.selling.unwrap()[0].unit_price, "1650000.00000000");
+
+        let calculated = exchange
+            .calculate_market_order(BTC_UAH, 0.2, OrderType::Buying)
+            .await
+            .unwrap();
+        assert_eq!(calculated.covered, 0.2);
+        assert_eq!(calculated.total_sum, 330_100.0);
+
+        let executed = exchange
+            .execute_market_order(BTC_UAH, 0.2, OrderType::Buying, None)
+            .await
+            .unwrap();
+        assert_eq!(executed.in_orders, 2);
+        assert_eq!(simulator.balance("alice", Token::BTC), "0.19960000");
+
+        let resting = exchange
+            .create_order(order("0.1", "1640000", OrderType::Buying))
+            .await
+            .unwrap();
+        let open = exchange
+            .user_orders(OrdersFilter::new(Some(BTC_UAH), None, None))
+            .await
+            .unwrap();
+        assert_eq!(open.results.len(), 1);
+        assert_eq!(open.results[0].unit_price, "1640000.00000000");
+        exchange
+            .destroy_user_order(resting.pk.unwrap())
+            .await
+            .unwrap();
+        assert!(matches!(
+            exchange.destroy_user_order(resting.pk.unwrap()).await,
+            Err(RichamsterError::OrderNotFound(_))
+        ));
+
+        let history = exchange
+            .orders_history(OrdersFilter::new(Some(BTC_UAH), None, None))
+            .await
+            .unwrap();
+        assert_eq!(history.results.len(), 2);
+
+        let user = User::with_keys("alice".to_owned(), "secret".to_owned()).client(server.client());
+        let balances = user.balances(None).await.unwrap();
+        let uah = balances
+            .iter()
+            .find(|b| b.currency.abbreviation == "UAH")
+            .unwrap();
+        assert_eq!(uah.balance, "669900.00000000");
+        assert_eq!(uah.in_orders, "0.00000000");
+    }
+}
@@ -19,7 +19,7 @@ impl Login {
 
 #[derive(Serialize, Deserialize, PartialEq, Clone, Debug)]
 pub enum LoginResponse {
-    Jwt(String),
+    Jwt(TokenData),
     RequiresTwoFactor(bool),
 }
 
@@ -89,6 +89,18 @@ pub struct Message {
 #[derive(Serialize, Deserialize, PartialEq, Clone, Debug)]
 pub struct OtpLogin {
     pub otp_token: String,
+    /// Token handed out by the first login step, when the server issues one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ephemeral_token: Option<String>,
+}
+
+impl OtpLogin {
+    pub fn new(otp_token: String) -> Self {
+        Self {
+            otp_token,
+            ephemeral_token: None,
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize, PartialEq, Clone, Debug)]
@@ -113,13 +125,15 @@ impl Display for OtpLoginResponseError {
 
 #[derive(Serialize, Deserialize, PartialEq, Clone, Debug)]
 pub enum OtpLoginResponse {
-    Jwt(String),
+    Jwt(TokenData),
     Error(OtpLoginResponseError),
 }
 
 #[derive(Serialize, Deserialize, PartialEq, Clone, Debug)]
 pub struct TokenData {
     pub access: String,
+    /// Empty when the server does not rotate refresh tokens.
+    #[serde(default)]
     pub refresh: String,
 }
 
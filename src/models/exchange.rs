@@ -13,11 +13,11 @@ pub struct CurrencyInfoResponse {
 
 #[derive(Serialize, Deserialize, PartialEq, Clone, Debug)]
 pub struct CurrencyInfo {
-    id: i32,
-    abbreviation: String,
-    title: String,
-    icon: Url,
-    precision: i32,
+    pub id: i32,
+    pub abbreviation: String,
+    pub title: String,
+    pub icon: Url,
+    pub precision: i32,
 }
 
 #[derive(Serialize, Deserialize, PartialEq, Clone, Debug)]
@@ -155,7 +155,7 @@ pub struct OrdersHistory {
 
 #[derive(Serialize, Deserialize, PartialEq, Clone, Debug)]
 pub struct OrderHistoryRecord {
-    pk: i32,
+    pub pk: i32,
     #[serde(deserialize_with = "crate::models::deserialize::string_timestamp_deserialize")]
     pub created_at: DateTime<Local>,
     #[serde(deserialize_with = "crate::models::deserialize::option_timestamp_deserialize")]
@@ -171,6 +171,7 @@ pub struct OrdersFilter {
     pair: Option<CurrencyPair>,
     ordering: Option<String>,
     page_size: Option<i32>,
+    page: Option<i32>,
 }
 
 impl OrdersFilter {
@@ -183,9 +184,15 @@ impl OrdersFilter {
             pair,
             ordering,
             page_size,
+            page: None,
         }
     }
 
+    pub fn page(mut self, page: i32) -> Self {
+        self.page = Some(page);
+        self
+    }
+
     pub fn compose_url(&self, url: &mut Url) -> String {
         if let Some(pair) = &self.pair {
             url.query_pairs_mut()
@@ -198,6 +205,10 @@ impl OrdersFilter {
             url.query_pairs_mut()
                 .append_pair("page_size", page_size.to_string().as_str());
         }
+        if let Some(page) = &self.page {
+            url.query_pairs_mut()
+                .append_pair("page", page.to_string().as_str());
+        }
         url.to_string()
     }
 }
@@ -223,6 +234,10 @@ pub struct NewOrder {
     #[serde(deserialize_with = "crate::models::deserialize::date_string_deserialize")]
     pub created_at: Option<DateTime<Local>>,
     pub is_partial: Option<bool>,
+    /// Local identifier tracked in the
+    /// [`OrderJournal`](crate::richamster::journal::OrderJournal), never sent to the server.
+    #[serde(skip)]
+    pub client_order_id: Option<String>,
 }
 
 impl NewOrder {
@@ -243,8 +258,23 @@ impl NewOrder {
             o_type: Some(order_type),
             created_at: None,
             is_partial: None,
+            client_order_id: Some(generate_client_order_id()),
         }
     }
+
+    /// Replaces the generated client order id.
+    pub fn client_order_id(mut self, id: impl Into<String>) -> Self {
+        self.client_order_id = Some(id.into());
+        self
+    }
+}
+
+fn generate_client_order_id() -> String {
+    format!(
+        "{:x}-{:016x}",
+        Local::now().timestamp_millis(),
+        fastrand::u64(..)
+    )
 }
 
 #[derive(Serialize, Deserialize, PartialEq, Clone, Debug)]
@@ -1,4 +1,6 @@
-use crate::api::{Api, AuthenticationApi, CLIENT, RequestData, RequestPath};
+use crate::api::client::RichamsterClient;
+use crate::api::transport::{TransportRequest, TransportResponse};
+use crate::api::{Api, AuthenticationApi, RequestData};
 use crate::errors::RichamsterError;
 use crate::models::auth::LoginResponse::{Jwt, RequiresTwoFactor};
 use crate::models::auth::{
@@ -6,100 +8,139 @@ use crate::models::auth::{
     OtpLoginResponseError, RefreshToken, RegisterUser, RegisterUserError, RegisterUserResponse,
     TokenData,
 };
-use reqwest::{IntoUrl, Method, Response, StatusCode};
+use reqwest::{Method, StatusCode};
 use serde::Serialize;
+use url::Url;
 
-pub struct Auth;
+#[derive(Clone, Debug, Default)]
+pub struct Auth {
+    client: RichamsterClient,
+}
+
+impl Auth {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn client(mut self, client: RichamsterClient) -> Self {
+        self.client = client;
+        self
+    }
+}
 
 impl Auth {
     async fn send_request(
-        url: impl IntoUrl,
+        &self,
+        api: Api,
+        url: Url,
         method: Method,
         body: impl Serialize,
-    ) -> Result<Response, reqwest::Error> {
-        CLIENT.request(method, url).json(&body).send().await
+    ) -> Result<TransportResponse, RichamsterError> {
+        let request = TransportRequest::new(method, url).json_body(serde_json::to_string(&body)?);
+        self.client.execute(api, request).await
     }
 
     pub async fn login(
+        &self,
         email: impl AsRef<str>,
         password: impl AsRef<str>,
     ) -> Result<LoginResponse, RichamsterError> {
         let login = Login::new(email.as_ref(), password.as_ref());
-        let RequestData(url, method) = Api::Authentication(AuthenticationApi::Login).request_data();
-        let resp = Self::send_request(url, method, login).await?;
-        match resp.status() {
-            StatusCode::OK => Ok(RequiresTwoFactor(true)),
-            StatusCode::CREATED => {
-                let token: TokenData = serde_json::from_str(&resp.text().await?)?;
-                Ok(Jwt(token.access))
-            }
-            StatusCode::SERVICE_UNAVAILABLE => Err(RichamsterError::ServiceUnavailable),
-            StatusCode::BAD_REQUEST => {
-                let text = resp.text().await?;
-                let error: LoginResponseError = serde_json::from_str(&text)?;
-                Err(RichamsterError::Login(error))
-            }
-            StatusCode::FORBIDDEN => {
-                let text = resp.text().await?;
-                let resp: LoginResponseError = serde_json::from_str(&text)?;
-                Err(RichamsterError::InvalidCredential(resp))
-            }
-            status => Err(RichamsterError::UnsupportedResponseCode(
-                status,
-                resp.text().await?,
-            )),
-        }
+        let api = Api::Authentication(AuthenticationApi::Login);
+        let RequestData(url, method) = self.client.request_data(&api);
+        let resp = self.send_request(api, url, method, login).await?;
+        login_response(&resp)
     }
 
     pub async fn register_user(
+        &self,
         register_user: RegisterUser,
     ) -> Result<RegisterUserResponse, RichamsterError> {
-        let RequestData(url, method) =
-            Api::Authentication(AuthenticationApi::Register).request_data();
-        let resp = Self::send_request(url, method, register_user).await?;
+        let api = Api::Authentication(AuthenticationApi::Register);
+        let RequestData(url, method) = self.client.request_data(&api);
+        let resp = self.send_request(api, url, method, register_user).await?;
 
         match resp.status() {
             StatusCode::CREATED => {
-                let response: RegisterUserResponse = serde_json::from_str(&resp.text().await?)?;
+                let response: RegisterUserResponse = resp.json()?;
                 Ok(response)
             }
             _ => {
-                let error: RegisterUserError = serde_json::from_str(&resp.text().await?)?;
-                Err(RichamsterError::Register(error))
+                let error: RegisterUserError = resp.json()?;
+                Err(RichamsterError::Register(Box::new(error)))
             }
         }
     }
 
-    pub async fn two_factor_login(otp_token: String) -> Result<OtpLoginResponse, RichamsterError> {
-        let RequestData(url, method) =
-            Api::Authentication(AuthenticationApi::TwoFactorLogin).request_data();
-        let resp = Self::send_request(url, method, OtpLogin { otp_token }).await?;
-        if resp.status() == StatusCode::CREATED {
-            let token: TokenData = serde_json::from_str(&resp.text().await?)?;
-            Ok(OtpLoginResponse::Jwt(token.access))
-        } else {
-            let error: OtpLoginResponseError = serde_json::from_str(&resp.text().await?)?;
-            Err(RichamsterError::Otp(error))
-        }
+    /// Second login step, not tied to the first one. Prefer
+    /// [`LoginFlow`](crate::richamster::login::LoginFlow), which carries the
+    /// server state of the first step over.
+    pub async fn two_factor_login(
+        &self,
+        otp_token: String,
+    ) -> Result<OtpLoginResponse, RichamsterError> {
+        let api = Api::Authentication(AuthenticationApi::TwoFactorLogin);
+        let RequestData(url, method) = self.client.request_data(&api);
+        let resp = self
+            .send_request(api, url, method, OtpLogin::new(otp_token))
+            .await?;
+        two_factor_response(&resp)
     }
 
-    pub async fn refresh_token(jwt_token: String) -> Result<TokenData, RichamsterError> {
-        let RequestData(url, method) =
-            Api::Authentication(AuthenticationApi::RefreshToken).request_data();
-        let resp = Self::send_request(url, method, RefreshToken { refresh: jwt_token }).await?;
+    pub async fn refresh_token(&self, jwt_token: String) -> Result<TokenData, RichamsterError> {
+        let api = Api::Authentication(AuthenticationApi::RefreshToken);
+        let RequestData(url, method) = self.client.request_data(&api);
+        let resp = self
+            .send_request(api, url, method, RefreshToken { refresh: jwt_token })
+            .await?;
         match resp.status() {
             StatusCode::OK => {
-                let response: TokenData = serde_json::from_str(&resp.text().await?)?;
+                let response: TokenData = resp.json()?;
                 Ok(response)
             }
             StatusCode::BAD_REQUEST => {
-                let response: NonFieldsError = serde_json::from_str(&resp.text().await?)?;
+                let response: NonFieldsError = resp.json()?;
                 Err(RichamsterError::InvalidJwtToken(response))
             }
             status => Err(RichamsterError::UnsupportedResponseCode(
                 status,
-                resp.text().await?,
+                resp.text().to_owned(),
             )),
         }
     }
 }
+
+pub(crate) fn login_response(resp: &TransportResponse) -> Result<LoginResponse, RichamsterError> {
+    match resp.status() {
+        StatusCode::OK => Ok(RequiresTwoFactor(true)),
+        StatusCode::CREATED => {
+            let token: TokenData = resp.json()?;
+            Ok(Jwt(token))
+        }
+        StatusCode::SERVICE_UNAVAILABLE => Err(RichamsterError::ServiceUnavailable),
+        StatusCode::BAD_REQUEST => {
+            let error: LoginResponseError = resp.json()?;
+            Err(RichamsterError::Login(Box::new(error)))
+        }
+        StatusCode::FORBIDDEN => {
+            let resp: LoginResponseError = resp.json()?;
+            Err(RichamsterError::InvalidCredential(Box::new(resp)))
+        }
+        status => Err(RichamsterError::UnsupportedResponseCode(
+            status,
+            resp.text().to_owned(),
+        )),
+    }
+}
+
+pub(crate) fn two_factor_response(
+    resp: &TransportResponse,
+) -> Result<OtpLoginResponse, RichamsterError> {
+    if resp.status() == StatusCode::CREATED {
+        let token: TokenData = resp.json()?;
+        Ok(OtpLoginResponse::Jwt(token))
+    } else {
+        let error: OtpLoginResponseError = resp.json()?;
+        Err(RichamsterError::Otp(Box::new(error)))
+    }
+}
@@ -1,6 +1,15 @@
-use hmac_sha256::HMAC;
-use reqwest::RequestBuilder;
+use crate::api::Api;
+use crate::api::client::RichamsterClient;
+use crate::api::permissions::Permissions;
+use crate::api::signing::{SigningScheme, canonical_request};
+use crate::api::transport::{TransportRequest, TransportResponse};
+use crate::errors::RichamsterError;
+use crate::richamster::session::Session;
+use crate::richamster::signer::Signer;
+use async_trait::async_trait;
+use reqwest::StatusCode;
 use secrecy::{ExposeSecret, SecretBox};
+use std::sync::{Arc, RwLock};
 
 const HEADER_API_KEY: &str = "Api-Key";
 const HEADER_SIGNATURE: &str = "Signature";
@@ -15,7 +24,7 @@ impl JwtToken {
         Self(SecretBox::new(Box::new(token)))
     }
 
-    fn value(&self) -> &str {
+    pub(crate) fn value(&self) -> &str {
         self.0.expose_secret()
     }
 }
@@ -28,7 +37,7 @@ impl ApiKey {
         Self(SecretBox::new(Box::new(api_key)))
     }
 
-    fn value(&self) -> &str {
+    pub(crate) fn value(&self) -> &str {
         self.0.expose_secret()
     }
 }
@@ -41,7 +50,7 @@ impl SecretKey {
         Self(SecretBox::new(Box::new(secret_key)))
     }
 
-    fn value(&self) -> &str {
+    pub(crate) fn value(&self) -> &str {
         self.0.expose_secret()
     }
 }
@@ -52,46 +61,164 @@ pub enum AuthState {
     Unauthorized,
     JwtTokenAuth(JwtToken),
     ApiSecretKeyAuth(ApiKey, SecretKey),
+    /// Fixed JWT, sent as is and never refreshed, plus API keys for signed
+    /// requests. Use [`AuthState::Session`] for a token that is refreshed.
     JwtTokenWithApiSecretKeyAuth(JwtToken, ApiKey, SecretKey),
+    Session(Session),
+    /// API key whose requests are signed by a [`Signer`] holding the secret key.
+    ApiKeySignerAuth(ApiKey, Arc<dyn Signer>),
 }
 
-pub trait HeaderCompose {
-    fn compose(self, auth_state: &AuthState, payload: Option<&str>) -> RequestBuilder;
+/// [`AuthState`] shared by the clones of a service and by the views of a
+/// [`Richamster`](crate::richamster::Richamster). Replacing it switches all of
+/// them to the new credentials at once; requests already sent finish with the
+/// previous ones.
+///
+/// Each handle also carries the [`Permissions`] its requests are checked
+/// against. They stay with the handle whatever client it is used with, and
+/// [`restrict`](Self::restrict) can only narrow them.
+#[derive(Clone, Debug, Default)]
+pub struct SharedAuthState {
+    state: Arc<RwLock<Arc<AuthState>>>,
+    permissions: Permissions,
 }
 
-impl HeaderCompose for RequestBuilder {
-    fn compose(self, auth_state: &AuthState, payload: Option<&str>) -> RequestBuilder {
-        match auth_state {
+impl SharedAuthState {
+    pub fn new(auth_state: AuthState) -> Self {
+        Self {
+            state: Arc::new(RwLock::new(Arc::new(auth_state))),
+            permissions: Permissions::default(),
+        }
+    }
+
+    pub fn current(&self) -> Arc<AuthState> {
+        self.state.read().unwrap().clone()
+    }
+
+    /// Installs `auth_state` and returns the state it replaces, e.g. after
+    /// rotating the API keys. Permissions are kept.
+    pub fn replace(&self, auth_state: AuthState) -> Arc<AuthState> {
+        std::mem::replace(&mut *self.state.write().unwrap(), Arc::new(auth_state))
+    }
+
+    pub fn permissions(&self) -> Permissions {
+        self.permissions
+    }
+
+    /// Handle to the same credentials allowed only what both `permissions` and
+    /// this handle allow.
+    pub fn restrict(&self, permissions: Permissions) -> Self {
+        Self {
+            state: self.state.clone(),
+            permissions: self.permissions.intersect(permissions),
+        }
+    }
+
+    /// Fails with [`RichamsterError::PermissionDenied`] before anything is
+    /// signed or sent, including a session refresh, when `api` needs a
+    /// capability this handle lacks.
+    pub(crate) async fn send(
+        &self,
+        client: &RichamsterClient,
+        api: Api,
+        request: TransportRequest,
+    ) -> Result<TransportResponse, RichamsterError> {
+        self.permissions.check(api)?;
+        self.current().send(client, api, request).await
+    }
+}
+
+impl From<AuthState> for SharedAuthState {
+    fn from(auth_state: AuthState) -> Self {
+        Self::new(auth_state)
+    }
+}
+
+#[async_trait]
+pub trait HeaderCompose: Sized {
+    async fn compose(
+        self,
+        auth_state: &AuthState,
+        scheme: SigningScheme,
+    ) -> Result<Self, RichamsterError>;
+}
+
+#[async_trait]
+impl HeaderCompose for TransportRequest {
+    async fn compose(
+        self,
+        auth_state: &AuthState,
+        scheme: SigningScheme,
+    ) -> Result<Self, RichamsterError> {
+        Ok(match auth_state {
             AuthState::Unauthorized => self,
             AuthState::JwtTokenAuth(jwt_token) => {
-                AuthState::insert_jwt_token_header(self, jwt_token)
+                AuthState::insert_jwt_token_header(self, jwt_token.value())
             }
             AuthState::ApiSecretKeyAuth(api, secret) => {
-                AuthState::insert_keys_headers(self, api, secret, payload.unwrap_or(""))
+                AuthState::insert_keys_headers(self, api, secret, scheme).await?
             }
             AuthState::JwtTokenWithApiSecretKeyAuth(jwt_token, api, secret) => {
-                let builder = AuthState::insert_jwt_token_header(self, jwt_token);
-                AuthState::insert_keys_headers(builder, api, secret, payload.unwrap_or(""))
+                let request = AuthState::insert_jwt_token_header(self, jwt_token.value());
+                AuthState::insert_keys_headers(request, api, secret, scheme).await?
             }
-        }
+            AuthState::Session(session) => {
+                AuthState::insert_jwt_token_header(self, &session.access_token().0)
+            }
+            AuthState::ApiKeySignerAuth(api, signer) => {
+                AuthState::insert_keys_headers(self, api, signer.as_ref(), scheme).await?
+            }
+        })
     }
 }
 
 impl AuthState {
-    fn insert_keys_headers(
-        builder: RequestBuilder,
+    async fn insert_keys_headers(
+        request: TransportRequest,
         api: &ApiKey,
-        secret: &SecretKey,
-        payload: &str,
-    ) -> RequestBuilder {
-        let hmac = HMAC::mac(payload, secret.value());
-        let hex = hex::encode(hmac);
-        builder
+        signer: &dyn Signer,
+        scheme: SigningScheme,
+    ) -> Result<TransportRequest, RichamsterError> {
+        let request = scheme.stamp(request);
+        let signature = signer.sign(&canonical_request(scheme, &request)).await?;
+        Ok(request
             .header(HEADER_API_KEY, api.value())
-            .header(HEADER_SIGNATURE, hex)
+            .header(HEADER_SIGNATURE, signature))
     }
 
-    fn insert_jwt_token_header(builder: RequestBuilder, jwt_token: &JwtToken) -> RequestBuilder {
-        builder.header(HEADER_AUTH, format!("{} {}", JWT, jwt_token.value()))
+    fn insert_jwt_token_header(request: TransportRequest, jwt_token: &str) -> TransportRequest {
+        request.header(HEADER_AUTH, format!("{} {}", JWT, jwt_token))
+    }
+
+    /// Sends the request with the credentials of this state. A [`Session`] token
+    /// is refreshed when it is about to expire, and once more when the server
+    /// answers `401 Unauthorized`, in which case the request is sent again.
+    pub(crate) async fn send(
+        &self,
+        client: &RichamsterClient,
+        api: Api,
+        request: TransportRequest,
+    ) -> Result<TransportResponse, RichamsterError> {
+        let AuthState::Session(session) = self else {
+            let scheme = client.signing_scheme();
+            return client
+                .execute_composed(api, request, |request| request.compose(self, scheme))
+                .await;
+        };
+        let (token, generation) = session.valid_access_token(client).await?;
+        let response = client
+            .execute(
+                api,
+                AuthState::insert_jwt_token_header(request.clone(), &token),
+            )
+            .await?;
+        if response.status() != StatusCode::UNAUTHORIZED {
+            return Ok(response);
+        }
+        session.refresh_after(client, generation).await?;
+        let (token, _) = session.access_token();
+        client
+            .execute(api, AuthState::insert_jwt_token_header(request, &token))
+            .await
     }
 }
@@ -0,0 +1,334 @@
+use crate::errors::RichamsterError;
+use crate::richamster::common::{ApiKey, AuthState, JwtToken, SecretKey};
+use secrecy::{ExposeSecret, SecretString};
+use serde_derive::Deserialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+const DEFAULT_ENV_PREFIX: &str = "RICHAMSTER";
+const DEFAULT_PROFILE: &str = "default";
+
+/// Secrets a service authenticates with. Which [`AuthState`] they produce
+/// depends on what is present: a JWT, a key pair, or both.
+#[derive(Debug, Default)]
+pub struct Credentials {
+    pub(crate) jwt: Option<SecretString>,
+    pub(crate) api_key: Option<SecretString>,
+    pub(crate) secret_key: Option<SecretString>,
+}
+
+impl Credentials {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn jwt(mut self, jwt: impl Into<String>) -> Self {
+        self.jwt = Some(jwt.into().into());
+        self
+    }
+
+    pub fn keys(mut self, api_key: impl Into<String>, secret_key: impl Into<String>) -> Self {
+        self.api_key = Some(api_key.into().into());
+        self.secret_key = Some(secret_key.into().into());
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.jwt.is_none() && self.api_key.is_none() && self.secret_key.is_none()
+    }
+
+    pub fn into_auth_state(self) -> Result<AuthState, RichamsterError> {
+        let jwt = self.jwt.map(|jwt| JwtToken::new(expose(&jwt)));
+        let keys = match (self.api_key, self.secret_key) {
+            (Some(api_key), Some(secret_key)) => Some((
+                ApiKey::new(expose(&api_key)),
+                SecretKey::new(expose(&secret_key)),
+            )),
+            (None, None) => None,
+            _ => {
+                return Err(RichamsterError::Credentials(
+                    "api key and secret key must be given together".to_owned(),
+                ));
+            }
+        };
+        Ok(match (jwt, keys) {
+            (Some(jwt), Some((api_key, secret_key))) => {
+                AuthState::JwtTokenWithApiSecretKeyAuth(jwt, api_key, secret_key)
+            }
+            (Some(jwt), None) => AuthState::JwtTokenAuth(jwt),
+            (None, Some((api_key, secret_key))) => AuthState::ApiSecretKeyAuth(api_key, secret_key),
+            (None, None) => AuthState::Unauthorized,
+        })
+    }
+}
+
+fn expose(secret: &SecretString) -> String {
+    secret.expose_secret().to_owned()
+}
+
+/// Source of [`Credentials`], passed to the `with_credentials` constructor of a
+/// service.
+pub trait CredentialProvider {
+    fn credentials(&self) -> Result<Credentials, RichamsterError>;
+
+    fn auth_state(&self) -> Result<AuthState, RichamsterError> {
+        self.credentials()?.into_auth_state()
+    }
+}
+
+/// Reads `RICHAMSTER_JWT`, `RICHAMSTER_API_KEY` and `RICHAMSTER_SECRET_KEY`, or
+/// the same names with another prefix. Fails when none of them is set.
+#[derive(Clone, Debug)]
+pub struct EnvProvider {
+    prefix: String,
+}
+
+impl Default for EnvProvider {
+    fn default() -> Self {
+        Self {
+            prefix: DEFAULT_ENV_PREFIX.to_owned(),
+        }
+    }
+}
+
+impl EnvProvider {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.prefix = prefix.into();
+        self
+    }
+
+    fn load(&self, var: impl Fn(&str) -> Option<String>) -> Result<Credentials, RichamsterError> {
+        let var = |name: &str| var(&format!("{}_{name}", self.prefix)).map(SecretString::from);
+        let credentials = Credentials {
+            jwt: var("JWT"),
+            api_key: var("API_KEY"),
+            secret_key: var("SECRET_KEY"),
+        };
+        if credentials.is_empty() {
+            return Err(RichamsterError::Credentials(format!(
+                "none of the {}_* variables is set",
+                self.prefix
+            )));
+        }
+        Ok(credentials)
+    }
+}
+
+impl CredentialProvider for EnvProvider {
+    fn credentials(&self) -> Result<Credentials, RichamsterError> {
+        self.load(|name| std::env::var(name).ok())
+    }
+}
+
+#[derive(Deserialize)]
+struct Profile {
+    jwt: Option<String>,
+    api_key: Option<String>,
+    secret_key: Option<String>,
+}
+
+/// Credentials file with one table per profile, read as JSON when the file
+/// name ends with `.json` and as TOML otherwise:
+///
+/// ```toml
+/// [default]
+/// api_key = "..."
+/// secret_key = "..."
+///
+/// [trading]
+/// jwt = "..."
+/// ```
+#[derive(Clone, Debug)]
+pub struct FileProvider {
+    path: PathBuf,
+    profile: String,
+}
+
+impl FileProvider {
+    pub fn new(path: impl AsRef<Path>) -> Self {
+        Self {
+            path: path.as_ref().to_owned(),
+            profile: DEFAULT_PROFILE.to_owned(),
+        }
+    }
+
+    /// Profile to read, `default` by default.
+    pub fn profile(mut self, profile: impl Into<String>) -> Self {
+        self.profile = profile.into();
+        self
+    }
+
+    fn parse(&self, contents: &str) -> Result<Credentials, RichamsterError> {
+        let json = self.path.extension().is_some_and(|ext| ext == "json");
+        // Errors are built from positions and parser messages only, as a
+        // deserialization message or the `Display` of a TOML error may quote a secret.
+        let profile: Option<Profile> = if json {
+            let mut profiles: HashMap<String, serde_json::Value> = serde_json::from_str(contents)
+                .map_err(|e| {
+                self.error(format!(
+                    "invalid JSON at line {} column {}",
+                    e.line(),
+                    e.column()
+                ))
+            })?;
+            profiles
+                .remove(&self.profile)
+                .map(serde_json::from_value)
+                .transpose()
+                .map_err(|_| self.invalid_profile())?
+        } else {
+            let mut profiles: HashMap<String, toml::Value> =
+                toml::from_str(contents).map_err(|e| {
+                    let line = e
+                        .span()
+                        .map(|span| contents[..span.start].matches('\n').count() + 1);
+                    self.error(match line {
+                        Some(line) => format!("invalid TOML at line {line}: {}", e.message()),
+                        None => format!("invalid TOML: {}", e.message()),
+                    })
+                })?;
+            profiles
+                .remove(&self.profile)
+                .map(toml::Value::try_into)
+                .transpose()
+                .map_err(|_| self.invalid_profile())?
+        };
+        let profile =
+            profile.ok_or_else(|| self.error(format!("profile {} not found", self.profile)))?;
+        Ok(Credentials {
+            jwt: profile.jwt.map(Into::into),
+            api_key: profile.api_key.map(Into::into),
+            secret_key: profile.secret_key.map(Into::into),
+        })
+    }
+
+    fn invalid_profile(&self) -> RichamsterError {
+        self.error(format!(
+            "profile {} must be a table of string values",
+            self.profile
+        ))
+    }
+
+    fn error(&self, message: String) -> RichamsterError {
+        RichamsterError::Credentials(format!("{}: {message}", self.path.display()))
+    }
+}
+
+impl CredentialProvider for FileProvider {
+    fn credentials(&self) -> Result<Credentials, RichamsterError> {
+        self.parse(&std::fs::read_to_string(&self.path)?)
+    }
+}
+
+/// Credentials produced by a closure, e.g. one querying a secrets manager.
+pub struct CallbackProvider<F> {
+    callback: F,
+}
+
+impl<F> CallbackProvider<F>
+where
+    F: Fn() -> Result<Credentials, RichamsterError>,
+{
+    pub fn new(callback: F) -> Self {
+        Self { callback }
+    }
+}
+
+impl<F> CredentialProvider for CallbackProvider<F>
+where
+    F: Fn() -> Result<Credentials, RichamsterError>,
+{
+    fn credentials(&self) -> Result<Credentials, RichamsterError> {
+        (self.callback)()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const TOML: &str = r#"
+[default]
+api_key = "api"
+secret_key = "secret"
+
+[trading]
+jwt = "jwt"
+api_key = "api"
+secret_key = "secret"
+"#;
+
+    #[test]
+    fn env_variables_use_prefix() {
+        let env = HashMap::from([
+            ("BOT_API_KEY".to_owned(), "api".to_owned()),
+            ("BOT_SECRET_KEY".to_owned(), "secret".to_owned()),
+        ]);
+        let lookup = |name: &str| env.get(name).cloned();
+        let state = EnvProvider::new()
+            .prefix("BOT")
+            .load(lookup)
+            .unwrap()
+            .into_auth_state()
+            .unwrap();
+        assert!(matches!(state, AuthState::ApiSecretKeyAuth(..)));
+        assert!(matches!(
+            EnvProvider::new().load(lookup),
+            Err(RichamsterError::Credentials(_))
+        ));
+    }
+
+    #[test]
+    fn file_profiles_are_selected_by_name() {
+        let toml = FileProvider::new("credentials.toml").profile("trading");
+        let state = toml.parse(TOML).unwrap().into_auth_state().unwrap();
+        assert!(matches!(state, AuthState::JwtTokenWithApiSecretKeyAuth(..)));
+        assert!(toml.clone().profile("missing").parse(TOML).is_err());
+
+        let json = FileProvider::new("credentials.json");
+        let state = json
+            .parse(r#"{"default":{"jwt":"jwt"}}"#)
+            .unwrap()
+            .into_auth_state()
+            .unwrap();
+        assert!(matches!(state, AuthState::JwtTokenAuth(_)));
+    }
+
+    #[test]
+    fn parse_errors_do_not_quote_secrets() {
+        let toml = FileProvider::new("credentials.toml");
+        let json = FileProvider::new("credentials.json");
+        let errors = [
+            toml.parse("[default]\napi_key = \"api\"\nsecret_key = \"s3cret\" x\n"),
+            toml.parse("[default]\nsecret_key = \"s3cret\n"),
+            toml.parse("default = \"s3cret\"\n"),
+            json.parse(r#"{"default":{"secret_key":"s3cret"},}"#),
+            json.parse(r#"{"default":"s3cret"}"#),
+        ];
+        for error in errors {
+            let Err(RichamsterError::Credentials(message)) = error else {
+                panic!("expected a credentials error, got {error:?}");
+            };
+            assert!(!message.contains("s3cret"), "{message}");
+        }
+    }
+
+    #[test]
+    fn incomplete_key_pair_is_rejected() {
+        let provider =
+            CallbackProvider::new(|| Ok(Credentials::new().keys("api", "secret").jwt("jwt")));
+        assert!(matches!(
+            provider.auth_state(),
+            Ok(AuthState::JwtTokenWithApiSecretKeyAuth(..))
+        ));
+        let half = Credentials {
+            api_key: Some("api".into()),
+            ..Credentials::default()
+        };
+        assert!(half.into_auth_state().is_err());
+    }
+}
@@ -1,6 +1,10 @@
+use crate::api::Api;
 use crate::api::ExchangeApi;
+use crate::api::client::RichamsterClient;
+use crate::api::permissions::Permissions;
+use crate::api::retry;
 use crate::api::token::CurrencyPair;
-use crate::api::{Api, RequestPath};
+use crate::api::transport::TransportResponse;
 use crate::api::{RequestData, token};
 use crate::errors::RichamsterError;
 use crate::models::common::OrderType;
@@ -9,15 +13,38 @@ use crate::models::exchange::{
     MarketOrderCalculator, MarketOrderInfo, MarketOrderResponse, NewOrder, NewOrderError,
     OrderBookFilter, OrdersBook, OrdersFilter, OrdersHistory, TickerResponse,
 };
-use crate::richamster::common::{ApiKey, AuthState, HeaderCompose, JwtToken, SecretKey};
+use crate::richamster::common::{ApiKey, AuthState, JwtToken, SecretKey, SharedAuthState};
+use crate::richamster::credentials::CredentialProvider;
+use crate::richamster::journal::{OrderJournal, SubmissionState};
+use crate::richamster::markets::MarketRegistry;
+use crate::richamster::session::Session;
+use crate::richamster::signer::Signer;
 use crate::{process_response, send_request};
+use chrono::{DateTime, Local};
 use percent_encoding::percent_decode_str;
 use reqwest::StatusCode;
+use std::sync::Arc;
+use std::time::Duration;
 use url::Url;
 
-#[derive(Default)]
+/// Lookup used before re-sending an order whose previous submission failed in a
+/// way that does not tell whether the server accepted it.
+pub trait OrderDedup {
+    /// Returns the order created by an earlier attempt, if there is one.
+    fn find_existing(
+        &self,
+        exchange: &Exchange,
+        order: &NewOrder,
+        submitted_at: DateTime<Local>,
+    ) -> impl Future<Output = Result<Option<NewOrder>, RichamsterError>> + Send;
+}
+
+#[derive(Clone, Debug, Default)]
 pub struct Exchange {
-    auth_state: AuthState,
+    client: RichamsterClient,
+    auth_state: SharedAuthState,
+    markets: MarketRegistry,
+    journal: OrderJournal,
 }
 
 impl Exchange {
@@ -27,57 +54,142 @@ impl Exchange {
 
     pub fn with_jwt_token(token: String) -> Self {
         Self {
-            auth_state: AuthState::JwtTokenAuth(JwtToken::new(token)),
+            auth_state: SharedAuthState::new(AuthState::JwtTokenAuth(JwtToken::new(token))),
+            ..Self::default()
         }
     }
 
     pub fn with_keys(api_key: String, secret_key: String) -> Self {
         Self {
-            auth_state: AuthState::ApiSecretKeyAuth(
+            auth_state: SharedAuthState::new(AuthState::ApiSecretKeyAuth(
                 ApiKey::new(api_key),
                 SecretKey::new(secret_key),
-            ),
+            )),
+            ..Self::default()
+        }
+    }
+
+    pub fn with_jwt_and_keys(jwt: String, api_key: String, secret_key: String) -> Self {
+        Self {
+            auth_state: SharedAuthState::new(AuthState::JwtTokenWithApiSecretKeyAuth(
+                JwtToken::new(jwt),
+                ApiKey::new(api_key),
+                SecretKey::new(secret_key),
+            )),
+            ..Self::default()
+        }
+    }
+
+    /// Authenticates with the JWT of `session`, refreshed as needed.
+    pub fn with_session(session: Session) -> Self {
+        Self {
+            auth_state: SharedAuthState::new(AuthState::Session(session)),
+            ..Self::default()
         }
     }
+
+    /// Signs requests with `signer` instead of a secret key held in memory.
+    pub fn with_signer(api_key: String, signer: Arc<dyn Signer>) -> Self {
+        Self {
+            auth_state: SharedAuthState::new(AuthState::ApiKeySignerAuth(
+                ApiKey::new(api_key),
+                signer,
+            )),
+            ..Self::default()
+        }
+    }
+
+    /// Authenticates with the credentials loaded from `provider`.
+    pub fn with_credentials(provider: &impl CredentialProvider) -> Result<Self, RichamsterError> {
+        Ok(Self {
+            auth_state: SharedAuthState::new(provider.auth_state()?),
+            ..Self::default()
+        })
+    }
+
+    /// Service authenticating with `auth_state`, shared with other services.
+    pub(crate) fn shared(client: RichamsterClient, auth_state: SharedAuthState) -> Self {
+        Self {
+            client,
+            auth_state,
+            ..Self::default()
+        }
+    }
+
+    /// Credentials of this service and its clones, see [`SharedAuthState::replace`].
+    pub fn auth_state(&self) -> &SharedAuthState {
+        &self.auth_state
+    }
+
+    /// Limits this handle to `permissions`, see [`Permissions`]. Capabilities
+    /// can only be removed, by this call as by any later one.
+    pub fn restrict(mut self, permissions: Permissions) -> Self {
+        self.auth_state = self.auth_state.restrict(permissions);
+        self
+    }
+
+    pub fn client(mut self, client: RichamsterClient) -> Self {
+        self.client = client;
+        self
+    }
+
+    /// Registry used to resolve currency pairs to market ids. Pass a clone of the
+    /// same registry to several exchanges to share its cache.
+    pub fn market_registry(mut self, markets: MarketRegistry) -> Self {
+        self.markets = markets;
+        self
+    }
+
+    pub fn markets(&self) -> &MarketRegistry {
+        &self.markets
+    }
+
+    /// Journal recording orders submitted through [`Exchange::create_order`].
+    /// Share a clone between exchanges trading from the same account.
+    pub fn order_journal(mut self, journal: OrderJournal) -> Self {
+        self.journal = journal;
+        self
+    }
+
+    pub fn journal(&self) -> &OrderJournal {
+        &self.journal
+    }
 }
 
 impl Exchange {
     pub async fn restrictions_list(&self) -> Result<Vec<CurrencyPairRestriction>, RichamsterError> {
-        let RequestData(url, method) = Api::Exchange(ExchangeApi::Restrictions).request_data();
-        Ok(send_request!(url, method, self.auth_state).json().await?)
+        let api = Api::Exchange(ExchangeApi::Restrictions);
+        let RequestData(url, method) = self.client.request_data(&api);
+        Ok(send_request!(self.client, api, url, method, self.auth_state).json()?)
     }
 
     pub async fn ticker_list(
         &self,
         pair: Option<CurrencyPair>,
     ) -> Result<TickerResponse, RichamsterError> {
-        let RequestData(mut url, method) = Api::Exchange(ExchangeApi::TickerList).request_data();
+        let api = Api::Exchange(ExchangeApi::TickerList);
+        let RequestData(mut url, method) = self.client.request_data(&api);
         if let Some(pair) = pair {
             url.query_pairs_mut()
                 .append_pair("pair", pair.to_string().as_str());
         }
-        Ok(send_request!(url, method, self.auth_state).json().await?)
+        Ok(send_request!(self.client, api, url, method, self.auth_state).json()?)
     }
 
     pub async fn favourites_pair_toggle(
         &self,
         pair: CurrencyPair,
     ) -> Result<FavouritePairResponse, RichamsterError> {
-        let market_list = self.markets_list().await?;
-        let market = if let Some(m) = market_list
-            .iter()
-            .find(|m| m.abbreviation == pair.to_string())
-        {
-            m
-        } else {
-            return Err(RichamsterError::IllegalCurrencyPair(pair));
-        };
-        let RequestData(url, method) = Api::Exchange(ExchangeApi::Favourites).request_data();
-        let url = percent_decode_str(url.to_string().as_str())
-            .decode_utf8_lossy()
-            .replace("{id}", market.id.to_string().as_str());
+        let market_id = self.markets.market_id(self, pair).await?;
+        let api = Api::Exchange(ExchangeApi::Favourites);
+        let RequestData(url, method) = self.client.request_data(&api);
+        let url = Url::parse(
+            &percent_decode_str(url.as_str())
+                .decode_utf8_lossy()
+                .replace("{id}", market_id.to_string().as_str()),
+        )?;
 
-        let resp = send_request!(url, method, self.auth_state);
+        let resp = send_request!(self.client, api, url, method, self.auth_state);
         process_response!(resp, FavouritePairResponse)
     }
 
@@ -85,57 +197,64 @@ impl Exchange {
         &self,
         token: Option<token::Token>,
     ) -> Result<CurrencyInfoResponse, RichamsterError> {
-        let RequestData(mut url, method) = Api::Exchange(ExchangeApi::Currencies).request_data();
+        let api = Api::Exchange(ExchangeApi::Currencies);
+        let RequestData(mut url, method) = self.client.request_data(&api);
         if let Some(t) = token {
             url.query_pairs_mut()
                 .append_pair("abbreviation", t.as_ref());
         }
-        let resp = send_request!(url, method, self.auth_state);
+        let resp = send_request!(self.client, api, url, method, self.auth_state);
         process_response!(resp, CurrencyInfoResponse)
     }
 
     pub async fn markets_list(&self) -> Result<Vec<Market>, RichamsterError> {
-        let RequestData(url, method) = Api::Exchange(ExchangeApi::Markets).request_data();
-        let resp = send_request!(url, method, self.auth_state);
+        let api = Api::Exchange(ExchangeApi::Markets);
+        let RequestData(url, method) = self.client.request_data(&api);
+        let resp = send_request!(self.client, api, url, method, self.auth_state);
         process_response!(resp, Vec<Market>)
     }
 
     pub async fn order_book(&self, filter: OrderBookFilter) -> Result<OrdersBook, RichamsterError> {
-        let RequestData(mut url, method) = Api::Exchange(ExchangeApi::OrderBook).request_data();
-        let url = filter.compose_url(&mut url);
-        Ok(send_request!(url, method, self.auth_state).json().await?)
+        let api = Api::Exchange(ExchangeApi::OrderBook);
+        let RequestData(mut url, method) = self.client.request_data(&api);
+        filter.compose_url(&mut url);
+        Ok(send_request!(self.client, api, url, method, self.auth_state).json()?)
     }
 
     pub async fn orders_history(
         &self,
         filter: OrdersFilter,
     ) -> Result<OrdersHistory, RichamsterError> {
-        let RequestData(mut url, method) = Api::Exchange(ExchangeApi::OrdersHistory).request_data();
-        let url = filter.compose_url(&mut url);
-        let response = send_request!(url, method, self.auth_state);
-        let string = response.text().await?;
-        let response: OrdersHistory = serde_json::from_str(&string)?;
+        let api = Api::Exchange(ExchangeApi::OrdersHistory);
+        let RequestData(mut url, method) = self.client.request_data(&api);
+        filter.compose_url(&mut url);
+        let response = send_request!(self.client, api, url, method, self.auth_state);
+        let response: OrdersHistory = response.json()?;
         Ok(response)
     }
 
     pub async fn next_orders_history(&self, url: Url) -> Result<OrdersHistory, RichamsterError> {
-        let response = send_request!(url, reqwest::Method::GET, self.auth_state);
-        let string = response.text().await?;
-        let orders_history: OrdersHistory = serde_json::from_str(&string)?;
+        let api = Api::Exchange(ExchangeApi::OrdersHistory);
+        let response = send_request!(self.client, api, url, reqwest::Method::GET, self.auth_state);
+        let orders_history: OrdersHistory = response.json()?;
         Ok(orders_history)
     }
 
+    /// A timeout fails with [`RichamsterError::OutcomeUnknown`], the order may
+    /// have been cancelled; check [`Exchange::user_orders`] before assuming it was not.
+    /// The same applies when the returned future is dropped before completion.
     pub async fn destroy_user_order(&self, id: i32) -> Result<(), RichamsterError> {
-        let RequestData(mut url, method) = Api::Exchange(ExchangeApi::DestroyOrder).request_data();
+        let api = Api::Exchange(ExchangeApi::DestroyOrder);
+        let RequestData(mut url, method) = self.client.request_data(&api);
         url = url.join(id.to_string().as_str())?;
-        let resp = send_request!(url, method, self.auth_state);
+        let resp = send_request!(self.client, api, url, method, self.auth_state);
         match resp.status() {
             StatusCode::NO_CONTENT => Ok(()),
             StatusCode::UNAUTHORIZED => Err(RichamsterError::UnauthorizedAccess),
             StatusCode::NOT_FOUND => Err(RichamsterError::OrderNotFound(id)),
             status => Err(RichamsterError::UnsupportedResponseCode(
                 status,
-                resp.text().await?,
+                resp.text().to_owned(),
             )),
         }
     }
@@ -144,32 +263,72 @@ impl Exchange {
         &self,
         filter: OrdersFilter,
     ) -> Result<OrdersHistory, RichamsterError> {
-        let RequestData(mut url, method) = Api::Exchange(ExchangeApi::UserOrders).request_data();
-        let url = filter.compose_url(&mut url);
-        let resp = send_request!(url, method, self.auth_state);
-        let string = resp.text().await?;
-        let response: OrdersHistory = serde_json::from_str(&string)?;
+        let api = Api::Exchange(ExchangeApi::UserOrders);
+        let RequestData(mut url, method) = self.client.request_data(&api);
+        filter.compose_url(&mut url);
+        let resp = send_request!(self.client, api, url, method, self.auth_state);
+        let response: OrdersHistory = resp.json()?;
         Ok(response)
     }
 
+    /// A timeout fails with [`RichamsterError::OutcomeUnknown`], the order may
+    /// have been placed. The same applies when the returned future is dropped
+    /// before completion. [`Exchange::create_order_with_dedup`] resolves this by
+    /// looking the order up before resubmitting.
+    ///
+    /// Orders with a [`client_order_id`](NewOrder::client_order_id) are tracked
+    /// in the [`journal`](Exchange::journal) and the created order carries the same id.
     pub async fn create_order(&self, order: NewOrder) -> Result<NewOrder, RichamsterError> {
-        let RequestData(url, method) = Api::Exchange(ExchangeApi::NewOrder).request_data();
-        let resp = send_request!(url, method, self.auth_state, serde_json::to_string(&order)?);
+        self.place_order(&order).await.0
+    }
+
+    /// Submits a journaled order, also returning the `Retry-After` of the response.
+    async fn place_order(
+        &self,
+        order: &NewOrder,
+    ) -> (Result<NewOrder, RichamsterError>, Option<Duration>) {
+        self.journal.begin(order);
+        let (result, retry_after) = match self.submit_order(order).await {
+            Ok(resp) => (
+                Self::created_order(&resp),
+                retry::retry_after(resp.headers()),
+            ),
+            Err(e) => (Err(e), None),
+        };
+        let result = result.map(|mut created| {
+            created.client_order_id = order.client_order_id.clone();
+            created
+        });
+        self.journal.finish(order, &result);
+        (result, retry_after)
+    }
+
+    async fn submit_order(&self, order: &NewOrder) -> Result<TransportResponse, RichamsterError> {
+        let api = Api::Exchange(ExchangeApi::NewOrder);
+        let RequestData(url, method) = self.client.request_data(&api);
+        Ok(send_request!(
+            self.client,
+            api,
+            url,
+            method,
+            self.auth_state,
+            serde_json::to_string(order)?
+        ))
+    }
 
+    fn created_order(resp: &TransportResponse) -> Result<NewOrder, RichamsterError> {
         match resp.status() {
             StatusCode::CREATED => {
-                let response_string = resp.text().await?;
-                let response: NewOrder = serde_json::from_str(&response_string)?;
+                let response: NewOrder = resp.json()?;
                 Ok(response)
             }
             StatusCode::UNAUTHORIZED => Err(RichamsterError::UnauthorizedAccess),
             StatusCode::BAD_REQUEST => {
-                let response_string = resp.text().await?;
-                let response: NewOrderError = serde_json::from_str(&response_string)?;
+                let response: NewOrderError = resp.json()?;
                 Err(RichamsterError::NewOrderError(response))
             }
             status => {
-                let response_string = resp.text().await?;
+                let response_string = resp.text().to_owned();
                 Err(RichamsterError::UnsupportedResponseCode(
                     status,
                     response_string,
@@ -178,47 +337,70 @@ impl Exchange {
         }
     }
 
+    /// Same as [`Exchange::create_order`], but transient failures are retried
+    /// according to the client [`RetryPolicy`](crate::api::retry::RetryPolicy).
+    /// Before each resubmission `dedup` is asked whether the order already exists,
+    /// [`UserOrdersDedup`](crate::richamster::journal::UserOrdersDedup) checks
+    /// the open orders of the account.
+    pub async fn create_order_with_dedup<D: OrderDedup>(
+        &self,
+        order: NewOrder,
+        dedup: &D,
+    ) -> Result<NewOrder, RichamsterError> {
+        let policy = self.client.retry_policy();
+        let mut submitted_at = Local::now();
+        let mut attempt = 1;
+        loop {
+            match self.place_order(&order).await {
+                (Err(e), retry_after) if e.is_transient() && attempt < policy.attempts() => {
+                    tokio::time::sleep(policy.delay(attempt, retry_after)).await;
+                    if let Some(first) = self.journal.submitted_at(&order) {
+                        submitted_at = first;
+                    }
+                    if let Some(existing) = dedup.find_existing(self, &order, submitted_at).await? {
+                        if let Some(pk) = existing.pk {
+                            self.journal
+                                .set_state(&order, SubmissionState::Accepted(pk));
+                        }
+                        return Ok(existing);
+                    }
+                    attempt += 1;
+                }
+                (result, _) => return result,
+            }
+        }
+    }
+
     pub async fn calculate_market_order(
         &self,
         pair: CurrencyPair,
         amount: f64,
         order_type: OrderType,
     ) -> Result<MarketOrderCalculator, RichamsterError> {
-        let market_list = self.markets_list().await?;
-        let market = if let Some(m) = market_list
-            .iter()
-            .find(|m| m.abbreviation == pair.to_string())
-        {
-            m
-        } else {
-            return Err(RichamsterError::IllegalCurrencyPair(pair));
-        };
+        let market_id = self.markets.market_id(self, pair).await?;
 
-        let RequestData(mut url, method) =
-            Api::Exchange(ExchangeApi::CalculateMarketOrder).request_data();
+        let api = Api::Exchange(ExchangeApi::CalculateMarketOrder);
+        let RequestData(mut url, method) = self.client.request_data(&api);
         let market_order = MarketOrderInfo {
             amount: amount.to_string(),
-            currency_pair: market.id,
+            currency_pair: market_id,
             order_type,
             total: None,
         };
         market_order.compose_url(&mut url);
-        let resp = send_request!(url, method, self.auth_state);
+        let resp = send_request!(self.client, api, url, method, self.auth_state);
         match resp.status() {
             StatusCode::CREATED | StatusCode::OK => {
-                let response_string = resp.text().await?;
-                println!("response: {:?}", response_string);
-                let response: MarketOrderCalculator = serde_json::from_str(&response_string)?;
+                let response: MarketOrderCalculator = resp.json()?;
                 Ok(response)
             }
             StatusCode::UNAUTHORIZED => Err(RichamsterError::UnauthorizedAccess),
             StatusCode::BAD_REQUEST => {
-                let response_string = resp.text().await?;
-                let response: NewOrderError = serde_json::from_str(&response_string)?;
+                let response: NewOrderError = resp.json()?;
                 Err(RichamsterError::NewOrderError(response))
             }
             status => {
-                let response_string = resp.text().await?;
+                let response_string = resp.text().to_owned();
                 Err(RichamsterError::UnsupportedResponseCode(
                     status,
                     response_string,
@@ -227,6 +409,8 @@ impl Exchange {
         }
     }
 
+    /// A timeout fails with [`RichamsterError::OutcomeUnknown`], the order may
+    /// have been executed.
     pub async fn execute_market_order(
         &self,
         pair: CurrencyPair,
@@ -234,51 +418,37 @@ impl Exchange {
         order_type: OrderType,
         total: Option<f64>,
     ) -> Result<MarketOrderResponse, RichamsterError> {
-        let market_list = self.markets_list().await?;
-        let market = if let Some(m) = market_list
-            .iter()
-            .find(|m| m.abbreviation == pair.to_string())
-        {
-            m
-        } else {
-            return Err(RichamsterError::IllegalCurrencyPair(pair));
-        };
+        let market_id = self.markets.market_id(self, pair).await?;
 
         let total = total.map(|t| t.to_string());
-        let RequestData(url, method) =
-            Api::Exchange(ExchangeApi::ExecuteMarketOrder).request_data();
+        let api = Api::Exchange(ExchangeApi::ExecuteMarketOrder);
+        let RequestData(url, method) = self.client.request_data(&api);
         let market_order = MarketOrderInfo {
             amount: amount.to_string(),
-            currency_pair: market.id,
+            currency_pair: market_id,
             order_type,
             total,
         };
-        println!(
-            "Serialize order: {:?}",
-            serde_json::to_string(&market_order)
-        );
         let resp = send_request!(
+            self.client,
+            api,
             url,
             method,
             self.auth_state,
             serde_json::to_string(&market_order)?
         );
-        println!("status: {:?}", resp.status());
         match resp.status() {
             StatusCode::CREATED | StatusCode::OK => {
-                let response_string = resp.text().await?;
-                println!("response: {:?}", response_string);
-                let response: MarketOrderResponse = serde_json::from_str(&response_string)?;
+                let response: MarketOrderResponse = resp.json()?;
                 Ok(response)
             }
             StatusCode::UNAUTHORIZED => Err(RichamsterError::UnauthorizedAccess),
             StatusCode::BAD_REQUEST => {
-                let response_string = resp.text().await?;
-                let response: NewOrderError = serde_json::from_str(&response_string)?;
+                let response: NewOrderError = resp.json()?;
                 Err(RichamsterError::NewOrderError(response))
             }
             status => {
-                let response_string = resp.text().await?;
+                let response_string = resp.text().to_owned();
                 Err(RichamsterError::UnsupportedResponseCode(
                     status,
                     response_string,
@@ -289,15 +459,403 @@ impl Exchange {
 }
 
 #[cfg(test)]
-mod test {
+pub(crate) mod test {
     use super::*;
+    use crate::api::client::ClientConfig;
+    use crate::api::permissions::Capability;
+    use crate::api::retry::RetryPolicy;
+    use crate::api::signing::{SigningScheme, canonical_request};
+    use crate::api::token::Token;
+    use crate::api::transport::{InMemoryTransport, Transport, TransportRequest};
+    use async_trait::async_trait;
+    use hmac_sha256::HMAC;
+    use reqwest::Method;
+    use reqwest::header::{HeaderMap, HeaderValue, RETRY_AFTER};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    const CREATED_ORDER: &str = r#"{"pk":10,"order_type":"buying","amount":"1","unit_price":"100","currency_pair":"BTC/UAH","commission":null,"closed_at":null,"type":"buying","created_at":null,"is_partial":false}"#;
+
+    pub(crate) fn keys_exchange(transport: &Arc<InMemoryTransport>) -> Exchange {
+        signed_exchange(transport, SigningScheme::Legacy)
+    }
+
+    fn v2_exchange(transport: &Arc<InMemoryTransport>) -> Exchange {
+        signed_exchange(transport, SigningScheme::V2)
+    }
+
+    fn signed_exchange(transport: &Arc<InMemoryTransport>, scheme: SigningScheme) -> Exchange {
+        let client = ClientConfig::new()
+            .transport(transport.clone())
+            .retry_policy(RetryPolicy::new().initial_backoff(Duration::ZERO))
+            .signing_scheme(scheme)
+            .build()
+            .unwrap();
+        Exchange::with_keys("api".to_owned(), "secret".to_owned()).client(client)
+    }
+
+    fn new_order() -> NewOrder {
+        NewOrder::new(
+            "1".to_owned(),
+            "100".to_owned(),
+            CurrencyPair::new(Token::BTC, Token::UAH),
+            OrderType::Buying,
+        )
+    }
+
+    struct CountingDedup {
+        calls: AtomicUsize,
+        existing: Option<NewOrder>,
+    }
+
+    impl OrderDedup for CountingDedup {
+        async fn find_existing(
+            &self,
+            _exchange: &Exchange,
+            _order: &NewOrder,
+            _submitted_at: DateTime<Local>,
+        ) -> Result<Option<NewOrder>, RichamsterError> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            Ok(self.existing.clone())
+        }
+    }
+
+    const MARKETS: &str = r#"[{"id":7,"is_favourite":false,"abbreviation":"BTC/UAH","volume":1.5,"price_deviation":0,"last_price":"1650000.00"}]"#;
+    const RESTRICTIONS: &str =
+        r#"[{"id":1,"currency_pair":"BTC/UAH","min_quantity":"0.0001","price_scale":2}]"#;
+    const CURRENCIES: &str = r#"{"success":true,"data":[{"id":2,"abbreviation":"BTC","title":"Bitcoin","icon":"https://richamster.com/btc.svg","precision":8}]}"#;
+    const CALCULATED: &str = r#"{"average_price":1650000.0,"covered":0.5,"total_sum":825000.0}"#;
+
+    #[tokio::test]
+    async fn market_ids_are_resolved_from_cached_registry() {
+        let transport = Arc::new(InMemoryTransport::new());
+        transport
+            .respond(
+                Method::GET,
+                "exchange/markets/",
+                TransportResponse::new(StatusCode::OK, MARKETS),
+            )
+            .respond(
+                Method::GET,
+                "exchange/restrictions/",
+                TransportResponse::new(StatusCode::OK, RESTRICTIONS),
+            )
+            .respond(
+                Method::GET,
+                "exchange/currencies/",
+                TransportResponse::new(StatusCode::OK, CURRENCIES),
+            )
+            .respond(
+                Method::GET,
+                "exchange/user/orders/market/",
+                TransportResponse::new(StatusCode::OK, CALCULATED),
+            );
+        let exchange = keys_exchange(&transport);
+        let pair = CurrencyPair::new(Token::BTC, Token::UAH);
+        for _ in 0..2 {
+            exchange
+                .calculate_market_order(pair, 0.5, OrderType::Buying)
+                .await
+                .unwrap();
+        }
+        let requests = transport.requests();
+        let markets_requests = requests
+            .iter()
+            .filter(|r| r.url.path().ends_with("exchange/markets/"))
+            .count();
+        assert_eq!(markets_requests, 1);
+        assert!(
+            requests
+                .last()
+                .unwrap()
+                .url
+                .as_str()
+                .contains("currency_pair=7")
+        );
+
+        let snapshot = exchange.markets().snapshot(&exchange).await.unwrap();
+        assert_eq!(snapshot.pair(7), Some(pair));
+        assert_eq!(snapshot.restriction(pair).unwrap().min_quantity, "0.0001");
+        assert_eq!(snapshot.currency(Token::BTC).unwrap().precision, 8);
+        assert!(matches!(
+            exchange
+                .calculate_market_order(
+                    CurrencyPair::new(Token::ETH, Token::UAH),
+                    1.0,
+                    OrderType::Buying
+                )
+                .await,
+            Err(RichamsterError::IllegalCurrencyPair(_))
+        ));
+    }
+
+    /// Lets other tasks run before every response.
+    #[derive(Debug)]
+    struct YieldingTransport(Arc<InMemoryTransport>);
+
+    #[async_trait]
+    impl Transport for YieldingTransport {
+        async fn send(
+            &self,
+            request: TransportRequest,
+        ) -> Result<TransportResponse, RichamsterError> {
+            tokio::task::yield_now().await;
+            self.0.send(request).await
+        }
+    }
+
+    #[tokio::test]
+    async fn concurrent_lookups_load_markets_once() {
+        let transport = Arc::new(InMemoryTransport::new());
+        transport
+            .respond(
+                Method::GET,
+                "exchange/markets/",
+                TransportResponse::new(StatusCode::OK, MARKETS),
+            )
+            .respond(
+                Method::GET,
+                "exchange/restrictions/",
+                TransportResponse::new(StatusCode::OK, RESTRICTIONS),
+            )
+            .respond(
+                Method::GET,
+                "exchange/currencies/",
+                TransportResponse::new(StatusCode::OK, CURRENCIES),
+            );
+        let client = ClientConfig::new()
+            .transport(Arc::new(YieldingTransport(transport.clone())))
+            .build()
+            .unwrap();
+        let exchange = Exchange::new().client(client);
+        let pair = CurrencyPair::new(Token::BTC, Token::UAH);
+        let markets = exchange.markets();
+        let lookups = tokio::join!(
+            markets.market_id(&exchange, pair),
+            markets.market_id(&exchange, pair),
+            markets.market_id(&exchange, pair),
+        );
+        for market_id in [lookups.0, lookups.1, lookups.2] {
+            assert_eq!(market_id.unwrap(), 7);
+        }
+        assert_eq!(transport.requests().len(), 3);
+    }
 
     #[test]
     fn create_default_exchange() {
         let exchange: Exchange = Default::default();
-        match exchange.auth_state {
-            AuthState::Unauthorized => assert!(true),
-            _ => assert!(false),
-        }
+        assert!(matches!(
+            *exchange.auth_state.current(),
+            AuthState::Unauthorized
+        ));
+    }
+
+    #[tokio::test]
+    async fn create_order_signs_json_body() {
+        let transport = Arc::new(InMemoryTransport::new());
+        transport.respond(
+            Method::POST,
+            "exchange/user/orders/",
+            TransportResponse::new(StatusCode::CREATED, CREATED_ORDER),
+        );
+        let exchange = keys_exchange(&transport);
+        let created = exchange.create_order(new_order()).await.unwrap();
+        assert_eq!(created.pk, Some(10));
+        let request = transport.last_request().unwrap();
+        let body = request.body.clone().unwrap();
+        assert_eq!(body, serde_json::to_string(&new_order()).unwrap());
+        assert_eq!(
+            request.header_value("Content-Type"),
+            Some("application/json")
+        );
+        assert_eq!(request.header_value("Api-Key"), Some("api"));
+        assert_eq!(request.header_value("Nonce"), None);
+        assert_eq!(
+            request.header_value("Signature"),
+            Some(hex::encode(HMAC::mac(body, "secret")).as_str())
+        );
+    }
+
+    #[tokio::test]
+    async fn v2_scheme_signs_canonical_request() {
+        let transport = Arc::new(InMemoryTransport::new());
+        transport.respond(
+            Method::POST,
+            "exchange/user/orders/",
+            TransportResponse::new(StatusCode::CREATED, CREATED_ORDER),
+        );
+        let exchange = v2_exchange(&transport);
+        exchange.create_order(new_order()).await.unwrap();
+        let request = transport.last_request().unwrap();
+        assert_eq!(SigningScheme::of(&request), SigningScheme::V2);
+        let canonical = canonical_request(SigningScheme::V2, &request);
+        assert!(
+            canonical
+                .starts_with("RICHAMSTER-HMAC-SHA256-V2\nPOST\n/public/v1/exchange/user/orders/\n")
+        );
+        assert_eq!(
+            request.header_value("Signature"),
+            Some(hex::encode(HMAC::mac(canonical, "secret")).as_str())
+        );
+    }
+
+    #[tokio::test]
+    async fn retried_request_is_signed_again() {
+        let transport = Arc::new(InMemoryTransport::new());
+        transport
+            .respond(
+                Method::GET,
+                "exchange/user/orders/",
+                TransportResponse::new(StatusCode::BAD_GATEWAY, ""),
+            )
+            .respond(
+                Method::GET,
+                "exchange/user/orders/",
+                TransportResponse::new(
+                    StatusCode::OK,
+                    r#"{"next":null,"previous":null,"results":[]}"#,
+                ),
+            );
+        let exchange = v2_exchange(&transport);
+        exchange
+            .user_orders(OrdersFilter::new(None, None, None))
+            .await
+            .unwrap();
+        let requests = transport.requests();
+        assert_eq!(requests.len(), 2);
+        assert_ne!(
+            requests[0].header_value("Nonce"),
+            requests[1].header_value("Nonce")
+        );
+        assert_ne!(
+            requests[0].header_value("Signature"),
+            requests[1].header_value("Signature")
+        );
+    }
+
+    #[tokio::test]
+    async fn create_order_is_not_retried_without_dedup() {
+        let transport = Arc::new(InMemoryTransport::new());
+        transport.respond(
+            Method::POST,
+            "exchange/user/orders/",
+            TransportResponse::new(StatusCode::BAD_GATEWAY, ""),
+        );
+        let exchange = keys_exchange(&transport);
+        assert!(exchange.create_order(new_order()).await.is_err());
+        assert_eq!(transport.requests().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn create_order_with_dedup_resubmits_missing_order() {
+        let transport = Arc::new(InMemoryTransport::new());
+        transport
+            .respond(
+                Method::POST,
+                "exchange/user/orders/",
+                TransportResponse::new(StatusCode::GATEWAY_TIMEOUT, ""),
+            )
+            .respond(
+                Method::POST,
+                "exchange/user/orders/",
+                TransportResponse::new(StatusCode::CREATED, CREATED_ORDER),
+            );
+        let exchange = keys_exchange(&transport);
+        let dedup = CountingDedup {
+            calls: AtomicUsize::new(0),
+            existing: None,
+        };
+        let created = exchange
+            .create_order_with_dedup(new_order(), &dedup)
+            .await
+            .unwrap();
+        assert_eq!(created.pk, Some(10));
+        assert_eq!(dedup.calls.load(Ordering::SeqCst), 1);
+        assert_eq!(transport.requests().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn create_order_with_dedup_returns_existing_order() {
+        let transport = Arc::new(InMemoryTransport::new());
+        transport.respond(
+            Method::POST,
+            "exchange/user/orders/",
+            TransportResponse::new(StatusCode::GATEWAY_TIMEOUT, ""),
+        );
+        let exchange = keys_exchange(&transport);
+        let existing: NewOrder = serde_json::from_str(CREATED_ORDER).unwrap();
+        let dedup = CountingDedup {
+            calls: AtomicUsize::new(0),
+            existing: Some(existing.clone()),
+        };
+        let created = exchange
+            .create_order_with_dedup(new_order(), &dedup)
+            .await
+            .unwrap();
+        assert_eq!(created, existing);
+        assert_eq!(transport.requests().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn create_order_with_dedup_waits_for_retry_after() {
+        let transport = Arc::new(InMemoryTransport::new());
+        let mut headers = HeaderMap::new();
+        headers.insert(RETRY_AFTER, HeaderValue::from_static("0"));
+        transport
+            .respond(
+                Method::POST,
+                "exchange/user/orders/",
+                TransportResponse::new(StatusCode::TOO_MANY_REQUESTS, "").with_headers(headers),
+            )
+            .respond(
+                Method::POST,
+                "exchange/user/orders/",
+                TransportResponse::new(StatusCode::CREATED, CREATED_ORDER),
+            );
+        let client = ClientConfig::new()
+            .transport(transport.clone())
+            .retry_policy(RetryPolicy::new().initial_backoff(Duration::from_secs(60)))
+            .build()
+            .unwrap();
+        let exchange = Exchange::with_keys("api".to_owned(), "secret".to_owned()).client(client);
+        let dedup = CountingDedup {
+            calls: AtomicUsize::new(0),
+            existing: None,
+        };
+        let created = tokio::time::timeout(
+            Duration::from_secs(5),
+            exchange.create_order_with_dedup(new_order(), &dedup),
+        )
+        .await
+        .expect("Retry-After was not honoured")
+        .unwrap();
+        assert_eq!(created.pk, Some(10));
+        assert_eq!(transport.requests().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn read_only_keys_cannot_trade_whatever_the_client() {
+        let transport = Arc::new(InMemoryTransport::new());
+        let exchange = keys_exchange(&transport).restrict(Permissions::read_only());
+        let denied = |result: Result<NewOrder, RichamsterError>| {
+            matches!(
+                result,
+                Err(RichamsterError::PermissionDenied(
+                    Api::Exchange(ExchangeApi::NewOrder),
+                    Capability::Trading
+                ))
+            )
+        };
+        assert!(denied(exchange.create_order(new_order()).await));
+
+        let other_client = exchange.clone().client(RichamsterClient::new());
+        assert!(denied(other_client.create_order(new_order()).await));
+        let widened = exchange.clone().restrict(Permissions::all());
+        assert!(denied(widened.create_order(new_order()).await));
+        exchange.auth_state().replace(AuthState::ApiSecretKeyAuth(
+            ApiKey::new("rotated".to_owned()),
+            SecretKey::new("secret".to_owned()),
+        ));
+        assert!(denied(exchange.create_order(new_order()).await));
+        assert!(transport.requests().is_empty());
     }
 }
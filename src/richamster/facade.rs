@@ -0,0 +1,322 @@
+use crate::api::client::RichamsterClient;
+use crate::api::permissions::Permissions;
+use crate::errors::RichamsterError;
+use crate::richamster::auth::Auth;
+use crate::richamster::common::{ApiKey, AuthState, JwtToken, SecretKey, SharedAuthState};
+use crate::richamster::credentials::CredentialProvider;
+use crate::richamster::exchange::Exchange;
+use crate::richamster::feedback::Feedback;
+use crate::richamster::journal::OrderJournal;
+use crate::richamster::login::LoginFlow;
+use crate::richamster::markets::MarketRegistry;
+use crate::richamster::replenish::Replenish;
+use crate::richamster::session::Session;
+use crate::richamster::signer::Signer;
+use crate::richamster::user::User;
+use crate::richamster::withdraw::Withdraw;
+use std::sync::Arc;
+
+/// Entry point built once with a client and credentials. The service views it
+/// hands out share one auth state, so a [`Session`] refreshed by one of them is
+/// used by all, as well as one market registry and order journal.
+///
+/// ```no_run
+/// # use richamster_api::richamster::Richamster;
+/// # async fn run() -> Result<(), richamster_api::errors::RichamsterError> {
+/// let richamster = Richamster::with_keys("api key".to_owned(), "secret key".to_owned());
+/// let balances = richamster.user().balances(None).await?;
+/// let markets = richamster.exchange().markets_list().await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct Richamster {
+    client: RichamsterClient,
+    auth_state: SharedAuthState,
+    markets: MarketRegistry,
+    journal: OrderJournal,
+}
+
+impl Richamster {
+    /// Handle without credentials, for public endpoints only.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_auth_state(auth_state: AuthState) -> Self {
+        Self {
+            auth_state: SharedAuthState::new(auth_state),
+            ..Self::default()
+        }
+    }
+
+    pub fn with_jwt_token(token: String) -> Self {
+        Self::with_auth_state(AuthState::JwtTokenAuth(JwtToken::new(token)))
+    }
+
+    pub fn with_keys(api_key: String, secret_key: String) -> Self {
+        Self::with_auth_state(AuthState::ApiSecretKeyAuth(
+            ApiKey::new(api_key),
+            SecretKey::new(secret_key),
+        ))
+    }
+
+    /// The JWT is sent as is and never refreshed, see
+    /// [`Richamster::with_session`] for a token that is.
+    pub fn with_jwt_and_keys(jwt: String, api_key: String, secret_key: String) -> Self {
+        Self::with_auth_state(AuthState::JwtTokenWithApiSecretKeyAuth(
+            JwtToken::new(jwt),
+            ApiKey::new(api_key),
+            SecretKey::new(secret_key),
+        ))
+    }
+
+    /// Authenticates with the JWT of `session`, refreshed as needed.
+    pub fn with_session(session: Session) -> Self {
+        Self::with_auth_state(AuthState::Session(session))
+    }
+
+    /// Signs requests with `signer` instead of a secret key held in memory.
+    pub fn with_signer(api_key: String, signer: Arc<dyn Signer>) -> Self {
+        Self::with_auth_state(AuthState::ApiKeySignerAuth(ApiKey::new(api_key), signer))
+    }
+
+    /// Authenticates with the credentials loaded from `provider`.
+    pub fn with_credentials(provider: &impl CredentialProvider) -> Result<Self, RichamsterError> {
+        Ok(Self::with_auth_state(provider.auth_state()?))
+    }
+
+    pub fn client(mut self, client: RichamsterClient) -> Self {
+        self.client = client;
+        self
+    }
+
+    pub fn market_registry(mut self, markets: MarketRegistry) -> Self {
+        self.markets = markets;
+        self
+    }
+
+    pub fn order_journal(mut self, journal: OrderJournal) -> Self {
+        self.journal = journal;
+        self
+    }
+
+    /// Credentials of every view, see [`SharedAuthState::replace`].
+    pub fn auth_state(&self) -> &SharedAuthState {
+        &self.auth_state
+    }
+
+    /// Limits every view handed out afterwards to `permissions`, see
+    /// [`Permissions`].
+    pub fn restrict(mut self, permissions: Permissions) -> Self {
+        self.auth_state = self.auth_state.restrict(permissions);
+        self
+    }
+
+    pub fn exchange(&self) -> Exchange {
+        Exchange::shared(self.client.clone(), self.auth_state.clone())
+            .market_registry(self.markets.clone())
+            .order_journal(self.journal.clone())
+    }
+
+    pub fn user(&self) -> User {
+        User::shared(self.client.clone(), self.auth_state.clone())
+    }
+
+    pub fn wallet(&self) -> Wallet {
+        Wallet {
+            withdraw: Withdraw::shared(self.client.clone(), self.auth_state.clone()),
+            replenish: Replenish::shared(self.client.clone(), self.auth_state.clone()),
+        }
+    }
+
+    pub fn auth(&self) -> Auth {
+        Auth::new().client(self.client.clone())
+    }
+
+    pub fn login(&self) -> LoginFlow {
+        LoginFlow::new().client(self.client.clone())
+    }
+
+    pub fn feedback(&self) -> Feedback {
+        Feedback::new().client(self.client.clone())
+    }
+}
+
+/// Deposits and withdrawals, see [`Richamster::wallet`].
+#[derive(Clone, Debug)]
+pub struct Wallet {
+    pub(crate) withdraw: Withdraw,
+    pub(crate) replenish: Replenish,
+}
+
+impl Wallet {
+    pub fn withdraw(&self) -> &Withdraw {
+        &self.withdraw
+    }
+
+    pub fn replenish(&self) -> &Replenish {
+        &self.replenish
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::api::client::ClientConfig;
+    use crate::api::permissions::Capability;
+    use crate::api::token::Token;
+    use crate::api::transport::{InMemoryTransport, TransportResponse};
+    use crate::models::auth::TokenData;
+    use crate::models::exchange::OrdersFilter;
+    use crate::models::withdraw::WithdrawData;
+    use reqwest::{Method, StatusCode};
+
+    #[tokio::test]
+    async fn views_share_refreshed_session() {
+        let transport = Arc::new(InMemoryTransport::new());
+        transport
+            .respond(
+                Method::GET,
+                "user/balances/",
+                TransportResponse::new(StatusCode::UNAUTHORIZED, ""),
+            )
+            .respond(
+                Method::POST,
+                "token/refresh/",
+                TransportResponse::new(StatusCode::OK, r#"{"access":"access-2","refresh":""}"#),
+            )
+            .respond(
+                Method::GET,
+                "user/balances/",
+                TransportResponse::new(StatusCode::OK, "[]"),
+            )
+            .respond(
+                Method::GET,
+                "exchange/user/orders/",
+                TransportResponse::new(
+                    StatusCode::OK,
+                    r#"{"next":null,"previous":null,"results":[]}"#,
+                ),
+            );
+        let client = ClientConfig::new()
+            .transport(transport.clone())
+            .build()
+            .unwrap();
+        let richamster = Richamster::with_session(Session::new(TokenData {
+            access: "access-1".to_owned(),
+            refresh: "refresh-1".to_owned(),
+        }))
+        .client(client);
+
+        richamster.user().balances(None).await.unwrap();
+        richamster
+            .exchange()
+            .user_orders(OrdersFilter::new(None, None, None))
+            .await
+            .unwrap();
+
+        let request = transport.last_request().unwrap();
+        assert_eq!(request.header_value("Authorization"), Some("JWT access-2"));
+        assert_eq!(transport.requests().len(), 4);
+    }
+
+    #[test]
+    fn handles_are_send_sync_and_clone() {
+        fn assert_handle<T: Clone + Send + Sync + 'static>() {}
+        assert_handle::<Richamster>();
+        assert_handle::<Exchange>();
+        assert_handle::<User>();
+        assert_handle::<Withdraw>();
+        assert_handle::<Replenish>();
+        assert_handle::<SharedAuthState>();
+    }
+
+    #[tokio::test]
+    async fn replaced_credentials_reach_every_clone() {
+        let transport = Arc::new(InMemoryTransport::new());
+        for _ in 0..2 {
+            transport.respond(
+                Method::GET,
+                "user/balances/",
+                TransportResponse::new(StatusCode::OK, "[]"),
+            );
+        }
+        let client = ClientConfig::new()
+            .transport(transport.clone())
+            .build()
+            .unwrap();
+        let user = User::with_jwt_token("old".to_owned()).client(client);
+        let task = {
+            let user = user.clone();
+            tokio::spawn(async move { user.balances(None).await })
+        };
+        task.await.unwrap().unwrap();
+        assert_eq!(
+            transport
+                .last_request()
+                .unwrap()
+                .header_value("Authorization"),
+            Some("JWT old")
+        );
+
+        let previous = user
+            .auth_state()
+            .replace(AuthState::JwtTokenAuth(JwtToken::new("new".to_owned())));
+        assert!(matches!(*previous, AuthState::JwtTokenAuth(_)));
+        user.clone().balances(None).await.unwrap();
+        assert_eq!(
+            transport
+                .last_request()
+                .unwrap()
+                .header_value("Authorization"),
+            Some("JWT new")
+        );
+    }
+
+    #[tokio::test]
+    async fn restricted_views_share_credentials_not_permissions() {
+        let transport = Arc::new(InMemoryTransport::new());
+        transport.respond(
+            Method::GET,
+            "user/balances/",
+            TransportResponse::new(StatusCode::OK, "[]"),
+        );
+        let client = ClientConfig::new()
+            .transport(transport.clone())
+            .build()
+            .unwrap();
+        let richamster =
+            Richamster::with_keys("api".to_owned(), "secret".to_owned()).client(client);
+        let reporting = richamster.clone().restrict(Permissions::read_only());
+
+        reporting.user().balances(None).await.unwrap();
+        let withdrawal = WithdrawData {
+            address: "bc1q".to_owned(),
+            sum: "0.01".to_owned(),
+            pin_code: "0000".to_owned(),
+            finserver_channel_name: "BTC".to_owned(),
+        };
+        assert!(matches!(
+            reporting
+                .wallet()
+                .withdraw()
+                .withdraw(Token::BTC, withdrawal)
+                .await,
+            Err(RichamsterError::PermissionDenied(_, Capability::Wallet))
+        ));
+        assert_eq!(
+            richamster.wallet().withdraw().auth_state().permissions(),
+            Permissions::all()
+        );
+
+        richamster
+            .auth_state()
+            .replace(AuthState::JwtTokenAuth(JwtToken::new("rotated".to_owned())));
+        assert!(matches!(
+            *reporting.auth_state().current(),
+            AuthState::JwtTokenAuth(_)
+        ));
+        assert_eq!(transport.requests().len(), 1);
+    }
+}
@@ -1,26 +1,44 @@
-use crate::api::CLIENT;
 use crate::api::FeedbackApi;
-use crate::api::RequestPath;
+use crate::api::client::RichamsterClient;
+use crate::api::transport::TransportRequest;
 use crate::api::{Api, RequestData};
 use crate::errors::RichamsterError;
 use crate::models::feedback::Messenger;
 use reqwest::StatusCode;
 
-pub struct Feedback;
+#[derive(Clone, Debug, Default)]
+pub struct Feedback {
+    client: RichamsterClient,
+}
+
+impl Feedback {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn client(mut self, client: RichamsterClient) -> Self {
+        self.client = client;
+        self
+    }
+}
 
 impl Feedback {
-    pub async fn messengers_list() -> Result<Vec<Messenger>, RichamsterError> {
-        let RequestData(url, method) = Api::Feedback(FeedbackApi::Messengers).request_data();
-        let resp = CLIENT.request(method, url).send().await?;
+    pub async fn messengers_list(&self) -> Result<Vec<Messenger>, RichamsterError> {
+        let api = Api::Feedback(FeedbackApi::Messengers);
+        let RequestData(url, method) = self.client.request_data(&api);
+        let resp = self
+            .client
+            .execute(api, TransportRequest::new(method, url))
+            .await?;
 
         match resp.status() {
             StatusCode::OK => {
-                let messengers: Vec<Messenger> = serde_json::from_str(&resp.text().await?)?;
+                let messengers: Vec<Messenger> = resp.json()?;
                 Ok(messengers)
             }
             status => Err(RichamsterError::UnsupportedResponseCode(
                 status,
-                resp.text().await?,
+                resp.text().to_owned(),
             )),
         }
     }
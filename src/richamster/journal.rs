@@ -0,0 +1,402 @@
+use crate::errors::RichamsterError;
+use crate::models::exchange::{NewOrder, OrderHistoryRecord, OrdersFilter};
+use crate::richamster::exchange::{Exchange, OrderDedup};
+use chrono::{DateTime, Local};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+const DEFAULT_SETTLED_CAPACITY: usize = 1000;
+const OPEN_ORDERS_PAGE_SIZE: i32 = 100;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum SubmissionState {
+    /// The request was sent and no response arrived yet.
+    InFlight,
+    /// The request failed in a way that does not tell whether the order exists.
+    Uncertain,
+    /// The server created the order with this id.
+    Accepted(i32),
+    /// The server refused the order.
+    Rejected,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Submission {
+    pub order: NewOrder,
+    /// Time of the first attempt.
+    pub submitted_at: DateTime<Local>,
+    pub state: SubmissionState,
+}
+
+impl Submission {
+    /// Accepted or rejected, the outcome is known.
+    pub fn is_settled(&self) -> bool {
+        matches!(
+            self.state,
+            SubmissionState::Accepted(_) | SubmissionState::Rejected
+        )
+    }
+}
+
+/// Orders submitted through [`Exchange::create_order`], keyed by
+/// [`NewOrder::client_order_id`]. Clones share the same journal.
+///
+/// Settled submissions are kept so that [`UserOrdersDedup`] does not attribute
+/// one order to two submissions, up to a capacity past which the oldest ones
+/// are forgotten. Pending submissions are always kept.
+#[derive(Clone, Debug)]
+pub struct OrderJournal {
+    submissions: Arc<Mutex<HashMap<String, Submission>>>,
+    settled_capacity: usize,
+}
+
+impl Default for OrderJournal {
+    fn default() -> Self {
+        Self {
+            submissions: Arc::default(),
+            settled_capacity: DEFAULT_SETTLED_CAPACITY,
+        }
+    }
+}
+
+impl OrderJournal {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Settled submissions kept, 1000 by default.
+    pub fn settled_capacity(mut self, settled_capacity: usize) -> Self {
+        self.settled_capacity = settled_capacity;
+        self
+    }
+
+    pub fn get(&self, client_order_id: &str) -> Option<Submission> {
+        self.submissions
+            .lock()
+            .unwrap()
+            .get(client_order_id)
+            .cloned()
+    }
+
+    /// Submissions still in flight or with an uncertain outcome.
+    pub fn pending(&self) -> Vec<Submission> {
+        self.submissions
+            .lock()
+            .unwrap()
+            .values()
+            .filter(|s| {
+                matches!(
+                    s.state,
+                    SubmissionState::InFlight | SubmissionState::Uncertain
+                )
+            })
+            .cloned()
+            .collect()
+    }
+
+    /// Whether an accepted submission already accounts for the order `pk`.
+    pub fn is_claimed(&self, pk: i32) -> bool {
+        self.submissions
+            .lock()
+            .unwrap()
+            .values()
+            .any(|s| s.state == SubmissionState::Accepted(pk))
+    }
+
+    /// Forgets accepted and rejected submissions.
+    pub fn clear_settled(&self) {
+        self.submissions
+            .lock()
+            .unwrap()
+            .retain(|_, s| !s.is_settled());
+    }
+
+    pub(crate) fn begin(&self, order: &NewOrder) {
+        let Some(id) = &order.client_order_id else {
+            return;
+        };
+        self.submissions
+            .lock()
+            .unwrap()
+            .entry(id.clone())
+            .and_modify(|s| s.state = SubmissionState::InFlight)
+            .or_insert_with(|| Submission {
+                order: order.clone(),
+                submitted_at: Local::now(),
+                state: SubmissionState::InFlight,
+            });
+    }
+
+    pub(crate) fn finish(&self, order: &NewOrder, result: &Result<NewOrder, RichamsterError>) {
+        let state = match result {
+            Ok(created) => match created.pk {
+                Some(pk) => SubmissionState::Accepted(pk),
+                None => SubmissionState::Uncertain,
+            },
+            Err(e) if e.is_transient() => SubmissionState::Uncertain,
+            Err(_) => SubmissionState::Rejected,
+        };
+        self.set_state(order, state);
+    }
+
+    pub(crate) fn set_state(&self, order: &NewOrder, state: SubmissionState) {
+        let Some(id) = &order.client_order_id else {
+            return;
+        };
+        let mut submissions = self.submissions.lock().unwrap();
+        if let Some(submission) = submissions.get_mut(id) {
+            submission.state = state;
+        }
+        evict_settled(&mut submissions, self.settled_capacity);
+    }
+
+    pub(crate) fn submitted_at(&self, order: &NewOrder) -> Option<DateTime<Local>> {
+        let id = order.client_order_id.as_deref()?;
+        self.get(id).map(|s| s.submitted_at)
+    }
+}
+
+fn evict_settled(submissions: &mut HashMap<String, Submission>, capacity: usize) {
+    let mut settled: Vec<_> = submissions
+        .iter()
+        .filter(|(_, s)| s.is_settled())
+        .map(|(id, s)| (s.submitted_at, id.clone()))
+        .collect();
+    if settled.len() <= capacity {
+        return;
+    }
+    settled.sort();
+    for (_, id) in &settled[..settled.len() - capacity] {
+        submissions.remove(id);
+    }
+}
+
+/// Looks for the order among the open orders of the account: same pair, side,
+/// amount and price, created no earlier than the submission minus the allowed
+/// clock skew, and not claimed by another journal entry. An order that was
+/// completely filled in the meantime is no longer open and is not found.
+#[derive(Clone, Copy, Debug)]
+pub struct UserOrdersDedup {
+    clock_skew: Duration,
+}
+
+impl Default for UserOrdersDedup {
+    fn default() -> Self {
+        Self {
+            clock_skew: Duration::from_secs(5),
+        }
+    }
+}
+
+impl UserOrdersDedup {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Tolerated difference between the local and the server clock.
+    pub fn clock_skew(mut self, clock_skew: Duration) -> Self {
+        self.clock_skew = clock_skew;
+        self
+    }
+
+    fn matches(
+        &self,
+        order: &NewOrder,
+        record: &OrderHistoryRecord,
+        since: DateTime<Local>,
+    ) -> bool {
+        record.pair == order.currency_pair
+            && order.o_type == Some(record.side)
+            && same_number(&record.volume, &order.amount)
+            && same_number(&record.unit_price, &order.unit_price)
+            && record.created_at >= since
+    }
+}
+
+impl OrderDedup for UserOrdersDedup {
+    async fn find_existing(
+        &self,
+        exchange: &Exchange,
+        order: &NewOrder,
+        submitted_at: DateTime<Local>,
+    ) -> Result<Option<NewOrder>, RichamsterError> {
+        let since = submitted_at - chrono::Duration::from_std(self.clock_skew).unwrap_or_default();
+        let journal = exchange.journal();
+        let mut page = 1;
+        loop {
+            let pair = order.currency_pair.parse().ok();
+            let filter = OrdersFilter::new(pair, None, Some(OPEN_ORDERS_PAGE_SIZE)).page(page);
+            let open = exchange.user_orders(filter).await?;
+            let found = open.results.iter().find(|record| {
+                self.matches(order, record, since) && !journal.is_claimed(record.pk)
+            });
+            if let Some(record) = found {
+                return Ok(Some(existing_order(order, record)));
+            }
+            if open.next.is_none() || open.results.is_empty() {
+                return Ok(None);
+            }
+            page += 1;
+        }
+    }
+}
+
+fn same_number(a: &str, b: &str) -> bool {
+    match (a.parse::<f64>(), b.parse::<f64>()) {
+        (Ok(a), Ok(b)) => (a - b).abs() <= f64::EPSILON * a.abs().max(b.abs()),
+        _ => a == b,
+    }
+}
+
+fn existing_order(order: &NewOrder, record: &OrderHistoryRecord) -> NewOrder {
+    NewOrder {
+        pk: Some(record.pk),
+        order_type: Some(record.side),
+        amount: record.volume.clone(),
+        unit_price: record.unit_price.clone(),
+        currency_pair: record.pair.clone(),
+        commission: None,
+        closed_at: record.closed_at,
+        o_type: Some(record.side),
+        created_at: Some(record.created_at),
+        is_partial: None,
+        client_order_id: order.client_order_id.clone(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::api::token::{CurrencyPair, Token};
+    use crate::api::transport::{InMemoryTransport, TransportResponse};
+    use crate::models::common::OrderType;
+    use crate::richamster::exchange::test::keys_exchange;
+    use reqwest::{Method, StatusCode};
+
+    fn open_order(pk: i32, created_at: i64) -> String {
+        format!(
+            r#"{{"pk":{pk},"created_at":"{created_at}","closed_at":null,"side":"buying","volume":"1.00","unit_price":"100.0","sum":"100","pair":"BTC/UAH"}}"#
+        )
+    }
+
+    fn order() -> NewOrder {
+        NewOrder::new(
+            "1".to_owned(),
+            "100".to_owned(),
+            CurrencyPair::new(Token::BTC, Token::UAH),
+            OrderType::Buying,
+        )
+        .client_order_id("grid-1")
+    }
+
+    #[tokio::test]
+    async fn uncertain_submission_is_reconciled_with_open_orders() {
+        let now = Local::now().timestamp();
+        let open_orders = format!(
+            r#"{{"next":null,"previous":null,"results":[{},{}]}}"#,
+            open_order(5, now - 3600),
+            open_order(6, now)
+        );
+        let transport = Arc::new(InMemoryTransport::new());
+        transport
+            .respond(
+                Method::POST,
+                "exchange/user/orders/",
+                TransportResponse::new(StatusCode::GATEWAY_TIMEOUT, ""),
+            )
+            .respond(
+                Method::GET,
+                "exchange/user/orders/",
+                TransportResponse::new(StatusCode::OK, open_orders),
+            );
+        let exchange = keys_exchange(&transport);
+        let existing = exchange
+            .create_order_with_dedup(order(), &UserOrdersDedup::new())
+            .await
+            .unwrap();
+        assert_eq!(existing.pk, Some(6));
+        assert_eq!(existing.client_order_id.as_deref(), Some("grid-1"));
+        let posts = transport
+            .requests()
+            .iter()
+            .filter(|r| r.method == Method::POST)
+            .count();
+        assert_eq!(posts, 1);
+        let submission = exchange.journal().get("grid-1").unwrap();
+        assert_eq!(submission.state, SubmissionState::Accepted(6));
+        assert!(exchange.journal().pending().is_empty());
+    }
+
+    #[tokio::test]
+    async fn rejected_submission_is_settled() {
+        let transport = Arc::new(InMemoryTransport::new());
+        transport.respond(
+            Method::POST,
+            "exchange/user/orders/",
+            TransportResponse::new(
+                StatusCode::BAD_REQUEST,
+                r#"{"type":"validation_error","errors":[{"code":"invalid","detail":"Not enough funds","attr":"amount"}]}"#,
+            ),
+        );
+        let exchange = keys_exchange(&transport);
+        assert!(exchange.create_order(order()).await.is_err());
+        let submission = exchange.journal().get("grid-1").unwrap();
+        assert_eq!(submission.state, SubmissionState::Rejected);
+        exchange.journal().clear_settled();
+        assert!(exchange.journal().get("grid-1").is_none());
+    }
+
+    #[tokio::test]
+    async fn dedup_looks_past_the_first_page() {
+        let now = Local::now().timestamp();
+        let first = format!(
+            r#"{{"next":"https://richamster.com/public/v1/exchange/user/orders/?page=2","previous":null,"results":[{}]}}"#,
+            open_order(5, now - 3600)
+        );
+        let second = format!(
+            r#"{{"next":null,"previous":null,"results":[{}]}}"#,
+            open_order(7, now)
+        );
+        let transport = Arc::new(InMemoryTransport::new());
+        transport
+            .respond(
+                Method::GET,
+                "exchange/user/orders/",
+                TransportResponse::new(StatusCode::OK, first),
+            )
+            .respond(
+                Method::GET,
+                "exchange/user/orders/",
+                TransportResponse::new(StatusCode::OK, second),
+            );
+        let exchange = keys_exchange(&transport);
+        let existing = UserOrdersDedup::new()
+            .find_existing(&exchange, &order(), Local::now())
+            .await
+            .unwrap();
+        assert_eq!(existing.unwrap().pk, Some(7));
+        let requests = transport.requests();
+        assert_eq!(requests.len(), 2);
+        assert!(requests[1].url.query().unwrap().contains("page=2"));
+    }
+
+    #[test]
+    fn oldest_settled_submissions_are_evicted() {
+        let journal = OrderJournal::new().settled_capacity(2);
+        let orders: Vec<_> = (0..4)
+            .map(|i| order().client_order_id(format!("grid-{i}")))
+            .collect();
+        for (pk, order) in orders.iter().enumerate() {
+            journal.begin(order);
+            if pk > 0 {
+                journal.set_state(order, SubmissionState::Accepted(pk as i32));
+            }
+        }
+        assert!(journal.get("grid-0").is_some());
+        assert!(journal.get("grid-1").is_none());
+        assert!(journal.get("grid-2").is_some());
+        assert!(journal.get("grid-3").is_some());
+        assert_eq!(journal.pending().len(), 1);
+    }
+}
@@ -0,0 +1,280 @@
+use crate::api::client::RichamsterClient;
+use crate::api::transport::{TransportRequest, TransportResponse};
+use crate::api::{Api, AuthenticationApi, RequestData};
+use crate::errors::RichamsterError;
+use crate::models::auth::{Login, LoginResponse, OtpLogin, OtpLoginResponse};
+use crate::richamster::auth::{login_response, two_factor_response};
+use crate::richamster::session::Session;
+#[cfg(feature = "totp")]
+use crate::richamster::totp::Totp;
+use reqwest::header::SET_COOKIE;
+use secrecy::{ExposeSecret, SecretString};
+use serde::Serialize;
+use std::fmt::{Debug, Formatter};
+
+/// Outcome of the first login step.
+#[derive(Debug)]
+pub enum LoginStep {
+    Authenticated(Session),
+    TwoFactorRequired(Box<PendingTwoFactor>),
+}
+
+/// Login as a state machine: [`LoginFlow::start`] either authenticates right
+/// away or returns a [`PendingTwoFactor`] that remembers the cookies and the
+/// ephemeral token of the first step and sends them with the OTP.
+#[derive(Clone, Debug, Default)]
+pub struct LoginFlow {
+    client: RichamsterClient,
+}
+
+impl LoginFlow {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn client(mut self, client: RichamsterClient) -> Self {
+        self.client = client;
+        self
+    }
+
+    pub async fn start(
+        &self,
+        email: impl AsRef<str>,
+        password: impl AsRef<str>,
+    ) -> Result<LoginStep, RichamsterError> {
+        let api = Api::Authentication(AuthenticationApi::Login);
+        let login = Login::new(email.as_ref(), password.as_ref());
+        let resp = send(&self.client, api, login, None).await?;
+        match login_response(&resp)? {
+            LoginResponse::Jwt(tokens) => Ok(LoginStep::Authenticated(Session::new(tokens))),
+            LoginResponse::RequiresTwoFactor(_) => Ok(LoginStep::TwoFactorRequired(Box::new(
+                PendingTwoFactor::new(self.client.clone(), &resp),
+            ))),
+        }
+    }
+
+    /// Full login without a human: the OTP, when asked for, is generated by `totp`.
+    #[cfg(feature = "totp")]
+    pub async fn login_with_totp(
+        &self,
+        email: impl AsRef<str>,
+        password: impl AsRef<str>,
+        totp: &Totp,
+    ) -> Result<Session, RichamsterError> {
+        match self.start(email, password).await? {
+            LoginStep::Authenticated(session) => Ok(session),
+            LoginStep::TwoFactorRequired(pending) => pending.complete_with_totp(totp).await,
+        }
+    }
+}
+
+/// Second login step, tied to the first one. Consumed once the login
+/// completes, so a finished step cannot be replayed.
+pub struct PendingTwoFactor {
+    client: RichamsterClient,
+    cookies: Option<SecretString>,
+    ephemeral_token: Option<SecretString>,
+}
+
+impl Debug for PendingTwoFactor {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PendingTwoFactor")
+            .field("cookies", &self.cookies.is_some())
+            .field("ephemeral_token", &self.ephemeral_token.is_some())
+            .finish()
+    }
+}
+
+impl PendingTwoFactor {
+    fn new(client: RichamsterClient, resp: &TransportResponse) -> Self {
+        let cookies: Vec<&str> = resp
+            .headers()
+            .get_all(SET_COOKIE)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .filter_map(|cookie| cookie.split(';').next())
+            .map(str::trim)
+            .filter(|pair| !pair.is_empty())
+            .collect();
+        let ephemeral_token = resp
+            .json::<serde_json::Value>()
+            .ok()
+            .and_then(|body| body.get("ephemeral_token")?.as_str().map(str::to_owned));
+        Self {
+            client,
+            cookies: (!cookies.is_empty()).then(|| cookies.join("; ").into()),
+            ephemeral_token: ephemeral_token.map(Into::into),
+        }
+    }
+
+    pub async fn complete(self, otp_token: impl Into<String>) -> Result<Session, RichamsterError> {
+        self.submit(otp_token.into()).await
+    }
+
+    /// Completes the login with a generated code. A rejected code is followed by
+    /// the codes of the adjacent periods, in case the clocks disagree or the
+    /// period ended in between.
+    #[cfg(feature = "totp")]
+    pub async fn complete_with_totp(self, totp: &Totp) -> Result<Session, RichamsterError> {
+        let mut rejected = None;
+        for code in totp.candidates_at(std::time::SystemTime::now()) {
+            match self.submit(code).await {
+                Err(e @ RichamsterError::Otp(_)) => rejected = Some(e),
+                result => return result,
+            }
+        }
+        Err(rejected.expect("at least one candidate code"))
+    }
+
+    async fn submit(&self, otp_token: String) -> Result<Session, RichamsterError> {
+        let api = Api::Authentication(AuthenticationApi::TwoFactorLogin);
+        let otp = OtpLogin {
+            otp_token,
+            ephemeral_token: self
+                .ephemeral_token
+                .as_ref()
+                .map(|token| token.expose_secret().to_owned()),
+        };
+        let cookies = self.cookies.as_ref().map(|c| c.expose_secret());
+        let resp = send(&self.client, api, otp, cookies).await?;
+        match two_factor_response(&resp)? {
+            OtpLoginResponse::Jwt(tokens) => Ok(Session::new(tokens)),
+            OtpLoginResponse::Error(e) => Err(RichamsterError::Otp(Box::new(e))),
+        }
+    }
+}
+
+async fn send(
+    client: &RichamsterClient,
+    api: Api,
+    body: impl Serialize,
+    cookies: Option<&str>,
+) -> Result<TransportResponse, RichamsterError> {
+    let RequestData(url, method) = client.request_data(&api);
+    let mut request = TransportRequest::new(method, url).json_body(serde_json::to_string(&body)?);
+    if let Some(cookies) = cookies {
+        request = request.header("Cookie", cookies);
+    }
+    client.execute(api, request).await
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::api::client::ClientConfig;
+    use crate::api::transport::InMemoryTransport;
+    use reqwest::header::{HeaderMap, HeaderValue};
+    use reqwest::{Method, StatusCode};
+    use std::sync::Arc;
+
+    const TOKENS: &str = r#"{"access":"a.b.c","refresh":"d.e.f"}"#;
+
+    #[tokio::test]
+    async fn two_factor_step_carries_first_step_state() {
+        let mut headers = HeaderMap::new();
+        headers.append(
+            SET_COOKIE,
+            HeaderValue::from_static("sessionid=s1; HttpOnly; Path=/"),
+        );
+        headers.append(SET_COOKIE, HeaderValue::from_static("csrftoken=c1; Path=/"));
+        let transport = Arc::new(InMemoryTransport::new());
+        transport
+            .respond(
+                Method::POST,
+                "login/",
+                TransportResponse::new(StatusCode::OK, r#"{"ephemeral_token":"e1"}"#)
+                    .with_headers(headers),
+            )
+            .respond(
+                Method::POST,
+                "two-factor-login/",
+                TransportResponse::new(StatusCode::CREATED, TOKENS),
+            );
+        let client = ClientConfig::new()
+            .transport(transport.clone())
+            .build()
+            .unwrap();
+        let step = LoginFlow::new()
+            .client(client)
+            .start("a@b.c", "hunter2")
+            .await
+            .unwrap();
+        let LoginStep::TwoFactorRequired(pending) = step else {
+            panic!("two factor step expected");
+        };
+        let session = pending.complete("123456").await.unwrap();
+        assert_eq!(session.tokens().refresh, "d.e.f");
+
+        let request = transport.last_request().unwrap();
+        assert_eq!(
+            request.header_value("Cookie"),
+            Some("sessionid=s1; csrftoken=c1")
+        );
+        assert_eq!(
+            request.body.as_deref(),
+            Some(r#"{"otp_token":"123456","ephemeral_token":"e1"}"#)
+        );
+    }
+
+    #[tokio::test]
+    async fn login_without_two_factor_authenticates() {
+        let transport = Arc::new(InMemoryTransport::new());
+        transport.respond(
+            Method::POST,
+            "login/",
+            TransportResponse::new(StatusCode::CREATED, TOKENS),
+        );
+        let client = ClientConfig::new().transport(transport).build().unwrap();
+        let step = LoginFlow::new()
+            .client(client)
+            .start("a@b.c", "hunter2")
+            .await
+            .unwrap();
+        assert!(matches!(step, LoginStep::Authenticated(s) if s.tokens().access == "a.b.c"));
+    }
+
+    #[cfg(feature = "totp")]
+    #[tokio::test]
+    async fn rejected_totp_code_is_followed_by_adjacent_period() {
+        use secrecy::SecretBox;
+
+        let rejected = r#"{"type":"validation_error","errors":[{"code":"invalid","detail":"Invalid code","attr":"otp_token"}]}"#;
+        let transport = Arc::new(InMemoryTransport::new());
+        transport
+            .respond(
+                Method::POST,
+                "login/",
+                TransportResponse::new(StatusCode::OK, "{}"),
+            )
+            .respond(
+                Method::POST,
+                "two-factor-login/",
+                TransportResponse::new(StatusCode::BAD_REQUEST, rejected),
+            )
+            .respond(
+                Method::POST,
+                "two-factor-login/",
+                TransportResponse::new(StatusCode::CREATED, TOKENS),
+            );
+        let client = ClientConfig::new()
+            .transport(transport.clone())
+            .build()
+            .unwrap();
+        let totp = Totp::new(SecretBox::new(Box::new("JBSWY3DPEHPK3PXP".to_owned()))).unwrap();
+        let session = LoginFlow::new()
+            .client(client)
+            .login_with_totp("a@b.c", "hunter2", &totp)
+            .await
+            .unwrap();
+        assert_eq!(session.tokens().access, "a.b.c");
+
+        let codes: Vec<String> = transport
+            .requests()
+            .iter()
+            .skip(1)
+            .map(|r| r.body.clone().unwrap())
+            .collect();
+        assert_eq!(codes.len(), 2);
+        assert_ne!(codes[0], codes[1]);
+    }
+}
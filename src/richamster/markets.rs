@@ -0,0 +1,155 @@
+use crate::api::token::{CurrencyPair, Token};
+use crate::errors::RichamsterError;
+use crate::models::exchange::{CurrencyInfo, CurrencyPairRestriction, Market};
+use crate::richamster::exchange::Exchange;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+const DEFAULT_TTL: Duration = Duration::from_secs(300);
+
+/// Markets, restrictions and currencies as listed at one point in time, indexed
+/// by currency pair, market id and token. Entries whose abbreviation is not a
+/// known [`Token`] are left out.
+#[derive(Debug)]
+pub struct MarketSnapshot {
+    markets: HashMap<CurrencyPair, Market>,
+    pairs: HashMap<i32, CurrencyPair>,
+    restrictions: HashMap<CurrencyPair, CurrencyPairRestriction>,
+    currencies: HashMap<Token, CurrencyInfo>,
+    loaded_at: Instant,
+}
+
+impl MarketSnapshot {
+    pub fn new(
+        markets: Vec<Market>,
+        restrictions: Vec<CurrencyPairRestriction>,
+        currencies: Vec<CurrencyInfo>,
+    ) -> Self {
+        let markets: HashMap<CurrencyPair, Market> = markets
+            .into_iter()
+            .filter_map(|m| Some((m.abbreviation.parse().ok()?, m)))
+            .collect();
+        Self {
+            pairs: markets.iter().map(|(pair, m)| (m.id, *pair)).collect(),
+            markets,
+            restrictions: restrictions
+                .into_iter()
+                .filter_map(|r| Some((r.currency_pair.parse().ok()?, r)))
+                .collect(),
+            currencies: currencies
+                .into_iter()
+                .filter_map(|c| Some((c.abbreviation.parse().ok()?, c)))
+                .collect(),
+            loaded_at: Instant::now(),
+        }
+    }
+
+    pub fn market(&self, pair: CurrencyPair) -> Option<&Market> {
+        self.markets.get(&pair)
+    }
+
+    pub fn market_id(&self, pair: CurrencyPair) -> Option<i32> {
+        self.market(pair).map(|m| m.id)
+    }
+
+    pub fn pair(&self, market_id: i32) -> Option<CurrencyPair> {
+        self.pairs.get(&market_id).copied()
+    }
+
+    pub fn restriction(&self, pair: CurrencyPair) -> Option<&CurrencyPairRestriction> {
+        self.restrictions.get(&pair)
+    }
+
+    pub fn currency(&self, token: Token) -> Option<&CurrencyInfo> {
+        self.currencies.get(&token)
+    }
+
+    pub fn markets(&self) -> impl Iterator<Item = (&CurrencyPair, &Market)> {
+        self.markets.iter()
+    }
+
+    pub fn age(&self) -> Duration {
+        self.loaded_at.elapsed()
+    }
+}
+
+/// Lazily loaded [`MarketSnapshot`], reloaded once older than the TTL. Clones
+/// share the cache, so one registry can serve several [`Exchange`] instances.
+/// Concurrent lookups of an expired snapshot wait for a single reload.
+#[derive(Clone, Debug)]
+pub struct MarketRegistry {
+    ttl: Duration,
+    snapshot: Arc<Mutex<Option<Arc<MarketSnapshot>>>>,
+    loading: Arc<tokio::sync::Mutex<()>>,
+}
+
+impl Default for MarketRegistry {
+    fn default() -> Self {
+        Self::new(DEFAULT_TTL)
+    }
+}
+
+impl MarketRegistry {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            snapshot: Arc::new(Mutex::new(None)),
+            loading: Arc::new(tokio::sync::Mutex::new(())),
+        }
+    }
+
+    pub fn ttl(&self) -> Duration {
+        self.ttl
+    }
+
+    /// Cached snapshot, or a fresh one fetched through `exchange` when there is
+    /// none or it expired.
+    pub async fn snapshot(
+        &self,
+        exchange: &Exchange,
+    ) -> Result<Arc<MarketSnapshot>, RichamsterError> {
+        if let Some(snapshot) = self.cached() {
+            return Ok(snapshot);
+        }
+        let _loading = self.loading.lock().await;
+        if let Some(snapshot) = self.cached() {
+            return Ok(snapshot);
+        }
+        let (markets, restrictions, currencies) = tokio::try_join!(
+            exchange.markets_list(),
+            exchange.restrictions_list(),
+            exchange.currencies_list(None),
+        )?;
+        let snapshot = Arc::new(MarketSnapshot::new(markets, restrictions, currencies.data));
+        *self.snapshot.lock().unwrap() = Some(snapshot.clone());
+        Ok(snapshot)
+    }
+
+    /// Id of the market trading `pair`, fails with
+    /// [`RichamsterError::IllegalCurrencyPair`] for an unlisted pair.
+    pub async fn market_id(
+        &self,
+        exchange: &Exchange,
+        pair: CurrencyPair,
+    ) -> Result<i32, RichamsterError> {
+        self.snapshot(exchange)
+            .await?
+            .market_id(pair)
+            .ok_or(RichamsterError::IllegalCurrencyPair(pair))
+    }
+
+    /// Drops the cached snapshot, the next lookup reloads it.
+    pub fn invalidate(&self) {
+        *self.snapshot.lock().unwrap() = None;
+    }
+
+    fn cached(&self) -> Option<Arc<MarketSnapshot>> {
+        self.snapshot
+            .lock()
+            .unwrap()
+            .as_ref()
+            .filter(|snapshot| snapshot.age() < self.ttl)
+            .cloned()
+    }
+}
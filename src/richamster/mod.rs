@@ -1,7 +1,20 @@
 pub mod auth;
 pub mod common;
+pub mod credentials;
 pub mod exchange;
+pub mod facade;
 pub mod feedback;
+pub mod journal;
+pub mod login;
+pub mod markets;
 pub mod replenish;
+pub mod session;
+pub mod signer;
+#[cfg(feature = "encrypted-store")]
+pub mod store;
+#[cfg(feature = "totp")]
+pub mod totp;
 pub mod user;
 pub mod withdraw;
+
+pub use facade::{Richamster, Wallet};
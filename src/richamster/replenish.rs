@@ -1,31 +1,105 @@
+use crate::api::client::RichamsterClient;
+use crate::api::permissions::Permissions;
 use crate::api::token::Token;
-use crate::api::{Api, ReplenishApi, RequestData, RequestPath};
+use crate::api::{Api, ReplenishApi, RequestData};
 use crate::errors::RichamsterError;
 use crate::models::common::CurrencyChannel;
 use crate::models::replenish::{P2PReplenish, ReplenishInfo};
-use crate::richamster::common::{ApiKey, AuthState, HeaderCompose, JwtToken, SecretKey};
+use crate::richamster::common::{ApiKey, AuthState, JwtToken, SecretKey, SharedAuthState};
+use crate::richamster::credentials::CredentialProvider;
+use crate::richamster::session::Session;
+use crate::richamster::signer::Signer;
 use crate::send_request;
 use reqwest::StatusCode;
+use std::sync::Arc;
 
+#[derive(Clone, Debug, Default)]
 pub struct Replenish {
-    auth_state: AuthState,
+    client: RichamsterClient,
+    auth_state: SharedAuthState,
 }
 
 impl Replenish {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
     pub fn with_jwt_token(token: String) -> Self {
         Self {
-            auth_state: AuthState::JwtTokenAuth(JwtToken::new(token)),
+            client: RichamsterClient::default(),
+            auth_state: SharedAuthState::new(AuthState::JwtTokenAuth(JwtToken::new(token))),
         }
     }
 
     pub fn with_keys(api_key: String, secret_key: String) -> Self {
         Self {
-            auth_state: AuthState::ApiSecretKeyAuth(
+            client: RichamsterClient::default(),
+            auth_state: SharedAuthState::new(AuthState::ApiSecretKeyAuth(
                 ApiKey::new(api_key),
                 SecretKey::new(secret_key),
-            ),
+            )),
+        }
+    }
+
+    pub fn with_jwt_and_keys(jwt: String, api_key: String, secret_key: String) -> Self {
+        Self {
+            client: RichamsterClient::default(),
+            auth_state: SharedAuthState::new(AuthState::JwtTokenWithApiSecretKeyAuth(
+                JwtToken::new(jwt),
+                ApiKey::new(api_key),
+                SecretKey::new(secret_key),
+            )),
+        }
+    }
+
+    /// Authenticates with the JWT of `session`, refreshed as needed.
+    pub fn with_session(session: Session) -> Self {
+        Self {
+            client: RichamsterClient::default(),
+            auth_state: SharedAuthState::new(AuthState::Session(session)),
+        }
+    }
+
+    /// Signs requests with `signer` instead of a secret key held in memory.
+    pub fn with_signer(api_key: String, signer: Arc<dyn Signer>) -> Self {
+        Self {
+            client: RichamsterClient::default(),
+            auth_state: SharedAuthState::new(AuthState::ApiKeySignerAuth(
+                ApiKey::new(api_key),
+                signer,
+            )),
         }
     }
+
+    /// Authenticates with the credentials loaded from `provider`.
+    pub fn with_credentials(provider: &impl CredentialProvider) -> Result<Self, RichamsterError> {
+        Ok(Self {
+            client: RichamsterClient::default(),
+            auth_state: SharedAuthState::new(provider.auth_state()?),
+        })
+    }
+
+    /// Service authenticating with `auth_state`, shared with other services.
+    pub(crate) fn shared(client: RichamsterClient, auth_state: SharedAuthState) -> Self {
+        Self { client, auth_state }
+    }
+
+    /// Credentials of this service and its clones, see [`SharedAuthState::replace`].
+    pub fn auth_state(&self) -> &SharedAuthState {
+        &self.auth_state
+    }
+
+    /// Limits this handle to `permissions`, see [`Permissions`]. Capabilities
+    /// can only be removed, by this call as by any later one.
+    pub fn restrict(mut self, permissions: Permissions) -> Self {
+        self.auth_state = self.auth_state.restrict(permissions);
+        self
+    }
+
+    pub fn client(mut self, client: RichamsterClient) -> Self {
+        self.client = client;
+        self
+    }
 }
 
 impl Replenish {
@@ -34,14 +108,14 @@ impl Replenish {
         currency_name: Token,
         currency_channel: String,
     ) -> Result<ReplenishInfo, RichamsterError> {
-        let RequestData(url, method) = Api::Replenish(ReplenishApi::ReplenishInfo).request_data();
+        let api = Api::Replenish(ReplenishApi::ReplenishInfo);
+        let RequestData(url, method) = self.client.request_data(&api);
         let path = format!("{}/{}/", currency_name.as_ref(), currency_channel);
         let url = url.join(&path)?;
-        let resp = send_request!(url, method, self.auth_state);
+        let resp = send_request!(self.client, api, url, method, self.auth_state);
         match resp.status() {
             StatusCode::OK => {
-                let string = resp.text().await?;
-                let balance: ReplenishInfo = serde_json::from_str(&string)?;
+                let balance: ReplenishInfo = resp.json()?;
                 Ok(balance)
             }
             StatusCode::UNAUTHORIZED => Err(RichamsterError::UnauthorizedAccess),
@@ -51,7 +125,7 @@ impl Replenish {
             )),
             status => Err(RichamsterError::UnsupportedResponseCode(
                 status,
-                resp.text().await?,
+                resp.text().to_owned(),
             )),
         }
     }
@@ -60,20 +134,19 @@ impl Replenish {
         &self,
         currency_name: Token,
     ) -> Result<Vec<CurrencyChannel>, RichamsterError> {
-        let RequestData(mut url, method) =
-            Api::Replenish(ReplenishApi::ReplenishChannelsInfo).request_data();
+        let api = Api::Replenish(ReplenishApi::ReplenishChannelsInfo);
+        let RequestData(mut url, method) = self.client.request_data(&api);
         url = url.join(currency_name.as_ref())?;
-        let resp = send_request!(url, method, self.auth_state);
+        let resp = send_request!(self.client, api, url, method, self.auth_state);
         match resp.status() {
             StatusCode::OK => {
-                let string = resp.text().await?;
-                let channels: Vec<CurrencyChannel> = serde_json::from_str(&string)?;
+                let channels: Vec<CurrencyChannel> = resp.json()?;
                 Ok(channels)
             }
             StatusCode::UNAUTHORIZED => Err(RichamsterError::UnauthorizedAccess),
             status => Err(RichamsterError::UnsupportedResponseCode(
                 status,
-                resp.text().await?,
+                resp.text().to_owned(),
             )),
         }
     }
@@ -82,8 +155,11 @@ impl Replenish {
         &self,
         replenish: P2PReplenish,
     ) -> Result<P2PReplenish, RichamsterError> {
-        let RequestData(url, method) = Api::Replenish(ReplenishApi::P2PReplenish).request_data();
+        let api = Api::Replenish(ReplenishApi::P2PReplenish);
+        let RequestData(url, method) = self.client.request_data(&api);
         let resp = send_request!(
+            self.client,
+            api,
             url,
             method,
             self.auth_state,
@@ -91,14 +167,13 @@ impl Replenish {
         );
         match resp.status() {
             StatusCode::CREATED => {
-                let string = resp.text().await?;
-                let p2p_replenish: P2PReplenish = serde_json::from_str(&string)?;
+                let p2p_replenish: P2PReplenish = resp.json()?;
                 Ok(p2p_replenish)
             }
             StatusCode::UNAUTHORIZED => Err(RichamsterError::UnauthorizedAccess),
             status => Err(RichamsterError::UnsupportedResponseCode(
                 status,
-                resp.text().await?,
+                resp.text().to_owned(),
             )),
         }
     }
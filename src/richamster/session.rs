@@ -0,0 +1,343 @@
+use crate::api::client::RichamsterClient;
+use crate::errors::RichamsterError;
+use crate::models::auth::TokenData;
+use crate::richamster::auth::Auth;
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use secrecy::{ExposeSecret, SecretString};
+use std::fmt::{Debug, Formatter};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const DEFAULT_REFRESH_MARGIN: Duration = Duration::from_secs(60);
+
+#[derive(Debug)]
+struct Tokens {
+    access: SecretString,
+    refresh: SecretString,
+    expires_at: Option<SystemTime>,
+    /// Incremented on every refresh, so concurrent callers refresh only once.
+    generation: u64,
+}
+
+type RefreshListener =
+    Arc<dyn Fn(&TokenData) -> Pin<Box<dyn Future<Output = ()> + Send>> + Send + Sync>;
+
+struct Shared {
+    tokens: Mutex<Tokens>,
+    refreshing: tokio::sync::Mutex<()>,
+    refresh_listeners: Mutex<Vec<RefreshListener>>,
+}
+
+impl Debug for Shared {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Shared")
+            .field("tokens", &self.tokens)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Access and refresh tokens of a logged in user. The access token is refreshed
+/// with [`Auth::refresh_token`] shortly before it expires, and after a request
+/// is rejected with `401 Unauthorized`, in which case the request is sent again
+/// once with the new token. Clones share the tokens, so one session can back
+/// every service.
+#[derive(Clone, Debug)]
+pub struct Session {
+    shared: Arc<Shared>,
+    refresh_margin: Duration,
+}
+
+impl Session {
+    pub fn new(tokens: TokenData) -> Self {
+        Self {
+            shared: Arc::new(Shared {
+                tokens: Mutex::new(Tokens {
+                    expires_at: expiry(&tokens.access),
+                    access: tokens.access.into(),
+                    refresh: tokens.refresh.into(),
+                    generation: 0,
+                }),
+                refreshing: tokio::sync::Mutex::new(()),
+                refresh_listeners: Mutex::new(Vec::new()),
+            }),
+            refresh_margin: DEFAULT_REFRESH_MARGIN,
+        }
+    }
+
+    /// How long before expiry the access token is refreshed, one minute by default.
+    pub fn refresh_margin(mut self, refresh_margin: Duration) -> Self {
+        self.refresh_margin = refresh_margin;
+        self
+    }
+
+    /// Registers `listener` to be called with the new tokens after every
+    /// refresh, e.g. to persist them. Listeners are shared by all clones of the
+    /// session and called in the order they were added.
+    pub fn add_refresh_listener(&self, listener: impl Fn(&TokenData) + Send + Sync + 'static) {
+        self.push_listener(Arc::new(move |tokens| {
+            listener(tokens);
+            Box::pin(std::future::ready(()))
+        }));
+    }
+
+    /// Same as [`Session::add_refresh_listener`], but the refresh does not
+    /// return before the future returned by `listener` completes.
+    pub fn add_async_refresh_listener<F>(
+        &self,
+        listener: impl Fn(&TokenData) -> F + Send + Sync + 'static,
+    ) where
+        F: Future<Output = ()> + Send + 'static,
+    {
+        self.push_listener(Arc::new(move |tokens| Box::pin(listener(tokens))));
+    }
+
+    fn push_listener(&self, listener: RefreshListener) {
+        self.shared.refresh_listeners.lock().unwrap().push(listener);
+    }
+
+    /// Expiry decoded from the `exp` claim of the access token, if it has one.
+    pub fn expires_at(&self) -> Option<SystemTime> {
+        self.shared.tokens.lock().unwrap().expires_at
+    }
+
+    /// Current tokens, e.g. to persist the session.
+    pub fn tokens(&self) -> TokenData {
+        let tokens = self.shared.tokens.lock().unwrap();
+        TokenData {
+            access: tokens.access.expose_secret().to_owned(),
+            refresh: tokens.refresh.expose_secret().to_owned(),
+        }
+    }
+
+    /// Refreshes the access token now.
+    pub async fn refresh(&self, client: &RichamsterClient) -> Result<(), RichamsterError> {
+        let generation = self.shared.tokens.lock().unwrap().generation;
+        self.refresh_after(client, generation).await
+    }
+
+    pub(crate) fn access_token(&self) -> (String, u64) {
+        let tokens = self.shared.tokens.lock().unwrap();
+        (tokens.access.expose_secret().to_owned(), tokens.generation)
+    }
+
+    /// Access token valid for at least the refresh margin, refreshed first if needed.
+    pub(crate) async fn valid_access_token(
+        &self,
+        client: &RichamsterClient,
+    ) -> Result<(String, u64), RichamsterError> {
+        let (expiring, generation) = {
+            let tokens = self.shared.tokens.lock().unwrap();
+            let expiring = tokens
+                .expires_at
+                .is_some_and(|at| at <= SystemTime::now() + self.refresh_margin);
+            (expiring, tokens.generation)
+        };
+        if expiring {
+            self.refresh_after(client, generation).await?;
+        }
+        Ok(self.access_token())
+    }
+
+    /// Refreshes unless another caller already did since `generation` was read.
+    pub(crate) async fn refresh_after(
+        &self,
+        client: &RichamsterClient,
+        generation: u64,
+    ) -> Result<(), RichamsterError> {
+        let _refreshing = self.shared.refreshing.lock().await;
+        let refresh = {
+            let tokens = self.shared.tokens.lock().unwrap();
+            if tokens.generation != generation {
+                return Ok(());
+            }
+            tokens.refresh.expose_secret().to_owned()
+        };
+        tracing::debug!("refreshing access token");
+        let refreshed = Auth::new()
+            .client(client.clone())
+            .refresh_token(refresh)
+            .await?;
+        {
+            let mut tokens = self.shared.tokens.lock().unwrap();
+            tokens.expires_at = expiry(&refreshed.access);
+            tokens.access = refreshed.access.into();
+            if !refreshed.refresh.is_empty() {
+                tokens.refresh = refreshed.refresh.into();
+            }
+            tokens.generation += 1;
+        }
+        let listeners = self.shared.refresh_listeners.lock().unwrap().clone();
+        if !listeners.is_empty() {
+            let tokens = self.tokens();
+            for listener in listeners {
+                listener(&tokens).await;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl From<TokenData> for Session {
+    fn from(tokens: TokenData) -> Self {
+        Self::new(tokens)
+    }
+}
+
+/// `exp` claim of a JWT. The signature is not verified, the server does that.
+fn expiry(jwt: &str) -> Option<SystemTime> {
+    let payload = jwt.split('.').nth(1)?;
+    let claims: serde_json::Value =
+        serde_json::from_slice(&URL_SAFE_NO_PAD.decode(payload).ok()?).ok()?;
+    let exp = claims.get("exp")?.as_u64()?;
+    Some(UNIX_EPOCH + Duration::from_secs(exp))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::api::client::ClientConfig;
+    use crate::api::transport::{InMemoryTransport, TransportResponse};
+    use crate::richamster::user::User;
+    use reqwest::{Method, StatusCode};
+
+    const USER_DETAIL: &str = r#"{"username":"hamster","first_name":"Rich","middle_name":null,"last_name":"Hamster","dices":3,"role":"user","phone":null,"email":"hamster@example.com","fee":0.2}"#;
+
+    fn now() -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs()
+    }
+
+    fn refreshed(access: &str) -> TransportResponse {
+        TransportResponse::new(
+            StatusCode::OK,
+            format!(r#"{{"access":"{access}","refresh":"refresh-2"}}"#),
+        )
+    }
+
+    fn jwt(exp: u64) -> String {
+        let payload = URL_SAFE_NO_PAD.encode(format!(r#"{{"token_type":"access","exp":{exp}}}"#));
+        format!("eyJhbGciOiJIUzI1NiJ9.{payload}.c2lnbmF0dXJl")
+    }
+
+    #[test]
+    fn expiry_is_decoded_from_access_token() {
+        let session = Session::new(TokenData {
+            access: jwt(1_700_000_000),
+            refresh: "r".to_owned(),
+        });
+        assert_eq!(
+            session.expires_at(),
+            Some(UNIX_EPOCH + Duration::from_secs(1_700_000_000))
+        );
+        assert_eq!(expiry("opaque"), None);
+    }
+
+    #[tokio::test]
+    async fn expiring_token_is_refreshed_before_request() {
+        let fresh = jwt(now() + 3600);
+        let transport = Arc::new(InMemoryTransport::new());
+        transport
+            .respond(Method::POST, "token/refresh/", refreshed(&fresh))
+            .respond(
+                Method::GET,
+                "user/detail/",
+                TransportResponse::new(StatusCode::OK, USER_DETAIL),
+            );
+        let client = ClientConfig::new()
+            .transport(transport.clone())
+            .build()
+            .unwrap();
+        let session = Session::new(TokenData {
+            access: jwt(now() + 10),
+            refresh: "refresh-1".to_owned(),
+        });
+        let user = User::with_session(session.clone()).client(client);
+        user.detail_info().await.unwrap();
+
+        let requests = transport.requests();
+        assert_eq!(requests.len(), 2);
+        assert_eq!(
+            requests[0].body.as_deref(),
+            Some(r#"{"refresh":"refresh-1"}"#)
+        );
+        let authorization = format!("JWT {fresh}");
+        assert_eq!(
+            requests[1].header_value("Authorization"),
+            Some(authorization.as_str())
+        );
+        assert_eq!(session.tokens().refresh, "refresh-2");
+    }
+
+    #[tokio::test]
+    async fn unauthorized_request_is_sent_again_after_refresh() {
+        let transport = Arc::new(InMemoryTransport::new());
+        transport
+            .respond(Method::POST, "token/refresh/", refreshed("new-access"))
+            .respond(
+                Method::GET,
+                "user/detail/",
+                TransportResponse::new(StatusCode::UNAUTHORIZED, ""),
+            )
+            .respond(
+                Method::GET,
+                "user/detail/",
+                TransportResponse::new(StatusCode::OK, USER_DETAIL),
+            );
+        let client = ClientConfig::new()
+            .transport(transport.clone())
+            .build()
+            .unwrap();
+        let session = Session::new(TokenData {
+            access: "revoked".to_owned(),
+            refresh: "refresh-1".to_owned(),
+        });
+        let user = User::with_session(session.clone()).client(client);
+        assert_eq!(user.detail_info().await.unwrap().username, "hamster");
+
+        let requests = transport.requests();
+        assert_eq!(requests.len(), 3);
+        assert_eq!(
+            requests[0].header_value("Authorization"),
+            Some("JWT revoked")
+        );
+        assert_eq!(
+            requests[2].header_value("Authorization"),
+            Some("JWT new-access")
+        );
+        assert_eq!(session.tokens().access, "new-access");
+    }
+
+    #[tokio::test]
+    async fn refresh_listeners_added_to_any_clone_are_all_called() {
+        let transport = Arc::new(InMemoryTransport::new());
+        transport.respond(Method::POST, "token/refresh/", refreshed("new-access"));
+        let client = ClientConfig::new()
+            .transport(transport.clone())
+            .build()
+            .unwrap();
+        let session = Session::new(TokenData {
+            access: "old-access".to_owned(),
+            refresh: "refresh-1".to_owned(),
+        });
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        for (name, handle) in [("original", session.clone()), ("clone", session.clone())] {
+            let seen = seen.clone();
+            handle.add_refresh_listener(move |tokens| {
+                seen.lock().unwrap().push((name, tokens.access.clone()));
+            });
+        }
+        session.refresh(&client).await.unwrap();
+        assert_eq!(
+            *seen.lock().unwrap(),
+            [
+                ("original", "new-access".to_owned()),
+                ("clone", "new-access".to_owned())
+            ]
+        );
+    }
+}
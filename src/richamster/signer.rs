@@ -0,0 +1,219 @@
+use crate::errors::RichamsterError;
+use crate::richamster::common::SecretKey;
+use async_trait::async_trait;
+use hmac_sha256::HMAC;
+use std::fmt::Debug;
+
+/// Produces the hex `Signature` header from the canonical request, see
+/// [`canonical_request`](crate::api::signing::canonical_request). The secret
+/// key only has to be reachable by the signer, which lets it live outside the
+/// process sending the requests.
+#[async_trait]
+pub trait Signer: Debug + Send + Sync {
+    async fn sign(&self, canonical: &str) -> Result<String, RichamsterError>;
+}
+
+/// In-memory HMAC-SHA256, the signer used with [`with_keys`](crate::richamster::user::User::with_keys).
+#[async_trait]
+impl Signer for SecretKey {
+    async fn sign(&self, canonical: &str) -> Result<String, RichamsterError> {
+        Ok(hex::encode(HMAC::mac(canonical, self.value())))
+    }
+}
+
+#[cfg(all(unix, feature = "external-signer"))]
+pub use unix::{UnixSocketSigner, serve};
+
+/// Signing over a Unix domain socket. Each connection carries one request line,
+/// `{"payload":"<canonical request>"}`, answered with one line,
+/// `{"signature":"<hex>"}` or `{"error":"<message>"}`.
+#[cfg(all(unix, feature = "external-signer"))]
+mod unix {
+    use super::Signer;
+    use crate::errors::RichamsterError;
+    use async_trait::async_trait;
+    use serde_derive::{Deserialize, Serialize};
+    use std::path::{Path, PathBuf};
+    use std::sync::Arc;
+    use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+    use tokio::net::{UnixListener, UnixStream};
+
+    /// Longest request or response line read, canonical requests carry a body
+    /// hash rather than the body.
+    const MAX_LINE_BYTES: u64 = 64 * 1024;
+
+    #[derive(Serialize, Deserialize)]
+    struct SignRequest {
+        payload: String,
+    }
+
+    #[derive(Serialize, Deserialize)]
+    struct SignResponse {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        signature: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        error: Option<String>,
+    }
+
+    /// Forwards canonical requests to a signing daemon such as the
+    /// `richamster-signer` binary.
+    #[derive(Clone, Debug)]
+    pub struct UnixSocketSigner {
+        path: PathBuf,
+    }
+
+    impl UnixSocketSigner {
+        pub fn new(path: impl AsRef<Path>) -> Self {
+            Self {
+                path: path.as_ref().to_owned(),
+            }
+        }
+    }
+
+    #[async_trait]
+    impl Signer for UnixSocketSigner {
+        async fn sign(&self, canonical: &str) -> Result<String, RichamsterError> {
+            let mut stream = UnixStream::connect(&self.path).await?;
+            let mut line = serde_json::to_string(&SignRequest {
+                payload: canonical.to_owned(),
+            })?;
+            line.push('\n');
+            stream.write_all(line.as_bytes()).await?;
+            let mut response = String::new();
+            BufReader::new(stream.take(MAX_LINE_BYTES))
+                .read_line(&mut response)
+                .await?;
+            let response: SignResponse = serde_json::from_str(&response)?;
+            match (response.signature, response.error) {
+                (Some(signature), _) => Ok(signature),
+                (None, error) => Err(RichamsterError::Signer(
+                    error.unwrap_or_else(|| "empty response".to_owned()),
+                )),
+            }
+        }
+    }
+
+    /// Answers signing requests on `listener` with `signer` until accepting fails.
+    pub async fn serve(listener: UnixListener, signer: Arc<dyn Signer>) -> std::io::Result<()> {
+        loop {
+            let (stream, _) = listener.accept().await?;
+            let signer = signer.clone();
+            tokio::spawn(async move {
+                if let Err(e) = handle(stream, signer.as_ref()).await {
+                    tracing::warn!("signing request failed: {e}");
+                }
+            });
+        }
+    }
+
+    async fn handle(stream: UnixStream, signer: &dyn Signer) -> Result<(), RichamsterError> {
+        let (read, mut write) = stream.into_split();
+        let mut line = String::new();
+        BufReader::new(read.take(MAX_LINE_BYTES))
+            .read_line(&mut line)
+            .await?;
+        let response = match serde_json::from_str::<SignRequest>(&line) {
+            Ok(request) => match signer.sign(&request.payload).await {
+                Ok(signature) => SignResponse {
+                    signature: Some(signature),
+                    error: None,
+                },
+                Err(e) => SignResponse {
+                    signature: None,
+                    error: Some(e.to_string()),
+                },
+            },
+            Err(e) => SignResponse {
+                signature: None,
+                error: Some(format!("invalid request: {e}")),
+            },
+        };
+        let mut response = serde_json::to_string(&response)?;
+        response.push('\n');
+        write.write_all(response.as_bytes()).await?;
+        Ok(())
+    }
+
+    #[cfg(test)]
+    mod test {
+        use super::*;
+        use crate::richamster::common::SecretKey;
+
+        #[tokio::test]
+        async fn daemon_signs_like_the_in_memory_signer() {
+            let path =
+                std::env::temp_dir().join(format!("richamster-signer-{}.sock", std::process::id()));
+            let _ = std::fs::remove_file(&path);
+            let listener = UnixListener::bind(&path).unwrap();
+            let key = SecretKey::new("secret".to_owned());
+            let expected = key.sign("canonical\nrequest").await.unwrap();
+            let server = tokio::spawn(serve(listener, Arc::new(key)));
+
+            let signature = UnixSocketSigner::new(&path)
+                .sign("canonical\nrequest")
+                .await
+                .unwrap();
+            assert_eq!(signature, expected);
+
+            let mut stream = UnixStream::connect(&path).await.unwrap();
+            let oversized = vec![b'a'; MAX_LINE_BYTES as usize + 16];
+            stream.write_all(&oversized).await.unwrap();
+            let mut response = String::new();
+            BufReader::new(stream)
+                .read_line(&mut response)
+                .await
+                .unwrap();
+            assert!(response.contains("invalid request"));
+            server.abort();
+            std::fs::remove_file(&path).unwrap();
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::api::client::ClientConfig;
+    use crate::api::signing::{SigningScheme, canonical_request};
+    use crate::api::transport::{InMemoryTransport, TransportResponse};
+    use crate::richamster::user::User;
+    use reqwest::{Method, StatusCode};
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Debug, Default)]
+    struct Recording(Mutex<Vec<String>>);
+
+    #[async_trait]
+    impl Signer for Recording {
+        async fn sign(&self, canonical: &str) -> Result<String, RichamsterError> {
+            self.0.lock().unwrap().push(canonical.to_owned());
+            Ok("external".to_owned())
+        }
+    }
+
+    #[tokio::test]
+    async fn requests_are_signed_by_the_signer() {
+        let transport = Arc::new(InMemoryTransport::new());
+        transport.respond(
+            Method::GET,
+            "user/balances/",
+            TransportResponse::new(StatusCode::OK, "[]"),
+        );
+        let client = ClientConfig::new()
+            .transport(transport.clone())
+            .signing_scheme(SigningScheme::V2)
+            .build()
+            .unwrap();
+        let signer = Arc::new(Recording::default());
+        let user = User::with_signer("api".to_owned(), signer.clone()).client(client);
+        user.balances(None).await.unwrap();
+
+        let request = transport.last_request().unwrap();
+        assert_eq!(request.header_value("Api-Key"), Some("api"));
+        assert_eq!(request.header_value("Signature"), Some("external"));
+        assert_eq!(
+            *signer.0.lock().unwrap(),
+            vec![canonical_request(SigningScheme::V2, &request)]
+        );
+    }
+}
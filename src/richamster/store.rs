@@ -0,0 +1,399 @@
+use crate::errors::RichamsterError;
+use crate::models::auth::TokenData;
+use crate::richamster::common::{ApiKey, JwtToken, SecretKey};
+use crate::richamster::credentials::{CredentialProvider, Credentials};
+use crate::richamster::session::Session;
+use argon2::{Algorithm, Argon2, Params, Version};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+use secrecy::{ExposeSecret, SecretString};
+use serde_derive::{Deserialize, Serialize};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use zeroize::{Zeroize, Zeroizing};
+
+const FORMAT_VERSION: u8 = 1;
+const SALT_LEN: usize = 16;
+
+/// Secrets kept in a [`SecretStore`].
+#[derive(Debug, Default)]
+pub struct StoredSecrets {
+    pub jwt: Option<JwtToken>,
+    pub refresh: Option<SecretString>,
+    pub api_key: Option<ApiKey>,
+    pub secret_key: Option<SecretKey>,
+}
+
+/// Plaintext form of [`StoredSecrets`], wiped when dropped.
+#[derive(Serialize, Deserialize, Default)]
+struct Plain {
+    jwt: Option<String>,
+    refresh: Option<String>,
+    api_key: Option<String>,
+    secret_key: Option<String>,
+}
+
+impl Drop for Plain {
+    fn drop(&mut self) {
+        self.jwt.zeroize();
+        self.refresh.zeroize();
+        self.api_key.zeroize();
+        self.secret_key.zeroize();
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct Envelope {
+    version: u8,
+    memory_kib: u32,
+    iterations: u32,
+    parallelism: u32,
+    salt: String,
+    nonce: String,
+    ciphertext: String,
+}
+
+impl Envelope {
+    /// Everything but the ciphertext is authenticated as associated data.
+    fn associated_data(&self) -> Vec<u8> {
+        format!(
+            "richamster-store:{}:{}:{}:{}:{}:{}",
+            self.version, self.memory_kib, self.iterations, self.parallelism, self.salt, self.nonce
+        )
+        .into_bytes()
+    }
+}
+
+/// File holding tokens and API keys encrypted with XChaCha20-Poly1305, under a
+/// key derived from a password with Argon2id. Every save uses a fresh salt and
+/// nonce, and the file is replaced atomically.
+#[derive(Clone, Debug)]
+pub struct SecretStore {
+    path: PathBuf,
+    password: Arc<SecretString>,
+    memory_kib: u32,
+    iterations: u32,
+    parallelism: u32,
+}
+
+impl SecretStore {
+    pub fn new(path: impl AsRef<Path>, password: SecretString) -> Self {
+        Self {
+            path: path.as_ref().to_owned(),
+            password: Arc::new(password),
+            memory_kib: Params::DEFAULT_M_COST,
+            iterations: Params::DEFAULT_T_COST,
+            parallelism: Params::DEFAULT_P_COST,
+        }
+    }
+
+    /// Argon2id cost used for the next save. Loading uses the cost recorded in
+    /// the file.
+    pub fn kdf_params(mut self, memory_kib: u32, iterations: u32, parallelism: u32) -> Self {
+        self.memory_kib = memory_kib;
+        self.iterations = iterations;
+        self.parallelism = parallelism;
+        self
+    }
+
+    /// Stored secrets, empty when the file does not exist yet.
+    pub fn load(&self) -> Result<StoredSecrets, RichamsterError> {
+        let contents = match std::fs::read(&self.path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                return Ok(StoredSecrets::default());
+            }
+            Err(e) => return Err(e.into()),
+        };
+        let envelope: Envelope = serde_json::from_slice(&contents)?;
+        if envelope.version != FORMAT_VERSION {
+            return Err(store_error(format!(
+                "unsupported format version {}",
+                envelope.version
+            )));
+        }
+        let salt = decode(&envelope.salt)?;
+        let nonce = decode(&envelope.nonce)?;
+        if nonce.len() != 24 {
+            return Err(store_error("invalid nonce"));
+        }
+        let key = self.derive_key(
+            &salt,
+            envelope.memory_kib,
+            envelope.iterations,
+            envelope.parallelism,
+        )?;
+        let cipher = XChaCha20Poly1305::new(key.as_ref().into());
+        let plaintext = Zeroizing::new(
+            cipher
+                .decrypt(
+                    XNonce::from_slice(&nonce),
+                    Payload {
+                        msg: &decode(&envelope.ciphertext)?,
+                        aad: &envelope.associated_data(),
+                    },
+                )
+                .map_err(|_| store_error("wrong password or corrupted file"))?,
+        );
+        let mut plain: Plain = serde_json::from_slice(&plaintext)?;
+        Ok(StoredSecrets {
+            jwt: plain.jwt.take().map(JwtToken::new),
+            refresh: plain.refresh.take().map(Into::into),
+            api_key: plain.api_key.take().map(ApiKey::new),
+            secret_key: plain.secret_key.take().map(SecretKey::new),
+        })
+    }
+
+    pub fn save(&self, secrets: &StoredSecrets) -> Result<(), RichamsterError> {
+        let plain = Plain {
+            jwt: secrets.jwt.as_ref().map(|t| t.value().to_owned()),
+            refresh: secrets
+                .refresh
+                .as_ref()
+                .map(|t| t.expose_secret().to_owned()),
+            api_key: secrets.api_key.as_ref().map(|k| k.value().to_owned()),
+            secret_key: secrets.secret_key.as_ref().map(|k| k.value().to_owned()),
+        };
+        let plaintext = Zeroizing::new(serde_json::to_vec(&plain)?);
+        drop(plain);
+
+        let mut salt = [0u8; SALT_LEN];
+        OsRng.fill_bytes(&mut salt);
+        let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let mut envelope = Envelope {
+            version: FORMAT_VERSION,
+            memory_kib: self.memory_kib,
+            iterations: self.iterations,
+            parallelism: self.parallelism,
+            salt: STANDARD.encode(salt),
+            nonce: STANDARD.encode(nonce),
+            ciphertext: String::new(),
+        };
+        let key = self.derive_key(&salt, self.memory_kib, self.iterations, self.parallelism)?;
+        let cipher = XChaCha20Poly1305::new(key.as_ref().into());
+        let ciphertext = cipher
+            .encrypt(
+                &nonce,
+                Payload {
+                    msg: &plaintext,
+                    aad: &envelope.associated_data(),
+                },
+            )
+            .map_err(|_| store_error("encryption failed"))?;
+        envelope.ciphertext = STANDARD.encode(ciphertext);
+        self.write(&serde_json::to_vec(&envelope)?)
+    }
+
+    /// Loads, modifies and saves the stored secrets.
+    pub fn update(&self, f: impl FnOnce(&mut StoredSecrets)) -> Result<(), RichamsterError> {
+        let mut secrets = self.load()?;
+        f(&mut secrets);
+        self.save(&secrets)
+    }
+
+    pub fn save_session(&self, session: &Session) -> Result<(), RichamsterError> {
+        self.save_tokens(&session.tokens())
+    }
+
+    /// Session from the stored tokens, persisted again on every refresh, or
+    /// `None` when no tokens are stored.
+    pub fn session(&self) -> Result<Option<Session>, RichamsterError> {
+        let secrets = self.load()?;
+        let (Some(access), Some(refresh)) = (secrets.jwt, secrets.refresh) else {
+            return Ok(None);
+        };
+        let session = Session::new(TokenData {
+            access: access.value().to_owned(),
+            refresh: refresh.expose_secret().to_owned(),
+        });
+        self.persist(&session);
+        Ok(Some(session))
+    }
+
+    /// Writes the tokens of `session` back to the store whenever it refreshes.
+    /// The write runs on the blocking thread pool and the refresh returns once
+    /// it is done. A write left running by a cancelled refresh never replaces
+    /// newer tokens.
+    pub fn persist(&self, session: &Session) {
+        let store = self.clone();
+        let writer = Arc::new(RefreshWriter::default());
+        session.add_async_refresh_listener(move |tokens| {
+            let generation = writer.requested.fetch_add(1, Ordering::Relaxed) + 1;
+            let (store, writer, tokens) = (store.clone(), writer.clone(), tokens.clone());
+            let write = tokio::task::spawn_blocking(move || {
+                let mut written = writer.written.lock().unwrap();
+                if *written > generation {
+                    return;
+                }
+                match store.save_tokens(&tokens) {
+                    Ok(()) => *written = generation,
+                    Err(e) => tracing::warn!("failed to persist refreshed tokens: {e}"),
+                }
+            });
+            async move {
+                if let Err(e) = write.await {
+                    tracing::warn!("failed to persist refreshed tokens: {e}");
+                }
+            }
+        });
+    }
+
+    fn save_tokens(&self, tokens: &TokenData) -> Result<(), RichamsterError> {
+        self.update(|secrets| {
+            secrets.jwt = Some(JwtToken::new(tokens.access.clone()));
+            secrets.refresh = Some(tokens.refresh.clone().into());
+        })
+    }
+
+    fn derive_key(
+        &self,
+        salt: &[u8],
+        memory_kib: u32,
+        iterations: u32,
+        parallelism: u32,
+    ) -> Result<Zeroizing<[u8; 32]>, RichamsterError> {
+        let params = Params::new(memory_kib, iterations, parallelism, Some(32))
+            .map_err(|e| store_error(e.to_string()))?;
+        let mut key = Zeroizing::new([0u8; 32]);
+        Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+            .hash_password_into(self.password.expose_secret().as_bytes(), salt, key.as_mut())
+            .map_err(|e| store_error(e.to_string()))?;
+        Ok(key)
+    }
+
+    fn write(&self, contents: &[u8]) -> Result<(), RichamsterError> {
+        let mut tmp = self.path.clone().into_os_string();
+        tmp.push(".tmp");
+        let tmp = PathBuf::from(tmp);
+        // A leftover file would keep its permissions, start from a new one.
+        match std::fs::remove_file(&tmp) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e.into()),
+            _ => {}
+        }
+        let mut options = std::fs::OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        let mut file = options.open(&tmp)?;
+        file.write_all(contents)?;
+        file.sync_all()?;
+        std::fs::rename(&tmp, &self.path)?;
+        Ok(())
+    }
+}
+
+impl CredentialProvider for SecretStore {
+    fn credentials(&self) -> Result<Credentials, RichamsterError> {
+        let secrets = self.load()?;
+        Ok(Credentials {
+            jwt: secrets.jwt.map(|t| t.value().into()),
+            api_key: secrets.api_key.map(|k| k.value().into()),
+            secret_key: secrets.secret_key.map(|k| k.value().into()),
+        })
+    }
+}
+
+/// Orders the background writes of [`SecretStore::persist`].
+#[derive(Default)]
+struct RefreshWriter {
+    requested: AtomicU64,
+    written: Mutex<u64>,
+}
+
+fn decode(value: &str) -> Result<Vec<u8>, RichamsterError> {
+    STANDARD
+        .decode(value)
+        .map_err(|e| store_error(e.to_string()))
+}
+
+fn store_error(message: impl Into<String>) -> RichamsterError {
+    RichamsterError::SecretStore(message.into())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::api::client::ClientConfig;
+    use crate::api::transport::{InMemoryTransport, TransportResponse};
+    use reqwest::{Method, StatusCode};
+
+    fn store(name: &str, password: &str) -> SecretStore {
+        let path = std::env::temp_dir().join(format!(
+            "richamster-store-{}-{name}.json",
+            std::process::id()
+        ));
+        SecretStore::new(path, password.into()).kdf_params(64, 1, 1)
+    }
+
+    #[test]
+    fn secrets_round_trip_encrypted() {
+        let store = store("round-trip", "correct horse");
+        store
+            .save(&StoredSecrets {
+                api_key: Some(ApiKey::new("api".to_owned())),
+                secret_key: Some(SecretKey::new("secret".to_owned())),
+                ..StoredSecrets::default()
+            })
+            .unwrap();
+        let raw = std::fs::read_to_string(&store.path).unwrap();
+        assert!(!raw.contains(r#""secret""#));
+
+        let loaded = store.load().unwrap();
+        assert_eq!(loaded.api_key.unwrap().value(), "api");
+        assert_eq!(loaded.secret_key.unwrap().value(), "secret");
+        assert!(loaded.jwt.is_none());
+
+        let wrong = SecretStore::new(&store.path, "wrong".into());
+        assert!(matches!(wrong.load(), Err(RichamsterError::SecretStore(_))));
+        std::fs::remove_file(&store.path).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn leftover_temporary_file_does_not_leak_permissions() {
+        use std::os::unix::fs::PermissionsExt;
+        let store = store("leftover", "pw");
+        let mut tmp = store.path.clone().into_os_string();
+        tmp.push(".tmp");
+        std::fs::write(&tmp, "stale").unwrap();
+        std::fs::set_permissions(&tmp, std::fs::Permissions::from_mode(0o644)).unwrap();
+        store.save(&StoredSecrets::default()).unwrap();
+        let mode = std::fs::metadata(&store.path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+        std::fs::remove_file(&store.path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn refreshed_session_is_written_back() {
+        let store = store("session", "pw");
+        store
+            .save_session(&Session::new(TokenData {
+                access: "access-1".to_owned(),
+                refresh: "refresh-1".to_owned(),
+            }))
+            .unwrap();
+        let transport = Arc::new(InMemoryTransport::new());
+        transport.respond(
+            Method::POST,
+            "token/refresh/",
+            TransportResponse::new(
+                StatusCode::OK,
+                r#"{"access":"access-2","refresh":"refresh-2"}"#,
+            ),
+        );
+        let client = ClientConfig::new().transport(transport).build().unwrap();
+
+        let session = store.session().unwrap().unwrap();
+        session.refresh(&client).await.unwrap();
+
+        let loaded = store.load().unwrap();
+        assert_eq!(loaded.jwt.unwrap().value(), "access-2");
+        assert_eq!(loaded.refresh.unwrap().expose_secret(), "refresh-2");
+        std::fs::remove_file(&store.path).unwrap();
+    }
+}
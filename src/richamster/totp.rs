@@ -0,0 +1,159 @@
+use crate::errors::RichamsterError;
+use base32::Alphabet;
+use hmac_sha1_compact::HMAC;
+use secrecy::{ExposeSecret, SecretBox};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const DEFAULT_STEP: Duration = Duration::from_secs(30);
+const DEFAULT_DIGITS: u32 = 6;
+
+/// RFC 6238 one-time password generator (HMAC-SHA1), for logging in accounts
+/// with two-factor authentication without a human typing the code, see
+/// [`PendingTwoFactor::complete_with_totp`](crate::richamster::login::PendingTwoFactor::complete_with_totp).
+#[derive(Debug)]
+pub struct Totp {
+    key: SecretBox<Vec<u8>>,
+    step: Duration,
+    digits: u32,
+    skew: u32,
+}
+
+impl Totp {
+    /// `secret` is the base32 shared secret shown when 2FA is enabled. Spaces,
+    /// padding and lowercase letters are accepted.
+    pub fn new(secret: SecretBox<String>) -> Result<Self, RichamsterError> {
+        let normalized: String = secret
+            .expose_secret()
+            .chars()
+            .filter(|c| !c.is_whitespace() && *c != '=')
+            .map(|c| c.to_ascii_uppercase())
+            .collect();
+        let key = base32::decode(Alphabet::Rfc4648 { padding: false }, &normalized)
+            .filter(|key| !key.is_empty())
+            .ok_or(RichamsterError::InvalidTotpSecret)?;
+        Ok(Self {
+            key: SecretBox::new(Box::new(key)),
+            step: DEFAULT_STEP,
+            digits: DEFAULT_DIGITS,
+            skew: 1,
+        })
+    }
+
+    /// Validity period of a code, 30 seconds by default.
+    pub fn step(mut self, step: Duration) -> Self {
+        self.step = step.max(Duration::from_secs(1));
+        self
+    }
+
+    /// Code length, 6 by default.
+    pub fn digits(mut self, digits: u32) -> Self {
+        self.digits = digits.clamp(1, 9);
+        self
+    }
+
+    /// Number of adjacent periods on each side tried after the current one, 1
+    /// by default.
+    pub fn skew(mut self, skew: u32) -> Self {
+        self.skew = skew;
+        self
+    }
+
+    pub fn code(&self) -> String {
+        self.code_at(SystemTime::now())
+    }
+
+    pub fn code_at(&self, time: SystemTime) -> String {
+        self.code_for(self.counter(time))
+    }
+
+    /// Code of the period containing `time`, then of the previous and the next
+    /// periods up to the skew, so a code is still found when the local clock
+    /// is off or the period ends while the request is in flight.
+    pub fn candidates_at(&self, time: SystemTime) -> Vec<String> {
+        let counter = self.counter(time);
+        let mut codes = vec![self.code_for(counter)];
+        for offset in 1..=u64::from(self.skew) {
+            if let Some(previous) = counter.checked_sub(offset) {
+                codes.push(self.code_for(previous));
+            }
+            codes.push(self.code_for(counter + offset));
+        }
+        codes
+    }
+
+    fn counter(&self, time: SystemTime) -> u64 {
+        let elapsed = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+        elapsed.as_secs() / self.step.as_secs()
+    }
+
+    fn code_for(&self, counter: u64) -> String {
+        let mac = HMAC::mac(&counter.to_be_bytes(), self.key.expose_secret());
+        let offset = usize::from(mac[19] & 0x0f);
+        let binary = u32::from_be_bytes([
+            mac[offset],
+            mac[offset + 1],
+            mac[offset + 2],
+            mac[offset + 3],
+        ]) & 0x7fff_ffff;
+        let code = binary % 10u32.pow(self.digits);
+        format!("{code:0width$}", width = self.digits as usize)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    // RFC 6238 appendix B, SHA1 secret "12345678901234567890".
+    const RFC_SECRET: &str = "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ";
+
+    fn totp() -> Totp {
+        Totp::new(SecretBox::new(Box::new(RFC_SECRET.to_owned())))
+            .unwrap()
+            .digits(8)
+    }
+
+    #[test]
+    fn codes_match_rfc_test_vectors() {
+        let vectors = [
+            (59, "94287082"),
+            (1_111_111_109, "07081804"),
+            (1_234_567_890, "89005924"),
+            (2_000_000_000, "69279037"),
+        ];
+        for (secs, code) in vectors {
+            assert_eq!(totp().code_at(UNIX_EPOCH + Duration::from_secs(secs)), code);
+        }
+    }
+
+    #[test]
+    fn adjacent_periods_are_candidates() {
+        let totp = totp();
+        let at = UNIX_EPOCH + Duration::from_secs(1_111_111_109);
+        let step = Duration::from_secs(30);
+        assert_eq!(
+            totp.candidates_at(at),
+            vec![
+                totp.code_at(at),
+                totp.code_at(at - step),
+                totp.code_at(at + step)
+            ]
+        );
+    }
+
+    #[test]
+    fn secret_is_normalized_and_validated() {
+        let spaced = Totp::new(SecretBox::new(Box::new(
+            "gezd gnbv gy3t qojq gezd gnbv gy3t qojq".to_owned(),
+        )))
+        .unwrap();
+        assert_eq!(
+            spaced.code_at(UNIX_EPOCH + Duration::from_secs(59)),
+            "287082"
+        );
+        assert!(matches!(
+            Totp::new(SecretBox::new(Box::new("not base32!".to_owned()))),
+            Err(RichamsterError::InvalidTotpSecret)
+        ));
+    }
+}
@@ -1,18 +1,25 @@
+use crate::api::Api;
 use crate::api::UserApi::{Balances, Detail, Orders, Transactions, Transfer};
-use crate::api::{Api, RequestPath};
+use crate::api::client::RichamsterClient;
+use crate::api::permissions::Permissions;
 use crate::api::{RequestData, token};
 use crate::errors::RichamsterError;
 use crate::models::user::{
     TransactionsFilter, TransferQuery, UserBalance, UserDetail, UserOrderResponse,
     UserOrdersFilter, UserTransactionResponce,
 };
-use crate::richamster::common::{ApiKey, AuthState, HeaderCompose, JwtToken, SecretKey};
+use crate::richamster::common::{ApiKey, AuthState, JwtToken, SecretKey, SharedAuthState};
+use crate::richamster::credentials::CredentialProvider;
+use crate::richamster::session::Session;
+use crate::richamster::signer::Signer;
 use crate::{process_response, send_request};
 use reqwest::StatusCode;
+use std::sync::Arc;
 
-#[derive(Default)]
+#[derive(Clone, Debug, Default)]
 pub struct User {
-    auth_state: AuthState,
+    client: RichamsterClient,
+    auth_state: SharedAuthState,
 }
 
 impl User {
@@ -22,28 +29,80 @@ impl User {
 
     pub fn with_jwt_token(token: String) -> Self {
         Self {
-            auth_state: AuthState::JwtTokenAuth(JwtToken::new(token)),
+            client: RichamsterClient::default(),
+            auth_state: SharedAuthState::new(AuthState::JwtTokenAuth(JwtToken::new(token))),
         }
     }
 
     pub fn with_keys(api_key: String, secret_key: String) -> Self {
         Self {
-            auth_state: AuthState::ApiSecretKeyAuth(
+            client: RichamsterClient::default(),
+            auth_state: SharedAuthState::new(AuthState::ApiSecretKeyAuth(
                 ApiKey::new(api_key),
                 SecretKey::new(secret_key),
-            ),
+            )),
         }
     }
 
     pub fn with_jwt_and_keys(jwt: String, api_key: String, secret_key: String) -> Self {
         Self {
-            auth_state: AuthState::JwtTokenWithApiSecretKeyAuth(
+            client: RichamsterClient::default(),
+            auth_state: SharedAuthState::new(AuthState::JwtTokenWithApiSecretKeyAuth(
                 JwtToken::new(jwt),
                 ApiKey::new(api_key),
                 SecretKey::new(secret_key),
-            ),
+            )),
         }
     }
+
+    /// Authenticates with the JWT of `session`, refreshed as needed.
+    pub fn with_session(session: Session) -> Self {
+        Self {
+            client: RichamsterClient::default(),
+            auth_state: SharedAuthState::new(AuthState::Session(session)),
+        }
+    }
+
+    /// Signs requests with `signer` instead of a secret key held in memory.
+    pub fn with_signer(api_key: String, signer: Arc<dyn Signer>) -> Self {
+        Self {
+            client: RichamsterClient::default(),
+            auth_state: SharedAuthState::new(AuthState::ApiKeySignerAuth(
+                ApiKey::new(api_key),
+                signer,
+            )),
+        }
+    }
+
+    /// Authenticates with the credentials loaded from `provider`.
+    pub fn with_credentials(provider: &impl CredentialProvider) -> Result<Self, RichamsterError> {
+        Ok(Self {
+            client: RichamsterClient::default(),
+            auth_state: SharedAuthState::new(provider.auth_state()?),
+        })
+    }
+
+    /// Service authenticating with `auth_state`, shared with other services.
+    pub(crate) fn shared(client: RichamsterClient, auth_state: SharedAuthState) -> Self {
+        Self { client, auth_state }
+    }
+
+    /// Credentials of this service and its clones, see [`SharedAuthState::replace`].
+    pub fn auth_state(&self) -> &SharedAuthState {
+        &self.auth_state
+    }
+
+    /// Limits this handle to `permissions`, see [`Permissions`]. Capabilities
+    /// can only be removed, by this call as by any later one.
+    pub fn restrict(mut self, permissions: Permissions) -> Self {
+        self.auth_state = self.auth_state.restrict(permissions);
+        self
+    }
+
+    pub fn client(mut self, client: RichamsterClient) -> Self {
+        self.client = client;
+        self
+    }
 }
 
 impl User {
@@ -51,18 +110,20 @@ impl User {
         &self,
         currency: Option<token::Token>,
     ) -> Result<Vec<UserBalance>, RichamsterError> {
-        let RequestData(mut url, method) = Api::User(Balances).request_data();
+        let api = Api::User(Balances);
+        let RequestData(mut url, method) = self.client.request_data(&api);
         if let Some(token) = currency {
             url.query_pairs_mut()
                 .append_pair("currency", token.as_ref());
         }
-        let resp = send_request!(url, method, self.auth_state);
+        let resp = send_request!(self.client, api, url, method, self.auth_state);
         process_response!(resp, Vec<UserBalance>)
     }
 
     pub async fn detail_info(&self) -> Result<UserDetail, RichamsterError> {
-        let RequestData(url, method) = Api::User(Detail).request_data();
-        let resp = send_request!(url, method, self.auth_state);
+        let api = Api::User(Detail);
+        let RequestData(url, method) = self.client.request_data(&api);
+        let resp = send_request!(self.client, api, url, method, self.auth_state);
         process_response!(resp, UserDetail)
     }
 
@@ -70,9 +131,10 @@ impl User {
         &self,
         parameters: TransactionsFilter,
     ) -> Result<UserTransactionResponce, RichamsterError> {
-        let RequestData(mut url, method) = Api::User(Transactions).request_data();
-        let url = parameters.compose_url(&mut url);
-        let resp = send_request!(url, method, self.auth_state);
+        let api = Api::User(Transactions);
+        let RequestData(mut url, method) = self.client.request_data(&api);
+        parameters.compose_url(&mut url);
+        let resp = send_request!(self.client, api, url, method, self.auth_state);
         process_response!(resp, UserTransactionResponce)
     }
 
@@ -80,17 +142,18 @@ impl User {
         &self,
         parameters: UserOrdersFilter,
     ) -> Result<UserOrderResponse, RichamsterError> {
-        let RequestData(mut url, method) = Api::User(Orders).request_data();
-        let url = parameters.compose_url(&mut url);
-        let resp = send_request!(url, method, self.auth_state);
-        let string = resp.text().await?;
-        Ok(serde_json::from_str(&string)?)
+        let api = Api::User(Orders);
+        let RequestData(mut url, method) = self.client.request_data(&api);
+        parameters.compose_url(&mut url);
+        let resp = send_request!(self.client, api, url, method, self.auth_state);
+        Ok(resp.json()?)
     }
 
     pub async fn transfer(&self, transfer_query: TransferQuery) -> Result<(), RichamsterError> {
-        let RequestData(url, method) = Api::User(Transfer).request_data();
+        let api = Api::User(Transfer);
+        let RequestData(url, method) = self.client.request_data(&api);
         let payload = serde_json::to_string(&transfer_query)?;
-        send_request!(url, method, self.auth_state, payload);
+        send_request!(self.client, api, url, method, self.auth_state, payload);
         Ok(())
     }
 }
@@ -98,13 +161,98 @@ impl User {
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::api::client::ClientConfig;
+    use crate::api::permissions::{Capability, Permissions};
+    use crate::api::transport::{InMemoryTransport, TransportResponse};
+    use crate::models::auth::TokenData;
+    use reqwest::Method;
+    use std::sync::Arc;
 
     #[test]
     fn create_default_user() {
         let user: User = Default::default();
-        match user.auth_state {
-            AuthState::Unauthorized => assert!(true),
-            _ => assert!(false),
-        }
+        assert!(matches!(
+            *user.auth_state.current(),
+            AuthState::Unauthorized
+        ));
+    }
+
+    #[tokio::test]
+    async fn balances_request_is_authorized_with_jwt() {
+        let transport = Arc::new(InMemoryTransport::new());
+        transport.respond(
+            Method::GET,
+            "user/balances/",
+            TransportResponse::new(StatusCode::OK, "[]"),
+        );
+        let client = ClientConfig::new()
+            .transport(transport.clone())
+            .build()
+            .unwrap();
+        let user = User::with_jwt_token("token".to_owned()).client(client);
+        let balances = user.balances(Some(token::Token::BTC)).await.unwrap();
+        assert!(balances.is_empty());
+        let request = transport.last_request().unwrap();
+        assert_eq!(request.method, Method::GET);
+        assert_eq!(
+            request.url.as_str(),
+            "https://richamster.com/public/v1/user/balances/?currency=BTC"
+        );
+        assert_eq!(request.header_value("Authorization"), Some("JWT token"));
+        assert_eq!(request.body, None);
+    }
+
+    #[tokio::test]
+    async fn unauthorized_balances_request() {
+        let transport = Arc::new(InMemoryTransport::new());
+        transport.respond(
+            Method::GET,
+            "user/balances/",
+            TransportResponse::new(StatusCode::UNAUTHORIZED, ""),
+        );
+        let client = ClientConfig::new()
+            .transport(transport.clone())
+            .build()
+            .unwrap();
+        let user = User::with_jwt_token("expired".to_owned()).client(client);
+        assert!(matches!(
+            user.balances(None).await,
+            Err(RichamsterError::UnauthorizedAccess)
+        ));
+    }
+
+    #[tokio::test]
+    async fn read_only_session_rejects_transfer_without_sending() {
+        let transport = Arc::new(InMemoryTransport::new());
+        transport.respond(
+            Method::GET,
+            "user/balances/",
+            TransportResponse::new(StatusCode::OK, "[]"),
+        );
+        let client = ClientConfig::new()
+            .transport(transport.clone())
+            .build()
+            .unwrap();
+        let user = User::with_session(Session::new(TokenData {
+            access: "access".to_owned(),
+            refresh: "refresh".to_owned(),
+        }))
+        .restrict(Permissions::read_only())
+        .client(client);
+        let transfer = TransferQuery {
+            amount: "1".to_owned(),
+            currency: token::Token::BTC,
+            to: "someone".to_owned(),
+            pin_code: "0000".to_owned(),
+        };
+        assert!(matches!(
+            user.transfer(transfer).await,
+            Err(RichamsterError::PermissionDenied(
+                Api::User(Transfer),
+                Capability::Wallet
+            ))
+        ));
+        assert!(transport.requests().is_empty());
+        user.balances(None).await.unwrap();
     }
 }
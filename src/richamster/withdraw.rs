@@ -1,18 +1,24 @@
+use crate::api::client::RichamsterClient;
+use crate::api::permissions::Permissions;
 use crate::api::token::Token;
-use crate::api::{Api, RequestData, RequestPath, WithdrawApi};
+use crate::api::{Api, RequestData, WithdrawApi};
 use crate::errors::RichamsterError;
 use crate::models::withdraw::{
     WithdrawData, WithdrawDetailError, WithdrawError, WithdrawFieldError, WithdrawInfoResponse,
     WithdrawResponse,
 };
-use crate::richamster::common::HeaderCompose;
-use crate::richamster::common::{ApiKey, AuthState, JwtToken, SecretKey};
+use crate::richamster::common::{ApiKey, AuthState, JwtToken, SecretKey, SharedAuthState};
+use crate::richamster::credentials::CredentialProvider;
+use crate::richamster::session::Session;
+use crate::richamster::signer::Signer;
 use crate::send_request;
 use reqwest::StatusCode;
+use std::sync::Arc;
 
-#[derive(Default)]
+#[derive(Clone, Debug, Default)]
 pub struct Withdraw {
-    auth_state: AuthState,
+    client: RichamsterClient,
+    auth_state: SharedAuthState,
 }
 
 impl Withdraw {
@@ -22,18 +28,80 @@ impl Withdraw {
 
     pub fn with_jwt_token(token: String) -> Self {
         Self {
-            auth_state: AuthState::JwtTokenAuth(JwtToken::new(token)),
+            client: RichamsterClient::default(),
+            auth_state: SharedAuthState::new(AuthState::JwtTokenAuth(JwtToken::new(token))),
         }
     }
 
     pub fn with_keys(api_key: String, secret_key: String) -> Self {
         Self {
-            auth_state: AuthState::ApiSecretKeyAuth(
+            client: RichamsterClient::default(),
+            auth_state: SharedAuthState::new(AuthState::ApiSecretKeyAuth(
                 ApiKey::new(api_key),
                 SecretKey::new(secret_key),
-            ),
+            )),
+        }
+    }
+
+    pub fn with_jwt_and_keys(jwt: String, api_key: String, secret_key: String) -> Self {
+        Self {
+            client: RichamsterClient::default(),
+            auth_state: SharedAuthState::new(AuthState::JwtTokenWithApiSecretKeyAuth(
+                JwtToken::new(jwt),
+                ApiKey::new(api_key),
+                SecretKey::new(secret_key),
+            )),
         }
     }
+
+    /// Authenticates with the JWT of `session`, refreshed as needed.
+    pub fn with_session(session: Session) -> Self {
+        Self {
+            client: RichamsterClient::default(),
+            auth_state: SharedAuthState::new(AuthState::Session(session)),
+        }
+    }
+
+    /// Signs requests with `signer` instead of a secret key held in memory.
+    pub fn with_signer(api_key: String, signer: Arc<dyn Signer>) -> Self {
+        Self {
+            client: RichamsterClient::default(),
+            auth_state: SharedAuthState::new(AuthState::ApiKeySignerAuth(
+                ApiKey::new(api_key),
+                signer,
+            )),
+        }
+    }
+
+    /// Authenticates with the credentials loaded from `provider`.
+    pub fn with_credentials(provider: &impl CredentialProvider) -> Result<Self, RichamsterError> {
+        Ok(Self {
+            client: RichamsterClient::default(),
+            auth_state: SharedAuthState::new(provider.auth_state()?),
+        })
+    }
+
+    /// Service authenticating with `auth_state`, shared with other services.
+    pub(crate) fn shared(client: RichamsterClient, auth_state: SharedAuthState) -> Self {
+        Self { client, auth_state }
+    }
+
+    /// Credentials of this service and its clones, see [`SharedAuthState::replace`].
+    pub fn auth_state(&self) -> &SharedAuthState {
+        &self.auth_state
+    }
+
+    /// Limits this handle to `permissions`, see [`Permissions`]. Capabilities
+    /// can only be removed, by this call as by any later one.
+    pub fn restrict(mut self, permissions: Permissions) -> Self {
+        self.auth_state = self.auth_state.restrict(permissions);
+        self
+    }
+
+    pub fn client(mut self, client: RichamsterClient) -> Self {
+        self.client = client;
+        self
+    }
 }
 
 impl Withdraw {
@@ -41,20 +109,20 @@ impl Withdraw {
         &self,
         token: Token,
     ) -> Result<WithdrawInfoResponse, RichamsterError> {
-        let RequestData(mut url, method) = Api::Withdraw(WithdrawApi::WithdrawInfo).request_data();
+        let api = Api::Withdraw(WithdrawApi::WithdrawInfo);
+        let RequestData(mut url, method) = self.client.request_data(&api);
         url = url.join(token.as_ref())?;
 
-        let resp = send_request!(url, method, self.auth_state);
+        let resp = send_request!(self.client, api, url, method, self.auth_state);
         match resp.status() {
             StatusCode::OK => {
-                let string = resp.text().await?;
-                let info: WithdrawInfoResponse = serde_json::from_str(&string)?;
+                let info: WithdrawInfoResponse = resp.json()?;
                 Ok(info)
             }
             StatusCode::UNAUTHORIZED => Err(RichamsterError::UnauthorizedAccess),
             status => Err(RichamsterError::UnsupportedResponseCode(
                 status,
-                resp.text().await?,
+                resp.text().to_owned(),
             )),
         }
     }
@@ -63,10 +131,13 @@ impl Withdraw {
         token: Token,
         withdraw: WithdrawData,
     ) -> Result<WithdrawResponse, RichamsterError> {
-        let RequestData(mut url, method) = Api::Withdraw(WithdrawApi::Withdraw).request_data();
+        let api = Api::Withdraw(WithdrawApi::Withdraw);
+        let RequestData(mut url, method) = self.client.request_data(&api);
         url = url.join(token.as_ref())?;
 
         let resp = send_request!(
+            self.client,
+            api,
             url,
             method,
             self.auth_state,
@@ -74,25 +145,25 @@ impl Withdraw {
         );
         match resp.status() {
             StatusCode::OK => {
-                let string = resp.text().await?;
-                let withdraw: WithdrawResponse = serde_json::from_str(&string)?;
+                let withdraw: WithdrawResponse = resp.json()?;
                 Ok(withdraw)
             }
             StatusCode::BAD_REQUEST => {
-                let error: WithdrawFieldError = serde_json::from_str(resp.text().await?.as_str())?;
-                Err(RichamsterError::WithdrawError(WithdrawError::Fields(error)))
+                let error: WithdrawFieldError = resp.json()?;
+                Err(RichamsterError::WithdrawError(Box::new(
+                    WithdrawError::Fields(error),
+                )))
             }
             StatusCode::UNAUTHORIZED => Err(RichamsterError::UnauthorizedAccess),
             StatusCode::FORBIDDEN => {
-                let detail: WithdrawDetailError =
-                    serde_json::from_str(resp.text().await?.as_str())?;
-                Err(RichamsterError::WithdrawError(WithdrawError::Detail(
-                    detail,
+                let detail: WithdrawDetailError = resp.json()?;
+                Err(RichamsterError::WithdrawError(Box::new(
+                    WithdrawError::Detail(detail),
                 )))
             }
             status => Err(RichamsterError::UnsupportedResponseCode(
                 status,
-                resp.text().await?,
+                resp.text().to_owned(),
             )),
         }
     }